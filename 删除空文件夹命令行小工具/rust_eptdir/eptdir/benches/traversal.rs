@@ -0,0 +1,147 @@
+// ========================================
+// 遍历的基准测试：比较文件系统调用次数
+// ========================================
+// 运行方法：cargo bench --bench traversal
+// 可以用参数调整合成目录树的大小：cargo bench --bench traversal -- <每层子目录数> <层数>
+//
+// 生成两棵相同的合成目录树，分别用两种方法清理：
+//   - 两次遍历（旧）：先遍历一次删除垃圾文件，再遍历一次删除空文件夹，
+//     删除每个目录之前还要再读取一次确认为空（这是 clean_directory 以前的做法，这里按原样重写）
+//   - 单次遍历（新）：clean_directory()，一次后序遍历同时完成两件事
+// 然后打印两者的 read_dir / stat / remove 次数和耗时
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use eptdir::{clean_directory, CleanOptions, JunkRules, SyscallCounts};
+
+fn main() {
+    // cargo bench 会传入 --bench 参数，只取数字参数
+    let numbers: Vec<usize> = env::args().skip(1).filter_map(|arg| arg.parse().ok()).collect();
+    let width = numbers.first().copied().unwrap_or(8);
+    let depth = numbers.get(1).copied().unwrap_or(4);
+
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let base = env::temp_dir().join(format!("eptdir_bench_{}", stamp));
+    let rules = JunkRules::default();
+
+    println!("合成目录树: 每层 {} 个子目录，共 {} 层", width, depth);
+    println!("{:<16}{:>10}{:>10}{:>10}{:>12}", "", "read_dir", "stat", "remove", "耗时");
+
+    let mut results = Vec::new();
+    for (name, threads) in [("两次遍历（旧）", 0), ("单次遍历（新）", 1), ("单次遍历 4 线程", 4)] {
+        let root = base.join(format!("tree{}", threads));
+        let (dirs, files) = build_tree(&root, width, depth);
+        if threads == 0 {
+            println!("（{} 个目录，{} 个文件）", dirs, files);
+        }
+
+        let start = Instant::now();
+        let (counts, removed) = if threads == 0 {
+            two_pass(&root, &rules)
+        } else {
+            let options = CleanOptions { threads, ..Default::default() };
+            let report = clean_directory(&root, &rules, &options).expect("清理失败");
+            (report.syscalls, report.removed_junk_files.len() + report.removed_dirs.len())
+        };
+        print_row(name, &counts, start.elapsed());
+        results.push(removed);
+    }
+
+    // 三种方法删除的条目数量必须相同，否则比较没有意义
+    assert!(results.windows(2).all(|pair| pair[0] == pair[1]), "删除的条目数量不同: {:?}", results);
+    fs::remove_dir_all(&base).ok();
+}
+
+fn print_row(name: &str, counts: &SyscallCounts, elapsed: Duration) {
+    println!(
+        "{:<16}{:>10}{:>10}{:>10}{:>10.1}ms",
+        name,
+        counts.read_dir,
+        counts.stat,
+        counts.remove,
+        elapsed.as_secs_f64() * 1000.0
+    );
+}
+
+// 生成合成目录树，返回 (目录数, 文件数)
+// 每个目录里有一个 thumbs.db；名字以 0 结尾的目录还有一个普通文件，会和它的所有上级目录一起保留
+fn build_tree(dir: &Path, width: usize, depth: usize) -> (usize, usize) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("thumbs.db"), b"junk").unwrap();
+    let mut dirs = 1;
+    let mut files = 1;
+    if dir.file_name().is_some_and(|name| name.to_string_lossy().ends_with('0')) {
+        fs::write(dir.join("keep.txt"), b"keep").unwrap();
+        files += 1;
+    }
+    if depth > 0 {
+        for i in 0..width {
+            let (d, f) = build_tree(&dir.join(format!("d{}", i)), width, depth - 1);
+            dirs += d;
+            files += f;
+        }
+    }
+    (dirs, files)
+}
+
+// ========================================
+// 两次遍历的旧算法（只保留和计数有关的部分：没有符号链接、排除规则、日志）
+// ========================================
+// 返回 (系统调用次数, 删除的条目数量)
+fn two_pass(root: &Path, rules: &JunkRules) -> (SyscallCounts, usize) {
+    let mut counts = SyscallCounts::default();
+    // 旧版本用 exists() 和 is_dir() 检查目标目录
+    counts.stat += 2;
+    let mut removed = remove_junk(root, rules, &mut counts);
+    removed += remove_empty_subdirs(root, &mut counts);
+    (counts, removed)
+}
+
+fn remove_junk(dir: &Path, rules: &JunkRules, counts: &mut SyscallCounts) -> usize {
+    let mut removed = 0;
+    counts.read_dir += 1;
+    for entry in fs::read_dir(dir).unwrap() {
+        let entry = entry.unwrap();
+        let file_type = entry.file_type().unwrap();
+        if file_type.is_file() && rules.is_junk_os(&entry.file_name()) {
+            // 读取大小，然后删除
+            counts.stat += 1;
+            entry.metadata().unwrap();
+            counts.remove += 1;
+            fs::remove_file(entry.path()).unwrap();
+            removed += 1;
+        } else if file_type.is_dir() {
+            removed += remove_junk(&entry.path(), rules, counts);
+        }
+    }
+    removed
+}
+
+// 返回删除的目录数量
+// 旧算法实际删除时不看计数，总是重新读取目录确认是否为空
+fn remove_empty_subdirs(dir: &Path, counts: &mut SyscallCounts) -> usize {
+    let mut removed = 0;
+    let mut subdirs: Vec<PathBuf> = Vec::new();
+    counts.read_dir += 1;
+    for entry in fs::read_dir(dir).unwrap() {
+        let entry = entry.unwrap();
+        if entry.file_type().unwrap().is_dir() {
+            subdirs.push(entry.path());
+        }
+    }
+    for subdir in subdirs {
+        removed += remove_empty_subdirs(&subdir, counts);
+        // 删除之前再读取一次确认为空，再读取元数据（写日志用），然后删除
+        counts.read_dir += 1;
+        if fs::read_dir(&subdir).unwrap().next().is_none() {
+            counts.stat += 1;
+            fs::symlink_metadata(&subdir).unwrap();
+            counts.remove += 1;
+            fs::remove_dir(&subdir).unwrap();
+            removed += 1;
+        }
+    }
+    removed
+}
@@ -0,0 +1,633 @@
+// ========================================
+// 命令行参数解析
+// ========================================
+// 支持的写法：
+//   --max-depth 3     选项和值分开
+//   --max-depth=3     选项和值用 = 连接
+//   --                之后的参数全部当作路径，即使以 - 开头
+use std::ffi::OsString;
+use std::fmt;
+use std::path::PathBuf;
+
+use eptdir::{journal, parse_duration, rules, AgeTime, CleanOptions, JunkRules, Quarantine, SymlinkPolicy};
+
+// 用法错误的退出码（和"清理过程中出错"的退出码 1 区分开）
+pub const EXIT_USAGE: i32 = 2;
+
+pub const HELP: &str = "\
+eptdir - 删除垃圾文件和空文件夹
+
+用法:
+    eptdir [选项] [目录...]
+    eptdir restore [选项] <隔离目录> [原目录]
+    eptdir undo [选项] <运行ID>
+
+不指定目录时清理当前目录。
+
+选项:
+    -h, --help              显示帮助信息
+    -V, --version           显示版本号
+    -n, --dry-run           预览模式：只列出将要删除的内容，不修改磁盘
+    -q, --quiet             只输出错误
+    -v, --verbose           输出更详细的信息（使用的规则、跳过的条目等）
+        --json              所有目录处理完以后，在标准输出写一个 JSON 文档（格式版本 schema_version 1）：
+                            目标、删除的文件和目录、错误、数量和退出码；其他文字都写到标准错误
+        --events            清理过程中实时输出事件，每行一个 JSON 对象（NDJSON）：进入目录、删除文件、
+                            删除目录、错误、开始和结束一个目标；不能和 --json 同时使用
+        --junk <规则>       额外的垃圾文件规则，可以多次使用
+                            例如 *.tmp、name:desktop.ini、regex:^~\\$.*\\.docx$
+                            前面可以加匹配模式：nocase:（忽略 ASCII 大小写）、casefold:（Unicode
+                            大小写折叠）、nfc:（忽略 NFC/NFD 差异），例如 nocase:*.TMP；
+                            regex: 规则只能加 nocase:
+        --no-default-junk   不使用内置的 thumbs.db / .DS_Store 规则
+        --empty-files       同时删除 0 字节的文件（__init__.py、py.typed、.gitkeep 等为空是正常的，不删除）
+        --keep-empty <规则> 不删除的 0 字节文件名（通配符，例如 *.lock），可以多次使用
+        --config <文件>     从配置文件读取规则
+        --exclude <规则>    不处理匹配的文件和目录（gitignore 风格，相对于目标目录），可以多次使用
+                            例如 .git、node_modules/、/build、logs/**；排除的目录会列在报告中
+        --include <规则>    重新包含被 --exclude 或配置文件排除的条目，可以多次使用
+        --keep-marker <名字> 额外的保留标记文件名，可以多次使用（内置 .gitkeep、.keep、.eptdir-keep）
+                            有保留标记的目录不会被删除，其中的垃圾文件照常删除；
+                            .eptdirignore 中列出的目录（gitignore 风格，相对于它所在的目录）同样保留
+        --no-keep-markers   不使用保留标记和 .eptdirignore
+        --max-depth <层数>  最多进入多少层子目录（目标目录中的条目为第 1 层）
+        --symlinks <策略>   符号链接的处理方式：skip（默认）、report、follow
+        --min-age <时长>    只删除比这更旧的文件和目录，单位 s/m/h/d/w，例如 2h、7d
+        --age-time <时间>   --min-age 比较的时间：mtime（默认，修改时间）、ctime、atime
+        --remove-root       目标目录清理后为空时也删除它（默认保留）
+        --save-plan <文件>  不删除任何内容，把将要删除的条目保存成清理计划（JSON），只能指定一个目录；
+                            不能和 --json、--events 同时使用
+        --plan <文件>       执行保存的清理计划，而不是遍历目录：计划以后有变化的条目会被跳过
+        -i, --interactive   删除前逐个确认：一个目录和其中所有将被删除的内容（一整串嵌套的空文件夹）
+                            只问一次，回答 y（是）、n（否，默认）、a（这一个和以后全部）、q（退出）；
+                            问题写到标准错误，标准输入必须是终端；不能和 --save-plan 同时使用
+        --trash             移动到当前用户的回收站（freedesktop.org 格式），而不是永久删除
+                            目标目录在另一个文件系统上时，改用那个分区上的回收站（.Trash-$uid）
+        --quarantine <目录> 移动到隔离目录中带时间戳的子目录，保留原来的相对路径
+        --journal <文件>    撤销日志文件（默认 ~/.local/state/eptdir/journal.jsonl）
+        --no-journal        不写撤销日志
+        --fail-fast         遇到第一个错误就停止（默认记录错误并继续）
+        --threads <N>       并行遍历的线程数（默认 1）；网络存储上的大目录树可以设成 CPU 核数或更多
+        --                  之后的参数都当作目录，即使以 - 开头
+
+    文件名不是有效的 UTF-8 或包含控制字符时，输出中写成 shell 的 $'...' 形式，
+    例如 $'\\xff\\xfe.tmp'；JSON 中写成 {\"escaped\": \"$'...'\", \"hex\": \"原始字节的十六进制\"}。
+
+restore 子命令:
+    把 --trash 或 --quarantine 移走的内容放回原位置。
+    <隔离目录> 可以是一次运行的时间戳子目录、整个隔离目录，或者回收站目录。
+    指定 [原目录] 时只恢复原来位于这个目录下的条目。
+    恢复回收站时只放回 eptdir 移进去的条目，其他程序删除的文件留在回收站中。
+    原位置已经有同名文件的条目不会被覆盖，会报告为错误。
+    -h, --help              显示帮助信息
+    -q, --quiet             只输出错误
+
+undo 子命令:
+    按撤销日志撤销一次清理：重新创建被删除的空文件夹，并恢复它们的权限和修改时间。
+    使用 --trash 或 --quarantine 移走的文件也会被放回；永久删除的文件无法恢复。
+    运行 ID 显示在每次清理的报告中，也可以在日志文件中查到。
+    -h, --help              显示帮助信息
+    -q, --quiet             只输出错误
+        --journal <文件>    撤销日志文件（默认 ~/.local/state/eptdir/journal.jsonl）
+
+退出码:
+    0   清理成功
+    1   有目录或条目清理失败
+    2   命令行用法错误
+";
+
+// 输出的详细程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+// 一次清理运行需要的全部参数
+#[derive(Debug)]
+pub struct Cli {
+    pub options: CleanOptions,
+    pub junk_rules: JunkRules,
+    // 要清理的目录；为空时清理当前目录
+    pub paths: Vec<PathBuf>,
+    pub verbosity: Verbosity,
+    pub json: bool,
+    // --events：实时输出 NDJSON 事件
+    pub events: bool,
+    // --save-plan：只制定清理计划，保存到这个文件
+    pub save_plan: Option<PathBuf>,
+    // --plan：执行这个文件中的清理计划
+    pub plan: Option<PathBuf>,
+    // --interactive：删除前逐个确认
+    pub interactive: bool,
+}
+
+// restore 子命令的参数
+#[derive(Debug)]
+pub struct RestoreArgs {
+    // 隔离目录或回收站
+    pub source: PathBuf,
+    // 只恢复原来位于这个目录下的条目
+    pub only_under: Option<PathBuf>,
+    pub verbosity: Verbosity,
+}
+
+// undo 子命令的参数
+#[derive(Debug)]
+pub struct UndoArgs {
+    pub run_id: String,
+    pub journal: PathBuf,
+    pub verbosity: Verbosity,
+}
+
+// 解析结果：显示帮助、显示版本、执行清理、恢复隔离的内容，或者撤销一次清理
+#[derive(Debug)]
+// Cli 比其他变体大很多，放在 Box 中
+pub enum Command {
+    Help,
+    Version,
+    Run(Box<Cli>),
+    Restore(RestoreArgs),
+    Undo(UndoArgs),
+}
+
+// 用法错误，main 打印后以 EXIT_USAGE 退出
+#[derive(Debug)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// 解析命令行参数（不包含程序名）
+// 参数使用 OsString，这样不是有效 UTF-8 的路径也能传进来
+pub fn parse_args<I>(args: I) -> Result<Command, UsageError>
+where
+    I: IntoIterator<Item = OsString>,
+{
+    // peekable() 可以先看一眼第一个参数，判断是不是子命令
+    let mut args = args.into_iter().peekable();
+    if args.peek().is_some_and(|arg| arg == "restore") {
+        args.next();
+        return parse_restore(args);
+    }
+    if args.peek().is_some_and(|arg| arg == "undo") {
+        args.next();
+        return parse_undo(args);
+    }
+
+    // 命令行默认遇到错误继续处理其余条目，最后统一汇总；--fail-fast 表示遇到第一个错误就停止
+    // 默认写撤销日志
+    let mut options =
+        CleanOptions { continue_on_error: true, journal: journal::default_path(), ..Default::default() };
+    let mut verbosity = Verbosity::Normal;
+    let mut json = false;
+    let mut events = false;
+    let mut save_plan: Option<PathBuf> = None;
+    let mut plan: Option<PathBuf> = None;
+    let mut interactive = false;
+    // 命令行里的 --junk 规则和 --config 配置文件，先收集，最后统一生成规则集合
+    let mut junk_specs: Vec<String> = Vec::new();
+    let mut config_files: Vec<PathBuf> = Vec::new();
+    // 命令行里的排除和包含规则排在配置文件的规则后面，优先级更高
+    let mut excludes: Vec<String> = Vec::new();
+    let mut includes: Vec<String> = Vec::new();
+    let mut use_default_junk = true;
+    let mut paths: Vec<PathBuf> = Vec::new();
+    // 遇到 -- 之后不再解析选项
+    let mut options_done = false;
+
+    // --junk 这类选项需要用 next() 再取一个值
+    let mut iter = args;
+    while let Some(arg) = iter.next() {
+        // 选项必须是有效的 UTF-8；不是的话只可能是路径
+        let text = match arg.to_str() {
+            Some(text) if !options_done && text.starts_with('-') && text != "-" => text.to_string(),
+            _ => {
+                paths.push(PathBuf::from(arg));
+                continue;
+            }
+        };
+
+        // --name=value 拆成选项名和值
+        let (name, inline_value) = match text.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+            _ => (text.clone(), None),
+        };
+
+        match name.as_str() {
+            "--" => options_done = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-n" | "--dry-run" => options.dry_run = true,
+            "-q" | "--quiet" => verbosity = Verbosity::Quiet,
+            "-v" | "--verbose" => verbosity = Verbosity::Verbose,
+            "--json" => json = true,
+            "--events" => events = true,
+            "-i" | "--interactive" => interactive = true,
+            "--no-default-junk" => use_default_junk = false,
+            "--no-keep-markers" => options.no_keep_markers = true,
+            "--empty-files" => options.empty_files = true,
+            // 默认保留目标目录本身，--remove-root 表示目标目录清理后为空时也删除它
+            "--remove-root" => options.remove_root = true,
+            "--fail-fast" => options.continue_on_error = false,
+            "--no-journal" => options.journal = None,
+            "--trash" => match Quarantine::home_trash() {
+                Some(dir) => options.quarantine = Some(Quarantine::Trash(dir)),
+                None => return Err(UsageError("找不到回收站：没有设置 HOME 或 XDG_DATA_HOME".to_string())),
+            },
+            "--junk" | "--config" | "--exclude" | "--include" | "--keep-marker" | "--keep-empty" | "--max-depth"
+            | "--symlinks" | "--min-age" | "--age-time" | "--quarantine" | "--journal" | "--threads" | "--save-plan"
+            | "--plan" => {
+                // 值可以写在 = 后面，也可以是下一个参数
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => match iter.next().and_then(|v| v.into_string().ok()) {
+                        Some(value) => value,
+                        None => return Err(UsageError(format!("{} 需要一个参数", name))),
+                    },
+                };
+                match name.as_str() {
+                    "--junk" => junk_specs.push(value),
+                    "--config" => config_files.push(PathBuf::from(value)),
+                    "--exclude" => excludes.push(value),
+                    "--include" => includes.push(value),
+                    "--keep-marker" => options.keep_markers.push(value),
+                    "--keep-empty" => options.empty_file_sentinels.push(value),
+                    "--quarantine" => options.quarantine = Some(Quarantine::Folder(PathBuf::from(value))),
+                    "--journal" => options.journal = Some(PathBuf::from(value)),
+                    "--save-plan" => save_plan = Some(PathBuf::from(value)),
+                    "--plan" => plan = Some(PathBuf::from(value)),
+                    "--max-depth" => {
+                        // parse::<usize>() 把字符串解析成无符号整数
+                        let depth = value
+                            .parse::<usize>()
+                            .map_err(|_| UsageError(format!("--max-depth 需要一个非负整数，实际是 {}", value)))?;
+                        options.max_depth = Some(depth);
+                    }
+                    "--min-age" => match parse_duration(&value) {
+                        Some(age) => options.min_age = Some(age),
+                        None => return Err(UsageError(format!("--min-age 需要一个时长，例如 2h、7d，实际是 {}", value))),
+                    },
+                    "--age-time" => {
+                        options.age_time = AgeTime::parse(&value)
+                            .ok_or_else(|| UsageError("--age-time 只能是 mtime、ctime 或 atime".to_string()))?;
+                    }
+                    "--threads" => {
+                        options.threads = match value.parse::<usize>() {
+                            Ok(threads) if threads > 0 => threads,
+                            _ => return Err(UsageError(format!("--threads 需要一个正整数，实际是 {}", value))),
+                        };
+                    }
+                    // 符号链接策略：skip（默认）/ report / follow
+                    _ => {
+                        options.symlinks = match value.as_str() {
+                            "skip" => SymlinkPolicy::Skip,
+                            "report" => SymlinkPolicy::Report,
+                            "follow" => SymlinkPolicy::Follow,
+                            _ => return Err(UsageError("--symlinks 只能是 skip、report 或 follow".to_string())),
+                        }
+                    }
+                }
+            }
+            _ => return Err(UsageError(format!("未知的选项 {}", text))),
+        }
+
+        // 不接受值的选项写成了 --name=value
+        if inline_value_rejected(&name, &text) {
+            return Err(UsageError(format!("{} 不接受参数", name)));
+        }
+    }
+
+    // 生成垃圾文件规则：内置规则 → 配置文件 → 命令行 --junk
+    // 排除和包含规则也是配置文件在前、命令行在后
+    let mut junk_rules = if use_default_junk { JunkRules::default() } else { JunkRules::empty() };
+    for config_file in &config_files {
+        rules::load_config(config_file, &mut junk_rules, &mut options)
+            .map_err(|e| UsageError(format!("无法读取配置文件: {}", e)))?;
+    }
+    for spec in &junk_specs {
+        junk_rules.add_spec(spec).map_err(|e| UsageError(e.to_string()))?;
+    }
+    options.exclude.extend(excludes);
+    options.include.extend(includes);
+    // 两种格式都写到标准输出，混在一起无法解析
+    if json && events {
+        return Err(UsageError("--json 和 --events 不能同时使用".to_string()));
+    }
+    // 计划只针对一个目录；执行计划时目录由计划决定
+    if save_plan.is_some() && plan.is_some() {
+        return Err(UsageError("--save-plan 和 --plan 不能同时使用".to_string()));
+    }
+    if save_plan.is_some() && paths.len() > 1 {
+        return Err(UsageError("--save-plan 只能指定一个目录".to_string()));
+    }
+    // 保存计划时不清理任何目录，没有可以输出的文档或事件
+    if save_plan.is_some() && (json || events) {
+        return Err(UsageError("--save-plan 不能和 --json 或 --events 同时使用".to_string()));
+    }
+    if plan.is_some() && !paths.is_empty() {
+        return Err(UsageError("--plan 不能和目录一起使用，要清理的目录记录在计划中".to_string()));
+    }
+    // 保存计划不删除任何内容，没有需要确认的
+    if interactive && save_plan.is_some() {
+        return Err(UsageError("--interactive 和 --save-plan 不能同时使用".to_string()));
+    }
+
+    Ok(Command::Run(Box::new(Cli { options, junk_rules, paths, verbosity, json, events, save_plan, plan, interactive })))
+}
+
+// --interactive 从标准输入读取回答；不是终端（管道、重定向、后台任务）时没有人回答，直接拒绝而不是一直等待
+// stdin_is_terminal 由 main 传入 io::stdin().is_terminal()，测试时可以直接给出
+pub fn check_interactive(cli: &Cli, stdin_is_terminal: bool) -> Result<(), UsageError> {
+    if cli.interactive && !stdin_is_terminal {
+        return Err(UsageError(
+            "--interactive 需要从终端读取回答，但标准输入不是终端；\
+             不需要确认时去掉 --interactive，需要先审阅时可以使用 --save-plan 和 --plan"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// 解析 restore 子命令的参数（不包含 restore 本身）
+fn parse_restore<I>(args: I) -> Result<Command, UsageError>
+where
+    I: Iterator<Item = OsString>,
+{
+    let mut verbosity = Verbosity::Normal;
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut options_done = false;
+
+    for arg in args {
+        match arg.to_str() {
+            Some("--") if !options_done => options_done = true,
+            Some("-h" | "--help") if !options_done => return Ok(Command::Help),
+            Some("-q" | "--quiet") if !options_done => verbosity = Verbosity::Quiet,
+            Some(text) if !options_done && text.starts_with('-') && text != "-" => {
+                return Err(UsageError(format!("restore 不支持选项 {}", text)));
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    // 第一个路径是隔离目录，第二个（可选）是原目录
+    let mut paths = paths.into_iter();
+    let Some(source) = paths.next() else {
+        return Err(UsageError("restore 需要指定隔离目录".to_string()));
+    };
+    let only_under = paths.next();
+    if paths.next().is_some() {
+        return Err(UsageError("restore 最多接受两个路径".to_string()));
+    }
+    Ok(Command::Restore(RestoreArgs { source, only_under, verbosity }))
+}
+
+// 解析 undo 子命令的参数（不包含 undo 本身）
+fn parse_undo<I>(mut args: I) -> Result<Command, UsageError>
+where
+    I: Iterator<Item = OsString>,
+{
+    let mut verbosity = Verbosity::Normal;
+    let mut journal = journal::default_path();
+    let mut run_ids: Vec<String> = Vec::new();
+
+    while let Some(arg) = args.next() {
+        let Some(text) = arg.to_str() else {
+            return Err(UsageError("运行 ID 必须是有效的文本".to_string()));
+        };
+        match text.split_once('=') {
+            Some(("--journal", value)) => journal = Some(PathBuf::from(value)),
+            _ => match text {
+                "-h" | "--help" => return Ok(Command::Help),
+                "-q" | "--quiet" => verbosity = Verbosity::Quiet,
+                "--journal" => match args.next() {
+                    Some(value) => journal = Some(PathBuf::from(value)),
+                    None => return Err(UsageError("--journal 需要一个参数".to_string())),
+                },
+                _ if text.starts_with('-') => return Err(UsageError(format!("undo 不支持选项 {}", text))),
+                _ => run_ids.push(text.to_string()),
+            },
+        }
+    }
+
+    let Some(journal) = journal else {
+        return Err(UsageError("找不到撤销日志：没有设置 HOME，请使用 --journal 指定".to_string()));
+    };
+    match run_ids.len() {
+        1 => Ok(Command::Undo(UndoArgs { run_id: run_ids.remove(0), journal, verbosity })),
+        0 => Err(UsageError("undo 需要指定运行 ID".to_string())),
+        _ => Err(UsageError("undo 只接受一个运行 ID".to_string())),
+    }
+}
+
+// 判断一个不需要值的选项是否被写成了 --name=value
+fn inline_value_rejected(name: &str, text: &str) -> bool {
+    let takes_value = matches!(
+        name,
+        "--junk" | "--config" | "--exclude" | "--include" | "--keep-marker" | "--keep-empty" | "--max-depth"
+            | "--symlinks" | "--min-age" | "--age-time" | "--quarantine" | "--journal" | "--threads" | "--save-plan"
+            | "--plan"
+    );
+    !takes_value && name != text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    // 把字符串数组转换成参数列表
+    fn parse(args: &[&str]) -> Result<Command, UsageError> {
+        parse_args(args.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn test_parse_help_and_version() {
+        assert!(matches!(parse(&["--help"]), Ok(Command::Help)), "期望 --help 显示帮助");
+        assert!(matches!(parse(&["-V"]), Ok(Command::Version)), "期望 -V 显示版本");
+    }
+
+    #[test]
+    fn test_parse_options_and_paths() {
+        let Ok(Command::Run(cli)) = parse(&["--dry-run", "--max-depth=2", "--exclude", ".git", "--threads", "4", "-q", "a", "b"])
+        else {
+            panic!("期望解析成功");
+        };
+        assert!(cli.options.dry_run, "期望开启预览模式");
+        assert_eq!(cli.options.max_depth, Some(2), "期望最大深度为2");
+        assert_eq!(cli.options.exclude, vec![".git".to_string()], "期望记录排除规则");
+        assert_eq!(cli.options.threads, 4, "期望使用4个线程");
+        assert_eq!(cli.verbosity, Verbosity::Quiet, "期望安静模式");
+        assert_eq!(cli.paths, vec![PathBuf::from("a"), PathBuf::from("b")], "期望两个路径");
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let Ok(Command::Run(cli)) = parse(&["--events", "a"]) else {
+            panic!("期望解析成功");
+        };
+        assert!(cli.events && !cli.json, "期望输出事件");
+        assert!(parse(&["--events", "--json"]).is_err(), "期望 --events 和 --json 不能同时使用");
+        assert!(parse(&["--events=yes"]).is_err(), "期望 --events 不接受参数");
+    }
+
+    #[test]
+    fn test_parse_plan() {
+        let Ok(Command::Run(cli)) = parse(&["--save-plan", "plan.json", "a"]) else {
+            panic!("期望解析成功");
+        };
+        assert_eq!(cli.save_plan, Some(PathBuf::from("plan.json")), "期望保存计划");
+        let Ok(Command::Run(cli)) = parse(&["--plan=plan.json", "-n"]) else {
+            panic!("期望解析成功");
+        };
+        assert_eq!(cli.plan, Some(PathBuf::from("plan.json")), "期望执行计划");
+        assert!(parse(&["--save-plan", "p.json", "a", "b"]).is_err(), "期望计划只能针对一个目录");
+        assert!(parse(&["--plan", "p.json", "a"]).is_err(), "期望执行计划时不能指定目录");
+        assert!(parse(&["--plan", "p.json", "--save-plan", "q.json"]).is_err(), "期望两个选项不能同时使用");
+        assert!(parse(&["--save-plan", "p.json", "--json"]).is_err(), "期望保存计划时不能输出 JSON 文档");
+        assert!(parse(&["--save-plan", "p.json", "--events"]).is_err(), "期望保存计划时不能输出事件");
+    }
+
+    #[test]
+    fn test_parse_interactive() {
+        let Ok(Command::Run(cli)) = parse(&["-i", "a"]) else {
+            panic!("期望解析成功");
+        };
+        assert!(cli.interactive, "期望逐个确认");
+        let Ok(Command::Run(cli)) = parse(&["--interactive", "--plan", "p.json"]) else {
+            panic!("期望解析成功");
+        };
+        assert!(cli.interactive && cli.plan.is_some(), "期望执行计划前也可以逐个确认");
+        assert!(parse(&["--interactive", "--save-plan", "p.json"]).is_err(), "期望保存计划时不能逐个确认");
+        assert!(parse(&["--interactive=yes"]).is_err(), "期望 --interactive 不接受参数");
+    }
+
+    #[test]
+    fn test_check_interactive() {
+        let Ok(Command::Run(cli)) = parse(&["-i", "a"]) else {
+            panic!("期望解析成功");
+        };
+        assert!(check_interactive(&cli, true).is_ok(), "期望标准输入是终端时可以逐个确认");
+        assert!(check_interactive(&cli, false).is_err(), "期望标准输入不是终端时按用法错误拒绝");
+        let Ok(Command::Run(cli)) = parse(&["a"]) else {
+            panic!("期望解析成功");
+        };
+        assert!(check_interactive(&cli, false).is_ok(), "期望没有 --interactive 时不检查标准输入");
+    }
+
+    #[test]
+    fn test_parse_exclude_and_include() {
+        let Ok(Command::Run(cli)) = parse(&["--exclude", "/build", "--include=/build/keep", "--exclude=tmp/"]) else {
+            panic!("期望解析成功");
+        };
+        assert_eq!(cli.options.exclude, vec!["/build".to_string(), "tmp/".to_string()], "期望按顺序记录排除规则");
+        assert_eq!(cli.options.include, vec!["/build/keep".to_string()], "期望记录包含规则");
+        assert!(parse(&["--include"]).is_err(), "期望缺少参数时报错");
+    }
+
+    #[test]
+    fn test_parse_keep_markers() {
+        let Ok(Command::Run(cli)) = parse(&["--keep-marker", ".placeholder", "--keep-marker=KEEP"]) else {
+            panic!("期望解析成功");
+        };
+        assert_eq!(cli.options.keep_markers, vec![".placeholder".to_string(), "KEEP".to_string()], "期望记录保留标记");
+        assert!(!cli.options.no_keep_markers, "期望默认使用保留标记");
+
+        let Ok(Command::Run(cli)) = parse(&["--no-keep-markers"]) else {
+            panic!("期望解析成功");
+        };
+        assert!(cli.options.no_keep_markers, "期望关闭保留标记");
+        assert!(parse(&["--no-keep-markers=1"]).is_err(), "期望不接受参数的选项带值时报错");
+    }
+
+    #[test]
+    fn test_parse_empty_files() {
+        let Ok(Command::Run(cli)) = parse(&["--empty-files", "--keep-empty", "*.lock"]) else {
+            panic!("期望解析成功");
+        };
+        assert!(cli.options.empty_files, "期望开启删除 0 字节文件");
+        assert_eq!(cli.options.empty_file_sentinels, vec!["*.lock".to_string()], "期望记录不删除的文件名");
+        let Ok(Command::Run(cli)) = parse(&[]) else {
+            panic!("期望解析成功");
+        };
+        assert!(!cli.options.empty_files, "期望默认不删除 0 字节文件");
+    }
+
+    #[test]
+    fn test_parse_min_age() {
+        let Ok(Command::Run(cli)) = parse(&["--min-age", "2h", "--age-time=ctime"]) else {
+            panic!("期望解析成功");
+        };
+        assert_eq!(cli.options.min_age, Some(Duration::from_secs(2 * 60 * 60)), "期望最小年龄为2小时");
+        assert_eq!(cli.options.age_time, AgeTime::Changed, "期望使用 ctime");
+        let Ok(Command::Run(cli)) = parse(&["--min-age=7d"]) else {
+            panic!("期望解析成功");
+        };
+        assert_eq!(cli.options.min_age, Some(Duration::from_secs(7 * 24 * 60 * 60)), "期望最小年龄为7天");
+        assert_eq!(cli.options.age_time, AgeTime::Modified, "期望默认使用 mtime");
+        assert!(parse(&["--min-age", "7"]).is_err(), "期望没有单位时报错");
+        assert!(parse(&["--min-age", "7y"]).is_err(), "期望未知单位时报错");
+        assert!(parse(&["--age-time", "btime"]).is_err(), "期望未知的时间报错");
+    }
+
+    #[test]
+    fn test_parse_double_dash_ends_options() {
+        let Ok(Command::Run(cli)) = parse(&["--", "--help", "-x"]) else {
+            panic!("期望解析成功");
+        };
+        assert_eq!(cli.paths, vec![PathBuf::from("--help"), PathBuf::from("-x")], "期望 -- 之后都是路径");
+    }
+
+    #[test]
+    fn test_parse_quarantine_and_restore() {
+        let Ok(Command::Run(cli)) = parse(&["--quarantine", "q", "restore"]) else {
+            panic!("期望解析成功");
+        };
+        assert_eq!(cli.options.quarantine, Some(Quarantine::Folder(PathBuf::from("q"))), "期望使用隔离目录");
+        // restore 不是第一个参数时只是普通的路径
+        assert_eq!(cli.paths, vec![PathBuf::from("restore")], "期望 restore 被当作路径");
+
+        let Ok(Command::Restore(args)) = parse(&["restore", "-q", "q/20261016-093000", "src"]) else {
+            panic!("期望解析为 restore 子命令");
+        };
+        assert_eq!(args.source, PathBuf::from("q/20261016-093000"), "期望第一个路径是隔离目录");
+        assert_eq!(args.only_under, Some(PathBuf::from("src")), "期望第二个路径是原目录");
+        assert_eq!(args.verbosity, Verbosity::Quiet, "期望安静模式");
+
+        assert!(parse(&["restore"]).is_err(), "期望缺少隔离目录时报错");
+        assert!(parse(&["restore", "--dry-run", "q"]).is_err(), "期望 restore 不支持的选项报错");
+    }
+
+    #[test]
+    fn test_parse_journal_and_undo() {
+        let Ok(Command::Run(cli)) = parse(&["--journal=j.jsonl", "a"]) else {
+            panic!("期望解析成功");
+        };
+        assert_eq!(cli.options.journal, Some(PathBuf::from("j.jsonl")), "期望使用指定的日志文件");
+        let Ok(Command::Run(cli)) = parse(&["--no-journal"]) else {
+            panic!("期望解析成功");
+        };
+        assert_eq!(cli.options.journal, None, "期望不写日志");
+
+        let Ok(Command::Undo(args)) = parse(&["undo", "--journal", "j.jsonl", "20261016-093000-1a2b"]) else {
+            panic!("期望解析为 undo 子命令");
+        };
+        assert_eq!(args.run_id, "20261016-093000-1a2b", "期望记录运行 ID");
+        assert_eq!(args.journal, PathBuf::from("j.jsonl"), "期望使用指定的日志文件");
+        assert!(parse(&["undo", "--journal", "j.jsonl"]).is_err(), "期望缺少运行 ID 时报错");
+    }
+
+    #[test]
+    fn test_parse_usage_errors() {
+        assert!(parse(&["--unknown"]).is_err(), "期望未知选项报错");
+        assert!(parse(&["--junk"]).is_err(), "期望缺少参数时报错");
+        assert!(parse(&["--max-depth", "abc"]).is_err(), "期望无效的深度报错");
+        assert!(parse(&["--threads", "0"]).is_err(), "期望线程数为0时报错");
+        assert!(parse(&["--dry-run=yes"]).is_err(), "期望不接受参数的选项带参数时报错");
+        assert!(parse(&["--junk", "regex:("]).is_err(), "期望无效的规则报错");
+    }
+}
@@ -0,0 +1,146 @@
+// ========================================
+// 控制台输出
+// ========================================
+// 命令行的文字输出是一个观察者：清理过程中每删除一个条目就输出一行，
+// 目标清理完以后再输出数量、跳过的条目和根目录的去留
+// 错误写到标准错误；--quiet 时只输出错误
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use eptdir::{escape_path, lock, CleanOptions, CleanReport, CleanupObserver, EptdirError, RemoveKind, RootStatus, SkipReason};
+
+use crate::cli::Verbosity;
+
+pub struct ConsoleObserver {
+    verbosity: Verbosity,
+    // 正在清理的目标是不是预览（制定清理计划时总是预览）
+    dry_run: AtomicBool,
+    // 隔离模式：条目被移走而不是删除
+    quarantine: bool,
+    // 正在清理的目标目录（根目录的去留在最后单独说明，不逐行列出）
+    target: Mutex<PathBuf>,
+}
+
+impl ConsoleObserver {
+    pub fn new(options: &CleanOptions, verbosity: Verbosity) -> Self {
+        ConsoleObserver {
+            verbosity,
+            dry_run: AtomicBool::new(options.dry_run),
+            quarantine: options.quarantine.is_some(),
+            target: Mutex::default(),
+        }
+    }
+
+    fn quiet(&self) -> bool {
+        self.verbosity == Verbosity::Quiet
+    }
+}
+
+impl CleanupObserver for ConsoleObserver {
+    fn on_target_started(&self, target: &Path, dry_run: bool) {
+        *lock(&self.target) = target.to_path_buf();
+        self.dry_run.store(dry_run, Ordering::Relaxed);
+        if self.quiet() {
+            return;
+        }
+        println!("开始清理目录: {}", escape_path(target));
+        if dry_run {
+            println!("预览模式：只列出将要删除的内容，不会修改磁盘");
+        }
+        println!(); // 空行
+    }
+
+    fn on_enter_dir(&self, dir: &Path) {
+        if self.verbosity == Verbosity::Verbose {
+            println!("进入目录: {}", escape_path(dir));
+        }
+    }
+
+    fn after_remove(&self, path: &Path, kind: RemoveKind, _bytes: u64) {
+        // 根目录单独说明，这里只列出其中的条目
+        if self.quiet() || (kind == RemoveKind::Dir && *path == *lock(&self.target)) {
+            return;
+        }
+        // 预览模式和正常模式使用不同的动词
+        let verb = match (kind, self.dry_run.load(Ordering::Relaxed), self.quarantine) {
+            (RemoveKind::JunkFile, true, _) => "[预览] 将删除垃圾文件",
+            (RemoveKind::EmptyFile, true, _) => "[预览] 将删除空文件",
+            (RemoveKind::Dir, true, _) => "[预览] 将删除空文件夹",
+            (RemoveKind::JunkFile, false, true) => "隔离垃圾文件",
+            (RemoveKind::EmptyFile, false, true) => "隔离空文件",
+            (RemoveKind::Dir, false, true) => "隔离空文件夹",
+            (RemoveKind::JunkFile, false, false) => "删除垃圾文件",
+            (RemoveKind::EmptyFile, false, false) => "删除空文件",
+            (RemoveKind::Dir, false, false) => "删除空文件夹",
+        };
+        println!("{}: {}", verb, escape_path(path));
+    }
+
+    fn on_error(&self, error: &EptdirError) {
+        eprintln!("错误: {}", error);
+    }
+
+    // 终止清理的错误由 main 输出
+    fn on_target_finished(&self, report: &CleanReport, error: Option<&EptdirError>) {
+        if self.quiet() || error.is_some() {
+            return;
+        }
+        print_summary(report);
+    }
+}
+
+// 一个目标清理完以后的汇总
+fn print_summary(report: &CleanReport) {
+    let target = escape_path(&report.target);
+    // 根目录不算在删除的空文件夹中
+    let dirs = report.removed_dir_count();
+    let verb = if report.dry_run { "将删除" } else { "已删除" };
+
+    println!(); // 空行
+    println!("{} {} 个垃圾文件，释放 {} 字节", verb, report.removed_junk_files.len(), report.bytes_freed);
+    // 0 字节的文件（--empty-files）单独列出
+    if !report.removed_empty_files.is_empty() {
+        println!("{} {} 个 0 字节的文件", verb, report.removed_empty_files.len());
+    }
+    println!("{} {} 个空文件夹", verb, dirs);
+
+    for skipped in &report.skipped {
+        match skipped.reason {
+            SkipReason::Symlink => println!("跳过符号链接: {}", escape_path(&skipped.path)),
+            SkipReason::SymlinkLoop => println!("检测到符号链接循环，跳过: {}", escape_path(&skipped.path)),
+            SkipReason::Excluded => println!("已排除: {}", escape_path(&skipped.path)),
+            SkipReason::Kept => println!("有保留标记，保留: {}", escape_path(&skipped.path)),
+            SkipReason::TooNew => println!("时间太新，保留: {}", escape_path(&skipped.path)),
+            SkipReason::Vetoed => println!("调用方拒绝删除，保留: {}", escape_path(&skipped.path)),
+            SkipReason::Changed => println!("计划之后发生了变化，跳过: {}", escape_path(&skipped.path)),
+        }
+    }
+    // 错误在发生时已经输出过了
+    if !report.errors.is_empty() {
+        println!("有 {} 个条目处理失败，已跳过", report.errors.len());
+    }
+
+    // 明确告诉用户根目录的去留
+    match report.root_status {
+        RootStatus::KeptEmpty => println!("根目录已为空，按设置保留: {}", target),
+        RootStatus::Removed if report.dry_run => println!("[预览] 将删除根目录: {}", target),
+        RootStatus::Removed => println!("根目录已为空，已删除: {}", target),
+        RootStatus::NotEmpty => println!("根目录保留: {}", target),
+    }
+
+    // 隔离模式：告诉用户内容去了哪里、怎么恢复
+    if let Some(quarantine) = &report.quarantine {
+        println!("已移动到隔离位置: {}", escape_path(&quarantine));
+        println!("可以使用 eptdir restore {} {} 恢复", escape_path(&quarantine), target);
+    }
+
+    if let Some(run_id) = &report.run_id {
+        println!("运行 ID: {}（可以使用 eptdir undo {} 撤销）", run_id, run_id);
+    }
+
+    println!(); // 空行
+    // as_secs_f64() 把耗时转换成秒（小数）
+    println!("目录清理完成: {}（耗时 {:.2} 秒）", target, report.elapsed.as_secs_f64());
+    println!(); // 空行
+}
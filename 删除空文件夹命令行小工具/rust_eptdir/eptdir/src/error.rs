@@ -0,0 +1,168 @@
+// ========================================
+// 错误类型
+// ========================================
+// 库中所有公开函数都返回 EptdirError，调用者可以用 match 区分
+// "目标不存在"、"权限不足"、"目录在删除前又被写入了内容" 等不同情况
+// EptdirError 只包含 PathBuf、String 和 io::Error，它们都是 Send + Sync，
+// 所以错误可以在线程之间传递
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::escape::escape_path;
+
+// 出错时正在执行的文件系统操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    // 读取目录内容（fs::read_dir 以及遍历其中的条目）
+    ReadDir,
+    // 读取文件或条目的元数据（类型、大小等）
+    Metadata,
+    // 删除文件
+    RemoveFile,
+    // 删除目录
+    RemoveDir,
+    // 读取文件内容（例如配置文件）
+    ReadFile,
+    // 写入文件（例如回收站的 .trashinfo）
+    WriteFile,
+    // 移动到隔离目录
+    Quarantine,
+    // 从隔离目录恢复
+    Restore,
+}
+
+impl Operation {
+    // JSON 输出中使用的名字
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::ReadDir => "read_dir",
+            Operation::Metadata => "metadata",
+            Operation::RemoveFile => "remove_file",
+            Operation::RemoveDir => "remove_dir",
+            Operation::ReadFile => "read_file",
+            Operation::WriteFile => "write_file",
+            Operation::Quarantine => "quarantine",
+            Operation::Restore => "restore",
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Operation::ReadDir => "读取目录",
+            Operation::Metadata => "读取元数据",
+            Operation::RemoveFile => "删除文件",
+            Operation::RemoveDir => "删除目录",
+            Operation::ReadFile => "读取文件",
+            Operation::WriteFile => "写入文件",
+            Operation::Quarantine => "移动到隔离目录",
+            Operation::Restore => "恢复",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// eptdir 的错误
+#[derive(Debug)]
+pub enum EptdirError {
+    // 目标目录不存在
+    TargetNotFound(PathBuf),
+    // 目标路径存在，但不是目录
+    NotADirectory(PathBuf),
+    // 检查时目录为空，删除时却不为空：其他程序在这期间往目录里写入了内容
+    DirectoryNotEmpty { path: PathBuf, source: io::Error },
+    // 文件系统操作失败
+    Io { path: PathBuf, operation: Operation, source: io::Error },
+    // 无效的垃圾文件规则（例如正则表达式语法错误）
+    InvalidRule { rule: String, message: String },
+    // 配置文件内容有误，line 从 1 开始
+    Config { path: PathBuf, line: usize, message: String },
+    // 要恢复的目录既不是 eptdir 的隔离目录，也不是回收站
+    NotAQuarantine(PathBuf),
+    // 撤销日志中没有这个运行 ID
+    RunNotFound(String),
+    // 清理计划无效：计划文件的格式不对，或者其中的条目不在目标目录中
+    // path 是计划文件或者出问题的条目
+    InvalidPlan { path: PathBuf, message: String },
+}
+
+impl EptdirError {
+    // 根据 io::Error 创建错误
+    // 删除目录时遇到"目录不为空"会被单独归类为 DirectoryNotEmpty
+    pub fn io(path: &Path, operation: Operation, source: io::Error) -> Self {
+        if operation == Operation::RemoveDir && source.kind() == io::ErrorKind::DirectoryNotEmpty {
+            return EptdirError::DirectoryNotEmpty { path: path.to_path_buf(), source };
+        }
+        EptdirError::Io { path: path.to_path_buf(), operation, source }
+    }
+
+    // 出错的路径（规则错误没有路径）
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            EptdirError::TargetNotFound(path)
+            | EptdirError::NotADirectory(path)
+            | EptdirError::NotAQuarantine(path)
+            | EptdirError::DirectoryNotEmpty { path, .. }
+            | EptdirError::Io { path, .. }
+            | EptdirError::Config { path, .. }
+            | EptdirError::InvalidPlan { path, .. } => Some(path),
+            EptdirError::InvalidRule { .. } | EptdirError::RunNotFound(_) => None,
+        }
+    }
+
+    // 出错时正在执行的操作
+    pub fn operation(&self) -> Option<Operation> {
+        match self {
+            EptdirError::DirectoryNotEmpty { .. } => Some(Operation::RemoveDir),
+            EptdirError::Io { operation, .. } => Some(*operation),
+            _ => None,
+        }
+    }
+
+    // 底层 io::Error 的种类，例如 PermissionDenied
+    pub fn kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            EptdirError::DirectoryNotEmpty { source, .. } | EptdirError::Io { source, .. } => {
+                Some(source.kind())
+            }
+            _ => None,
+        }
+    }
+}
+
+// Display 决定错误用 {} 打印时的样子
+impl fmt::Display for EptdirError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EptdirError::TargetNotFound(path) => write!(f, "目录不存在: {}", escape_path(path)),
+            EptdirError::NotADirectory(path) => write!(f, "该路径不是目录: {}", escape_path(path)),
+            EptdirError::DirectoryNotEmpty { path, .. } => {
+                write!(f, "目录在删除前被写入了新内容: {}", escape_path(path))
+            }
+            EptdirError::Io { path, operation, source } => {
+                write!(f, "{}失败: {}: {}", operation, escape_path(path), source)
+            }
+            EptdirError::InvalidRule { rule, message } => write!(f, "无效的规则 {}: {}", rule, message),
+            EptdirError::Config { path, line, message } => {
+                write!(f, "{}:{}: {}", escape_path(path), line, message)
+            }
+            EptdirError::NotAQuarantine(path) => {
+                write!(f, "不是 eptdir 隔离目录或回收站: {}", escape_path(path))
+            }
+            EptdirError::RunNotFound(run_id) => write!(f, "撤销日志中没有运行 ID {}", run_id),
+            EptdirError::InvalidPlan { path, message } => write!(f, "无效的清理计划 {}: {}", escape_path(path), message),
+        }
+    }
+}
+
+// 实现标准库的 Error trait，source() 返回底层的 io::Error
+impl std::error::Error for EptdirError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EptdirError::DirectoryNotEmpty { source, .. } | EptdirError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
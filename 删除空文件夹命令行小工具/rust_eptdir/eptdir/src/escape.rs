@@ -0,0 +1,105 @@
+// ========================================
+// 路径的转义和编码
+// ========================================
+// Unix 上文件名可以是任意字节，不一定是有效的 UTF-8；path.display() 会把无效的字节替换成 �，
+// 两个不同的文件名可能显示成一样的文本
+// 这里提供两种无歧义的表示方法：
+//   - 给人看：escape_path() 把需要转义的路径写成 shell 的 $'...' 形式，无效字节写成 \xHH，
+//     例如 $'/data/\xff\xfe.tmp'，可以直接复制到 bash / zsh 中使用
+//   - 给程序看：path_json() 在路径不是有效 UTF-8 时输出 {"escaped": "...", "hex": "..."}，
+//     hex 是路径原始字节的十六进制，可以无损还原
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+// 把路径转换成无歧义的文本
+// 有效的 UTF-8、不含控制字符的路径原样返回；否则写成 $'...' 形式
+pub fn escape_path<P: AsRef<Path> + ?Sized>(path: &P) -> Cow<'_, str> {
+    escape_os_str(path.as_ref().as_os_str())
+}
+
+// 和 escape_path 相同，用于文件名等 OsStr
+pub fn escape_os_str(text: &OsStr) -> Cow<'_, str> {
+    let bytes = text.as_encoded_bytes();
+    match std::str::from_utf8(bytes) {
+        // 本身以 $' 开头的路径也加引号，避免和转义后的形式混淆
+        Ok(text) if !text.starts_with("$'") && !text.chars().any(char::is_control) => Cow::Borrowed(text),
+        _ => Cow::Owned(quote(bytes)),
+    }
+}
+
+// 生成 $'...' 形式：\ 和 ' 前面加 \，控制字符和无效字节写成转义序列
+fn quote(bytes: &[u8]) -> String {
+    let mut quoted = String::from("$'");
+    // utf8_chunks() 把字节序列拆成"有效的 UTF-8 + 紧跟着的无效字节"的片段
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => quoted.push_str("\\\\"),
+                '\'' => quoted.push_str("\\'"),
+                '\n' => quoted.push_str("\\n"),
+                '\t' => quoted.push_str("\\t"),
+                '\r' => quoted.push_str("\\r"),
+                // write! 写入 String 不会失败
+                c if c.is_control() && (c as u32) < 0x80 => write!(quoted, "\\x{:02x}", c as u32).unwrap(),
+                c if c.is_control() => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+                c => quoted.push(c),
+            }
+        }
+        for byte in chunk.invalid() {
+            write!(quoted, "\\x{:02x}", byte).unwrap();
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+// 路径转换成 JSON：有效的 UTF-8 是普通字符串，否则是 {"escaped": ..., "hex": ...}
+pub fn path_json(path: &Path) -> Value {
+    match path.to_str() {
+        Some(text) => Value::String(text.to_string()),
+        None => {
+            let mut hex = String::new();
+            for byte in path_bytes(path) {
+                write!(hex, "{:02x}", byte).unwrap();
+            }
+            json!({ "escaped": escape_path(path), "hex": hex })
+        }
+    }
+}
+
+// path_json 的逆操作，格式不对时返回 None
+pub fn path_from_json(value: &Value) -> Option<PathBuf> {
+    if let Some(text) = value.as_str() {
+        return Some(PathBuf::from(text));
+    }
+    let hex = value["hex"].as_str()?;
+    // 每两个十六进制字符是一个字节
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect::<Option<Vec<u8>>>()?;
+    Some(path_from_bytes(bytes))
+}
+
+// 路径的原始字节
+// Unix 上就是文件名本身的字节；其他平台是标准库内部使用的编码（WTF-8）
+pub fn path_bytes(path: &Path) -> Vec<u8> {
+    path.as_os_str().as_encoded_bytes().to_vec()
+}
+
+// path_bytes 的逆操作
+#[cfg(unix)]
+pub fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+// 其他平台无法安全地还原任意字节，按 UTF-8 处理
+#[cfg(not(unix))]
+pub fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
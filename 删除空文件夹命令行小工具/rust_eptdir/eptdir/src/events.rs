@@ -0,0 +1,121 @@
+// ========================================
+// 清理过程中的事件
+// ========================================
+// CleanReport 要等整个目标清理完才返回；很大的目录树需要实时的进度时，
+// 把 EventSink 作为观察者放到 CleanOptions::observer 中，遍历时每发生一件事就调用一次回调
+// clean_directory()、remove_junk_files() 和 remove_empty_dirs() 都会产生事件
+//
+// 顺序：一个目标的事件以 TargetStarted 开始、以 TargetFinished 结束；
+// 单线程时其余事件按遍历的顺序产生（进入目录、删除其中的文件、处理子目录、最后删除目录本身）；
+// 多线程时不同子目录的事件会交错，同一个目录中的事件仍然是这个顺序
+// 目标不存在或不是目录时函数直接返回错误，不产生任何事件
+// 预览模式下"removed"的事件表示将被删除的条目
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::escape::path_json;
+use crate::observer::{CleanupObserver, RemoveKind};
+use crate::report::error_json;
+use crate::{CleanReport, EptdirError};
+
+// 一个事件；借用的数据只在回调期间有效
+#[derive(Debug, Clone, Copy)]
+pub enum Event<'e> {
+    // 开始清理一个目标目录
+    TargetStarted { target: &'e Path, dry_run: bool },
+    // 进入一个目录（读取它的条目之前）
+    DirEntered { path: &'e Path },
+    // 删除了一个垃圾文件，bytes 是它的大小
+    JunkRemoved { path: &'e Path, bytes: u64 },
+    // 删除了一个 0 字节的文件（CleanOptions::empty_files）
+    EmptyFileRemoved { path: &'e Path },
+    // 删除了一个空文件夹
+    DirRemoved { path: &'e Path },
+    // 一个条目处理失败，跳过它继续清理（只有 continue_on_error 时才有）
+    Error { error: &'e EptdirError },
+    // 目标清理结束；error 是终止清理的错误，这时 report 只包含终止之前的结果
+    TargetFinished { report: &'e CleanReport, error: Option<&'e EptdirError> },
+}
+
+impl Event<'_> {
+    // 事件的名字，也是 JSON 中 "event" 字段的值
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::TargetStarted { .. } => "target_started",
+            Event::DirEntered { .. } => "dir_entered",
+            Event::JunkRemoved { .. } => "junk_removed",
+            Event::EmptyFileRemoved { .. } => "empty_file_removed",
+            Event::DirRemoved { .. } => "dir_removed",
+            Event::Error { .. } => "error",
+            Event::TargetFinished { .. } => "target_finished",
+        }
+    }
+
+    // 转换成 JSON 对象（一行一个，供 NDJSON 输出使用）
+    // TargetFinished 只包含数量，不重复列出已经作为事件输出过的路径
+    pub fn to_json(&self) -> Value {
+        let mut value = match *self {
+            Event::TargetStarted { target, dry_run } => json!({ "target": path_json(target), "dry_run": dry_run }),
+            Event::DirEntered { path } | Event::EmptyFileRemoved { path } | Event::DirRemoved { path } => {
+                json!({ "path": path_json(path) })
+            }
+            Event::JunkRemoved { path, bytes } => json!({ "path": path_json(path), "bytes": bytes }),
+            Event::Error { error } => json!({ "error": error_json(error) }),
+            Event::TargetFinished { report, error } => json!({
+                "target": path_json(&report.target),
+                "removed_junk_files": report.removed_junk_files.len(),
+                "removed_empty_files": report.removed_empty_files.len(),
+                "removed_dirs": report.removed_dir_count(),
+                "skipped": report.skipped.len(),
+                "errors": report.errors.len(),
+                "bytes_freed": report.bytes_freed,
+                "elapsed_ms": report.elapsed.as_millis() as u64,
+                "root_status": report.root_status.as_str(),
+                "error": error.map(error_json),
+            }),
+        };
+        value["event"] = json!(self.name());
+        value
+    }
+}
+
+// 把观察者的通知转换成事件，交给回调
+// 回调的要求和 CleanupObserver 相同：Send + Sync，尽快返回
+pub struct EventSink(Box<dyn Fn(&Event<'_>) + Send + Sync>);
+
+impl EventSink {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&Event<'_>) + Send + Sync + 'static,
+    {
+        EventSink(Box::new(f))
+    }
+}
+
+impl CleanupObserver for EventSink {
+    fn on_target_started(&self, target: &Path, dry_run: bool) {
+        (self.0)(&Event::TargetStarted { target, dry_run });
+    }
+
+    fn on_enter_dir(&self, dir: &Path) {
+        (self.0)(&Event::DirEntered { path: dir });
+    }
+
+    fn after_remove(&self, path: &Path, kind: RemoveKind, bytes: u64) {
+        let event = match kind {
+            RemoveKind::JunkFile => Event::JunkRemoved { path, bytes },
+            RemoveKind::EmptyFile => Event::EmptyFileRemoved { path },
+            RemoveKind::Dir => Event::DirRemoved { path },
+        };
+        (self.0)(&event);
+    }
+
+    fn on_error(&self, error: &EptdirError) {
+        (self.0)(&Event::Error { error });
+    }
+
+    fn on_target_finished(&self, report: &CleanReport, error: Option<&EptdirError>) {
+        (self.0)(&Event::TargetFinished { report, error });
+    }
+}
@@ -0,0 +1,169 @@
+// ========================================
+// 排除和包含规则（gitignore 风格）
+// ========================================
+// 写法和 .gitignore 相同，路径相对于清理的目标目录：
+//   node_modules     任意深度上名字为 node_modules 的文件或目录
+//   *.log            任意深度上名字匹配 *.log 的文件或目录
+//   tmp/             以 / 结尾：只匹配目录
+//   /build           以 / 开头（或者中间有 /）：相对于目标目录，只匹配 <目标目录>/build
+//   docs/*.md        * 和 ? 不匹配 /
+//   **/cache         ** 匹配任意多层目录（包括零层）
+//   logs/**          logs 里面的所有内容（不包括 logs 本身）
+//   !keep.log        以 ! 开头：重新包含前面的规则排除的条目（文件名以 ! 开头时写成 \!）
+// 多条规则匹配同一个条目时，最后一条起作用
+// 被排除的目录不会被进入，所以无法重新包含被排除的目录里面的条目（和 git 相同）
+// .eptdirignore 使用同样的语法，路径相对于 .eptdirignore 所在的目录，匹配的目录会被保留
+use std::ffi::OsStr;
+use std::path::{Component, Path};
+
+use crate::rules::{glob_match_units, name_units};
+use crate::MatchMode;
+
+// 一组排除 / 包含规则
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    patterns: Vec<PathPattern>,
+}
+
+// 一条规则
+#[derive(Debug, Clone)]
+struct PathPattern {
+    // 以 ! 开头（或者来自 include）：匹配的条目不排除
+    include: bool,
+    // 以 / 结尾：只匹配目录
+    dir_only: bool,
+    // 没有 / 的规则只和文件名比较；有 / 的规则和相对于目标目录的整个路径比较
+    anchored: bool,
+    segments: Vec<Segment>,
+}
+
+// 规则中用 / 分开的一段
+#[derive(Debug, Clone)]
+enum Segment {
+    // **：任意多层目录
+    AnyDirs,
+    // 普通的通配符（* ? [...]），匹配一层
+    Glob(Vec<Option<char>>),
+}
+
+impl PathFilter {
+    // exclude: 排除规则（可以用 ! 重新包含）；include: 重新包含的规则，排在所有排除规则之后，优先级最高
+    pub fn new<S: AsRef<str>>(exclude: &[S], include: &[S]) -> Self {
+        let mut filter = PathFilter::default();
+        for pattern in exclude {
+            filter.add(pattern.as_ref(), false);
+        }
+        for pattern in include {
+            filter.add(pattern.as_ref(), true);
+        }
+        filter
+    }
+
+    // 按 .gitignore 文件的格式读取规则：每行一条，忽略空行和 # 开头的注释行
+    pub fn from_lines(text: &str) -> Self {
+        let mut filter = PathFilter::default();
+        for line in text.lines() {
+            let line = line.trim_end();
+            if !line.is_empty() && !line.starts_with('#') {
+                filter.add(line, false);
+            }
+        }
+        filter
+    }
+
+    // 添加一条规则；include 为 true 时意义反过来（!pattern 表示排除）
+    // 空的规则被忽略
+    pub fn add(&mut self, pattern: &str, include: bool) {
+        if let Some(pattern) = PathPattern::parse(pattern, include) {
+            self.patterns.push(pattern);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    // relative: 相对于目标目录的路径；is_dir: 条目是不是目录（符号链接不算目录）
+    pub fn is_excluded(&self, relative: &Path, is_dir: bool) -> bool {
+        // 从后往前找第一条匹配的规则
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(relative, is_dir))
+            .is_some_and(|pattern| !pattern.include)
+    }
+}
+
+impl PathPattern {
+    fn parse(pattern: &str, include: bool) -> Option<PathPattern> {
+        let mut pattern = pattern;
+        let mut include = include;
+        if let Some(rest) = pattern.strip_prefix('!') {
+            include = !include;
+            pattern = rest;
+        } else if let Some(rest) = pattern.strip_prefix('\\') {
+            // \! 和 \# 表示文件名本身以 ! 或 # 开头
+            pattern = rest;
+        }
+
+        let dir_only = pattern.ends_with('/');
+        let pattern = pattern.trim_end_matches('/');
+        // 开头或中间有 / 的规则相对于目标目录
+        let anchored = pattern.contains('/');
+        let pattern = pattern.trim_start_matches('/');
+        if pattern.is_empty() {
+            return None;
+        }
+
+        let mut segments: Vec<Segment> = pattern
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .map(|part| match part {
+                "**" => Segment::AnyDirs,
+                _ => Segment::Glob(name_units(MatchMode::Exact, OsStr::new(part))),
+            })
+            .collect();
+        // 末尾的 ** 只匹配里面的内容，至少要有一层
+        if matches!(segments.last(), Some(Segment::AnyDirs)) && segments.len() > 1 {
+            segments.insert(segments.len() - 1, Segment::Glob(vec![Some('*')]));
+        }
+
+        Some(PathPattern { include, dir_only, anchored, segments })
+    }
+
+    fn matches(&self, relative: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if !self.anchored {
+            // 只有一段、不含 / 的规则：和文件名比较
+            return match (self.segments.first(), relative.file_name()) {
+                (Some(Segment::Glob(glob)), Some(name)) => glob_match_units(glob, &name_units(MatchMode::Exact, name)),
+                (Some(Segment::AnyDirs), Some(_)) => true,
+                _ => false,
+            };
+        }
+        let parts: Vec<&OsStr> = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(part) => Some(part),
+                _ => None,
+            })
+            .collect();
+        match_segments(&self.segments, &parts)
+    }
+}
+
+// 规则的各段和路径的各层逐一比较；** 依次尝试吃掉 0 层、1 层……
+fn match_segments(segments: &[Segment], parts: &[&OsStr]) -> bool {
+    match segments.split_first() {
+        None => parts.is_empty(),
+        Some((Segment::AnyDirs, rest)) => (0..=parts.len()).any(|skip| match_segments(rest, &parts[skip..])),
+        Some((Segment::Glob(glob), rest)) => match parts.split_first() {
+            Some((part, tail)) => {
+                glob_match_units(glob, &name_units(MatchMode::Exact, part)) && match_segments(rest, tail)
+            }
+            None => false,
+        },
+    }
+}
@@ -0,0 +1,218 @@
+// ========================================
+// --interactive：删除前逐个确认
+// ========================================
+// 先制定清理计划（见 plan.rs），把计划中的条目分组，每组问一次，最后只执行确认了的条目
+// 执行时仍然会重新确认每个条目，确认以后发生变化的条目照样跳过
+//
+// 分组：会被删除的目录和其中所有会被删除的内容（垃圾文件、嵌套的空文件夹）是一组，
+// 一个回答就能确认一整串嵌套的空文件夹；不在这样的目录中的垃圾文件各自一组
+// 回答：y 删除这一组，n 保留（默认），a 删除这一组和以后所有的组，q 不再询问，保留剩下的所有组
+// 提示和问题写到标准错误，标准输出留给 --json / --events
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use eptdir::{escape_path, CleanupPlan, PlanEntry, RemoveKind};
+
+// 每组最多列出多少个条目，更多的只显示数量
+const LISTED: usize = 10;
+
+// 一个回答
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Answer {
+    Yes,
+    No,
+    All,
+    Quit,
+}
+
+impl Answer {
+    // 解析输入的一行；空行是默认的"否"，无法识别时返回 None
+    fn parse(line: &str) -> Option<Self> {
+        match line.trim().to_lowercase().as_str() {
+            "y" | "yes" | "是" => Some(Answer::Yes),
+            "" | "n" | "no" | "否" => Some(Answer::No),
+            "a" | "all" | "全部" => Some(Answer::All),
+            "q" | "quit" | "退出" => Some(Answer::Quit),
+            _ => None,
+        }
+    }
+}
+
+// 询问的状态在多个目标之间保持：回答过 a 或 q 以后不再询问
+pub struct Prompter<R, W> {
+    input: R,
+    output: W,
+    all: bool,
+    quit: bool,
+}
+
+impl<R: BufRead, W: Write> Prompter<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Prompter { input, output, all: false, quit: false }
+    }
+
+    // 回答过 q（或者输入已经结束）：剩下的目标不再处理
+    pub fn quit(&self) -> bool {
+        self.quit
+    }
+
+    // 逐组询问，返回只包含确认了的条目的计划（顺序和原来的计划相同）
+    pub fn confirm(&mut self, plan: &CleanupPlan) -> io::Result<CleanupPlan> {
+        let groups = group(plan);
+        let mut approved: HashSet<usize> = HashSet::new();
+        for (number, (root, members)) in groups.iter().enumerate() {
+            if self.quit {
+                break;
+            }
+            if !self.all {
+                self.show(plan, number + 1, groups.len(), root, members)?;
+                match self.ask()? {
+                    Answer::Yes => {}
+                    Answer::No => continue,
+                    Answer::All => self.all = true,
+                    Answer::Quit => {
+                        self.quit = true;
+                        break;
+                    }
+                }
+            }
+            approved.extend(members);
+        }
+
+        let entries = plan
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| approved.contains(index))
+            .map(|(_, entry)| entry.clone())
+            .collect();
+        Ok(CleanupPlan { target: plan.target.clone(), entries })
+    }
+
+    // 显示一组：第一行是这一组的根，下面列出其中的其他条目
+    fn show(&mut self, plan: &CleanupPlan, number: usize, count: usize, root: &Path, members: &[usize]) -> io::Result<()> {
+        let out = &mut self.output;
+        // 根在组中一定存在：目录组的根是这个目录本身，文件组只有这个文件
+        let head = members.iter().map(|&index| &plan.entries[index]).find(|entry| entry.path == root);
+        let others: Vec<&PlanEntry> =
+            members.iter().map(|&index| &plan.entries[index]).filter(|entry| entry.path != root).collect();
+        writeln!(out)?;
+        match head {
+            Some(entry) if entry.kind == RemoveKind::Dir && !others.is_empty() => {
+                writeln!(out, "[{}/{}] {}: {}（连同其中 {} 个条目）", number, count, describe(entry), escape_path(root), others.len())?
+            }
+            Some(entry) => writeln!(out, "[{}/{}] {}: {}", number, count, describe(entry), escape_path(root))?,
+            None => writeln!(out, "[{}/{}] {}", number, count, escape_path(root))?,
+        }
+        for entry in others.iter().take(LISTED) {
+            writeln!(out, "    {}: {}", describe(entry), escape_path(&entry.path))?;
+        }
+        if others.len() > LISTED {
+            writeln!(out, "    ……还有 {} 个", others.len() - LISTED)?;
+        }
+        Ok(())
+    }
+
+    // 读取一个回答，无法识别时重新询问；输入结束时当作 q
+    fn ask(&mut self) -> io::Result<Answer> {
+        loop {
+            write!(self.output, "删除吗？[y] 是 / [n] 否 / [a] 全部 / [q] 退出（默认 n）: ")?;
+            self.output.flush()?;
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                writeln!(self.output)?;
+                return Ok(Answer::Quit);
+            }
+            match Answer::parse(&line) {
+                Some(answer) => return Ok(answer),
+                None => writeln!(self.output, "请输入 y、n、a 或 q")?,
+            }
+        }
+    }
+}
+
+// 条目的类型和大小
+fn describe(entry: &PlanEntry) -> String {
+    match entry.kind {
+        RemoveKind::JunkFile => format!("垃圾文件（{} 字节）", entry.size),
+        RemoveKind::EmptyFile => "空文件".to_string(),
+        RemoveKind::Dir => "空文件夹".to_string(),
+    }
+}
+
+// 把计划中的条目分组：键是组的根（最外层会被删除的目录，或者单独的文件），值是条目的下标
+// 组按根的路径排序，组内保持计划中的顺序
+fn group(plan: &CleanupPlan) -> Vec<(PathBuf, Vec<usize>)> {
+    let dirs: HashSet<&Path> =
+        plan.entries.iter().filter(|entry| entry.kind == RemoveKind::Dir).map(|entry| entry.path.as_path()).collect();
+    let mut groups: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+    for (index, entry) in plan.entries.iter().enumerate() {
+        // 向上找到最外层会被删除的目录：目录只有在其中的内容都被删除时才会被删除，所以它们是连续的一串
+        let mut root = entry.path.as_path();
+        while let Some(parent) = root.parent().filter(|parent| dirs.contains(parent)) {
+            root = parent;
+        }
+        groups.entry(root.to_path_buf()).or_default().push(index);
+    }
+    groups.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, kind: RemoveKind) -> PlanEntry {
+        PlanEntry { path: PathBuf::from(path), kind, size: 0, mtime: None, inode: None }
+    }
+
+    fn sample() -> CleanupPlan {
+        CleanupPlan {
+            target: PathBuf::from("/t"),
+            entries: vec![
+                entry("/t/a/thumbs.db", RemoveKind::JunkFile),
+                entry("/t/b/thumbs.db", RemoveKind::JunkFile),
+                entry("/t/a/b/c", RemoveKind::Dir),
+                entry("/t/a/b", RemoveKind::Dir),
+                entry("/t/a", RemoveKind::Dir),
+                entry("/t/d", RemoveKind::Dir),
+            ],
+        }
+    }
+
+    // 回答一串输入，返回确认了的条目
+    fn answer(input: &str) -> (Vec<String>, bool) {
+        let mut prompter = Prompter::new(input.as_bytes(), Vec::new());
+        let plan = prompter.confirm(&sample()).unwrap();
+        let paths = plan.entries.iter().map(|entry| entry.path.to_string_lossy().into_owned()).collect();
+        (paths, prompter.quit())
+    }
+
+    #[test]
+    fn test_group_cascades() {
+        let groups = group(&sample());
+        let roots: Vec<_> = groups.iter().map(|(root, members)| (root.to_string_lossy().into_owned(), members.clone())).collect();
+        assert_eq!(
+            roots,
+            vec![("/t/a".to_string(), vec![0, 2, 3, 4]), ("/t/b/thumbs.db".to_string(), vec![1]), ("/t/d".to_string(), vec![5])],
+            "期望一整串嵌套的空文件夹和其中的垃圾文件是一组"
+        );
+    }
+
+    #[test]
+    fn test_answers() {
+        let (paths, quit) = answer("y\nn\nn\n");
+        assert_eq!(paths, ["/t/a/thumbs.db", "/t/a/b/c", "/t/a/b", "/t/a"], "期望一个回答确认整组，并保持原来的顺序");
+        assert!(!quit);
+
+        let (paths, _) = answer("huh\n\na\n");
+        assert_eq!(paths, ["/t/b/thumbs.db", "/t/d"], "期望无法识别的回答重新询问，空行是否，a 确认剩下的所有组");
+
+        let (paths, quit) = answer("y\nq\n");
+        assert_eq!(paths.len(), 4, "期望 q 以后保留剩下的组");
+        assert!(quit, "期望记录退出");
+
+        let (paths, quit) = answer("");
+        assert!(paths.is_empty() && quit, "期望输入结束时当作退出");
+    }
+}
@@ -0,0 +1,312 @@
+// ========================================
+// 撤销日志
+// ========================================
+// 打开 CleanOptions::journal 后，每次清理都会向日志文件追加记录（JSON Lines，每行一个 JSON 对象）：
+//   {"run_id":"20261016-093000-1a2b","time":"2026-10-16T09:30:00Z","event":"start","target":"/data/photos"}
+//   {"run_id":"20261016-093000-1a2b","time":"2026-10-16T09:30:00Z","event":"remove","path":"/data/photos/a",
+//    "type":"dir","size":4096,"mtime_secs":1760600000,"mtime_nanos":0,"mode":"755","readonly":false,
+//    "quarantine":null}
+// 日志只追加、不修改，可以作为审计记录
+// undo() 按 run_id 找出一次运行删除的内容，倒序重新创建空目录并恢复权限和修改时间；
+// 隔离模式下删除的文件（quarantine 字段不为 null）也会被放回原位置
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::error::{EptdirError, Operation};
+use crate::escape::{path_from_json, path_json};
+use crate::sys::Meta;
+use crate::trash::{format_datetime, move_path, remove_trash_info};
+
+// 默认的日志文件：$XDG_STATE_HOME/eptdir/journal.jsonl，没有设置时是 ~/.local/state/eptdir/journal.jsonl
+pub fn default_path() -> Option<PathBuf> {
+    let state_home = match env::var_os("XDG_STATE_HOME").filter(|value| !value.is_empty()) {
+        Some(state_home) => PathBuf::from(state_home),
+        None => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(state_home.join("eptdir").join("journal.jsonl"))
+}
+
+// 被删除条目的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EntryKind {
+    File,
+    Dir,
+}
+
+// 一次清理的日志写入器
+// 多线程清理时各个线程共用同一个 Journal，写入只需要 &self
+pub(crate) struct Journal {
+    file: File,
+    path: PathBuf,
+    run_id: String,
+}
+
+impl Journal {
+    // 以追加模式打开日志文件（不存在时创建，包括所在的目录），并写入 start 记录
+    pub(crate) fn open(path: &Path, target: &Path) -> Result<Self, EptdirError> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|e| EptdirError::io(parent, Operation::WriteFile, e))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| EptdirError::io(path, Operation::WriteFile, e))?;
+
+        let journal = Journal { file, path: path.to_path_buf(), run_id: new_run_id() };
+        let target = absolute(target);
+        journal.write(json!({ "event": "start", "target": path_json(&target) }))?;
+        Ok(journal)
+    }
+
+    pub(crate) fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    // 记录一个被删除（或移到隔离位置）的条目
+    // metadata 必须在删除之前读取；mtime 不为 None 时代替 metadata 中的修改时间
+    pub(crate) fn record(
+        &self,
+        path: &Path,
+        kind: EntryKind,
+        metadata: &Meta,
+        mtime: Option<SystemTime>,
+        quarantine: Option<&Path>,
+    ) -> Result<(), EptdirError> {
+        let (mtime_secs, mtime_nanos) = match mtime.or(metadata.modified) {
+            Some(time) => split_time(time),
+            None => (0, 0),
+        };
+        self.write(json!({
+            "event": "remove",
+            "path": path_json(&absolute(path)),
+            "type": match kind {
+                EntryKind::File => "file",
+                EntryKind::Dir => "dir",
+            },
+            "size": metadata.len,
+            "mtime_secs": mtime_secs,
+            "mtime_nanos": mtime_nanos,
+            // Unix 权限位，例如 "755"；其他平台没有权限位，是 null
+            "mode": metadata.mode.map(|mode| format!("{:o}", mode)),
+            "readonly": metadata.readonly,
+            "quarantine": quarantine.map(|dest| path_json(&absolute(dest))),
+        }))
+    }
+
+    // 写入一行记录：每行都带上 run_id 和时间
+    // 整行用一次 write_all 写入，多个进程（或线程）同时追加时也不会交错
+    // &File 也实现了 Write，追加模式下每次写入都在文件末尾，不需要加锁
+    fn write(&self, mut record: Value) -> Result<(), EptdirError> {
+        record["run_id"] = Value::String(self.run_id.clone());
+        record["time"] = Value::String(format!("{}Z", format_datetime(SystemTime::now(), "-", "T", ":")));
+        let mut line = record.to_string();
+        line.push('\n');
+        (&self.file)
+            .write_all(line.as_bytes())
+            .map_err(|e| EptdirError::io(&self.path, Operation::WriteFile, e))
+    }
+}
+
+// 生成运行 ID：时间戳 + 4 位十六进制数（由进程号和纳秒数混合而成），同一秒内的多次运行也不会重复
+fn new_run_id() -> String {
+    let now = SystemTime::now();
+    let nanos = now.duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    let mix = (nanos ^ std::process::id().rotate_left(16)) & 0xffff;
+    format!("{}-{:04x}", format_datetime(now, "", "-", ""), mix)
+}
+
+// 日志中保存绝对路径，这样在任何目录下都能撤销
+pub(crate) fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+// 把时间拆成 1970-01-01 以来的 (秒, 纳秒)，更早的时间秒数为负
+pub(crate) fn split_time(time: SystemTime) -> (i64, u32) {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        Err(e) => {
+            let d = e.duration();
+            if d.subsec_nanos() == 0 {
+                (-(d.as_secs() as i64), 0)
+            } else {
+                (-(d.as_secs() as i64) - 1, 1_000_000_000 - d.subsec_nanos())
+            }
+        }
+    }
+}
+
+// split_time 的逆操作
+pub(crate) fn join_time(secs: i64, nanos: u32) -> SystemTime {
+    if secs >= 0 {
+        UNIX_EPOCH + Duration::new(secs as u64, nanos)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs()) + Duration::from_nanos(nanos as u64)
+    }
+}
+
+// ========================================
+// 撤销
+// ========================================
+
+// 一次撤销的结果
+#[derive(Debug)]
+pub struct UndoReport {
+    pub run_id: String,
+    // 重新创建或放回的路径
+    pub restored: Vec<PathBuf>,
+    // 被永久删除、无法恢复的文件（没有使用隔离模式）
+    pub unrecoverable: Vec<PathBuf>,
+    // 恢复失败的条目
+    pub errors: Vec<EptdirError>,
+}
+
+// 日志中的一条 remove 记录
+struct Removed {
+    path: PathBuf,
+    kind: EntryKind,
+    mtime: SystemTime,
+    // Unix 权限位
+    mode: Option<u32>,
+    readonly: bool,
+    quarantine: Option<PathBuf>,
+}
+
+// 撤销一次运行：倒序（父目录在前）重新创建被删除的空目录，放回隔离的文件，
+// 然后正序（子目录在前）恢复权限和修改时间，避免创建子目录时又改掉父目录的修改时间
+// 已经存在的路径不会被覆盖
+pub fn undo(journal: &Path, run_id: &str) -> Result<UndoReport, EptdirError> {
+    let content = fs::read_to_string(journal).map_err(|e| EptdirError::io(journal, Operation::ReadFile, e))?;
+
+    let mut found = false;
+    let mut removed: Vec<Removed> = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        // 跳过空行；无法解析的行报告为配置错误，不继续猜测
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: Value = serde_json::from_str(line).map_err(|e| EptdirError::Config {
+            path: journal.to_path_buf(),
+            line: index + 1,
+            message: e.to_string(),
+        })?;
+        if record["run_id"] != run_id {
+            continue;
+        }
+        found = true;
+        if record["event"] == "remove"
+            && let Some(entry) = parse_removed(&record)
+        {
+            removed.push(entry);
+        }
+    }
+    if !found {
+        return Err(EptdirError::RunNotFound(run_id.to_string()));
+    }
+
+    let mut report =
+        UndoReport { run_id: run_id.to_string(), restored: Vec::new(), unrecoverable: Vec::new(), errors: Vec::new() };
+    // 第一遍：倒序重新创建
+    let mut created: Vec<&Removed> = Vec::new();
+    for entry in removed.iter().rev() {
+        if entry.kind == EntryKind::File && entry.quarantine.is_none() {
+            report.unrecoverable.push(entry.path.clone());
+            continue;
+        }
+        if entry.path.symlink_metadata().is_ok() {
+            // 目录已经被重新创建过，不需要再做什么
+            if entry.kind == EntryKind::Dir && entry.path.is_dir() {
+                continue;
+            }
+            report.errors.push(EptdirError::io(
+                &entry.path,
+                Operation::Restore,
+                io::Error::new(io::ErrorKind::AlreadyExists, "原位置已经存在"),
+            ));
+            continue;
+        }
+        if let Some(parent) = entry.path.parent()
+            && let Err(e) = fs::create_dir_all(parent)
+        {
+            report.errors.push(EptdirError::io(parent, Operation::Restore, e));
+            continue;
+        }
+
+        let result = match (entry.kind, &entry.quarantine) {
+            (EntryKind::File, Some(dest)) => move_path(dest, &entry.path),
+            _ => fs::create_dir(&entry.path),
+        };
+        match result {
+            Ok(()) => {
+                report.restored.push(entry.path.clone());
+                created.push(entry);
+            }
+            Err(e) => report.errors.push(EptdirError::io(&entry.path, Operation::Restore, e)),
+        }
+    }
+
+    // 第二遍：正序恢复权限和修改时间，并清理隔离位置中留下的内容
+    for entry in created.into_iter().rev() {
+        if let Some(dest) = &entry.quarantine {
+            if entry.kind == EntryKind::Dir {
+                // 隔离位置中对应的空目录已经没用了
+                fs::remove_dir(dest).ok();
+            }
+            remove_trash_info(dest);
+        }
+        if let Err(e) = restore_attributes(entry) {
+            report.errors.push(EptdirError::io(&entry.path, Operation::Restore, e));
+        }
+    }
+
+    Ok(report)
+}
+
+// 从 JSON 记录中取出需要的字段，字段缺失时返回 None
+fn parse_removed(record: &Value) -> Option<Removed> {
+    let kind = match record["type"].as_str()? {
+        "file" => EntryKind::File,
+        "dir" => EntryKind::Dir,
+        _ => return None,
+    };
+    Some(Removed {
+        path: path_from_json(&record["path"])?,
+        kind,
+        mtime: join_time(record["mtime_secs"].as_i64()?, record["mtime_nanos"].as_u64()? as u32),
+        mode: record["mode"].as_str().and_then(|mode| u32::from_str_radix(mode, 8).ok()),
+        readonly: record["readonly"].as_bool().unwrap_or(false),
+        quarantine: path_from_json(&record["quarantine"]),
+    })
+}
+
+// 恢复修改时间和权限：先改时间（需要打开条目），再改权限（权限可能不允许再打开）
+fn restore_attributes(entry: &Removed) -> io::Result<()> {
+    open_for_times(&entry.path)?.set_modified(entry.mtime)?;
+
+    let mut permissions = fs::metadata(&entry.path)?.permissions();
+    permissions.set_readonly(entry.readonly);
+    // Unix 上有完整的权限位时以权限位为准
+    #[cfg(unix)]
+    if let Some(mode) = entry.mode {
+        use std::os::unix::fs::PermissionsExt;
+        permissions.set_mode(mode);
+    }
+    fs::set_permissions(&entry.path, permissions)
+}
+
+// 打开条目用于修改时间；Windows 打开目录需要 FILE_FLAG_BACKUP_SEMANTICS
+#[cfg(windows)]
+pub(crate) fn open_for_times(path: &Path) -> io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+    OpenOptions::new().write(true).custom_flags(0x0200_0000).open(path)
+}
+
+#[cfg(not(windows))]
+pub(crate) fn open_for_times(path: &Path) -> io::Result<File> {
+    File::open(path)
+}
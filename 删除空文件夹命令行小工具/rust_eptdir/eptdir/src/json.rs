@@ -0,0 +1,238 @@
+// ========================================
+// --json 的输出格式
+// ========================================
+// 每次运行在所有目标都处理完以后，向标准输出写一个 JSON 文档；
+// 这时标准输出上没有其他内容，警告和错误的文字说明写到标准错误
+//
+// 格式带版本号 schema_version：只增加字段时版本号不变，删除字段或者改变字段含义时加一
+// 取值列表（status、reason、root_status、事件名）增加新的取值也不改变版本号（例如 vetoed、changed），
+// 使用者遇到不认识的取值时应该当作"其他"处理，而不是报错
+//
+// {
+//   "schema_version": 1,
+//   "eptdir_version": "0.1.0",
+//   "dry_run": false,
+//   "exit_status": 1,                      进程的退出码（0 成功，1 有目标或条目清理失败）
+//   "counts": {                            所有目标加起来的数量
+//     "targets": 2,
+//     "succeeded": 1,                      没有任何错误的目标
+//     "failed": 1,                         有条目处理失败，或者整个目标清理失败
+//     "skipped": 0,                        不存在或者不是目录的目标
+//     "removed_junk_files": 3,
+//     "removed_empty_files": 0,
+//     "removed_dirs": 2,                   不包括被删除的目标目录本身，它的去留见每个目标的 root_status
+//     "errors": 1,                         所有目标报告中的错误，加上整个目标失败的错误
+//     "bytes_freed": 1234
+//   },
+//   "targets": [                           按命令行中的顺序
+//     {
+//       "path": "/data/a",
+//       "status": "succeeded",             succeeded / failed / not_found / not_a_directory
+//       "report": { ... },                 清理报告，见下；目标没有被清理时为 null
+//       "error": null                      整个目标失败时的错误对象，否则为 null
+//     }
+//   ]
+// }
+//
+// 清理报告（CleanReport::to_json）：
+//   target, dry_run
+//   removed_junk_files, removed_empty_files, removed_dirs    路径列表（预览模式下是将删除的内容）；
+//                  root_status 为 removed 时 removed_dirs 的最后一个是目标目录本身
+//   skipped        [{ "path", "reason" }]，reason 是 symlink / symlink_loop / excluded / kept / too_new / vetoed /
+//                  changed（执行清理计划时，条目在计划以后发生了变化，或者计划中没有修改时间、无法确认）
+//   errors         错误对象列表
+//   bytes_freed, elapsed_ms
+//   root_status    not_empty / kept_empty / removed
+//   quarantine     隔离位置，没有隔离时为 null
+//   run_id         撤销日志中的运行 ID，没有写日志时为 null
+//   syscalls       { "read_dir", "stat", "remove" }
+//
+// 错误对象（report::error_json）：{ "path", "operation", "kind", "message" }，不适用的字段为 null
+// 路径：有效的 UTF-8 时是字符串，否则是 { "escaped": "$'...'", "hex": "原始字节的十六进制" }
+//
+// --events：清理过程中每发生一件事就在标准输出写一行 JSON（NDJSON），可以用 tail -f 之类的方式实时读取
+// 每行都有 "event" 字段，库产生的事件见 events.rs：
+//   target_started     { target, dry_run }
+//   dir_entered        { path }
+//   junk_removed       { path, bytes }
+//   empty_file_removed { path }
+//   dir_removed        { path }
+//   error              { error }                       跳过的条目的错误
+//   target_finished    { target, removed_junk_files, removed_empty_files, removed_dirs, skipped, errors,
+//                        bytes_freed, elapsed_ms, root_status, error }    error 是终止清理的错误，
+//                      数量和 counts 的含义相同（removed_dirs 不包括目标目录本身）
+// 命令行自己另外产生两种：
+//   target_skipped     { path, status, error }         不存在（not_found）或不是目录（not_a_directory）的目标
+//   run_finished       { schema_version, dry_run, exit_status, counts }   最后一行，counts 和上面相同
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use eptdir::escape::path_json;
+use eptdir::report::error_json;
+use eptdir::{CleanReport, EptdirError, EventSink};
+
+// 格式的版本号
+pub const SCHEMA_VERSION: u32 = 1;
+
+// 所有目标加起来的数量（文字汇总和 --events 也使用）
+#[derive(Debug, Default)]
+pub struct Totals {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub removed_junk_files: usize,
+    pub removed_empty_files: usize,
+    pub removed_dirs: usize,
+    pub errors: usize,
+    pub bytes_freed: u64,
+}
+
+impl Totals {
+    // 累加一个清理完成的目标；返回它的状态
+    pub fn add_report(&mut self, report: &CleanReport) -> &'static str {
+        self.removed_junk_files += report.removed_junk_files.len();
+        self.removed_empty_files += report.removed_empty_files.len();
+        self.removed_dirs += report.removed_dir_count();
+        self.errors += report.errors.len();
+        self.bytes_freed += report.bytes_freed;
+        // 有条目处理失败的目录不算成功
+        if report.errors.is_empty() {
+            self.succeeded += 1;
+            "succeeded"
+        } else {
+            self.failed += 1;
+            "failed"
+        }
+    }
+
+    // 累加一个没能清理的目标；返回它的状态
+    pub fn add_error(&mut self, error: &EptdirError) -> &'static str {
+        match error {
+            // 目标不存在或不是目录：只警告，不算失败
+            EptdirError::TargetNotFound(_) => {
+                self.skipped += 1;
+                "not_found"
+            }
+            EptdirError::NotADirectory(_) => {
+                self.skipped += 1;
+                "not_a_directory"
+            }
+            _ => {
+                self.failed += 1;
+                self.errors += 1;
+                "failed"
+            }
+        }
+    }
+}
+
+// 一个目标的结果
+pub fn target(path: &Path, status: &str, report: Option<&CleanReport>, error: Option<&EptdirError>) -> Value {
+    json!({
+        "path": path_json(path),
+        "status": status,
+        "report": report.map(CleanReport::to_json),
+        "error": error.map(error_json),
+    })
+}
+
+// 整次运行的文档
+pub fn document(dry_run: bool, exit_status: i32, totals: &Totals, targets: Vec<Value>) -> Value {
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "eptdir_version": env!("CARGO_PKG_VERSION"),
+        "dry_run": dry_run,
+        "exit_status": exit_status,
+        "counts": counts(totals, targets.len()),
+        "targets": targets,
+    })
+}
+
+// --events 的最后一行
+pub fn run_finished(dry_run: bool, exit_status: i32, totals: &Totals, targets: usize) -> Value {
+    json!({
+        "event": "run_finished",
+        "schema_version": SCHEMA_VERSION,
+        "dry_run": dry_run,
+        "exit_status": exit_status,
+        "counts": counts(totals, targets),
+    })
+}
+
+// --events 中没有清理的目标（不存在或不是目录）
+pub fn target_skipped(path: &Path, status: &str, error: &EptdirError) -> Value {
+    json!({
+        "event": "target_skipped",
+        "path": path_json(path),
+        "status": status,
+        "error": error_json(error),
+    })
+}
+
+// 把库产生的事件逐行写到标准输出
+pub fn event_sink() -> EventSink {
+    EventSink::new(|event| write_line(&event.to_json()))
+}
+
+// 写一行 JSON；标准输出按行缓冲，每行写完读取的一方就能看到
+// 读取的一方提前退出（管道关闭）时忽略写入错误，不中断清理
+pub fn write_line(value: &Value) {
+    // lock() 保证多线程同时写入时每行完整，不会交错
+    let _ = writeln!(io::stdout().lock(), "{}", value);
+}
+
+// 所有目标加起来的数量
+fn counts(totals: &Totals, targets: usize) -> Value {
+    json!({
+        "targets": targets,
+        "succeeded": totals.succeeded,
+        "failed": totals.failed,
+        "skipped": totals.skipped,
+        "removed_junk_files": totals.removed_junk_files,
+        "removed_empty_files": totals.removed_empty_files,
+        "removed_dirs": totals.removed_dirs,
+        "errors": totals.errors,
+        "bytes_freed": totals.bytes_freed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    use eptdir::RootStatus;
+
+    #[test]
+    fn test_document() {
+        let mut totals = Totals::default();
+        let mut report = CleanReport::new(PathBuf::from("/data/a"), false);
+        report.removed_dirs.push(PathBuf::from("/data/a/empty"));
+        // --remove-root 删除了目标目录本身：列在报告中，但不计入数量
+        report.removed_dirs.push(PathBuf::from("/data/a"));
+        report.root_status = RootStatus::Removed;
+        report.bytes_freed = 10;
+        let status = totals.add_report(&report);
+        let missing = EptdirError::TargetNotFound(PathBuf::from("/data/b"));
+        let missing_status = totals.add_error(&missing);
+        let targets = vec![
+            target(Path::new("/data/a"), status, Some(&report), None),
+            target(Path::new("/data/b"), missing_status, None, Some(&missing)),
+        ];
+
+        let document = document(false, 0, &totals, targets);
+        assert_eq!(document["schema_version"], SCHEMA_VERSION, "期望带格式版本号");
+        assert_eq!(document["exit_status"], 0, "期望记录退出码");
+        assert_eq!(document["counts"]["targets"], 2, "期望两个目标");
+        assert_eq!(document["counts"]["succeeded"], 1, "期望一个目标成功");
+        assert_eq!(document["counts"]["skipped"], 1, "期望一个目标被跳过");
+        assert_eq!(document["counts"]["removed_dirs"], 1, "期望累加删除的目录，不包括目标目录本身");
+        assert_eq!(document["targets"][0]["report"]["root_status"], "removed", "期望目标目录的去留记录在 root_status 中");
+        assert_eq!(document["targets"][0]["report"]["removed_dirs"][0], "/data/a/empty", "期望包含清理报告");
+        assert_eq!(document["targets"][1]["status"], "not_found", "期望记录目标的状态");
+        assert!(document["targets"][1]["report"].is_null(), "期望没有清理的目标没有报告");
+        assert_eq!(document["targets"][1]["error"]["path"], "/data/b", "期望记录目标的错误");
+    }
+}
@@ -1,232 +1,1518 @@
 // std::fs - 文件系统操作（读取目录、删除文件等）
 // std::path - 路径处理（Path, PathBuf 等）
+// std::collections - 集合类型（HashMap 用于记录目录改动之前的修改时间）
+use std::collections::HashMap;
+// std::ffi::OsString - 文件名（Unix 上可以是任意字节）
+use std::ffi::{OsStr, OsString};
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+// std::sync - 多线程清理时在线程之间共享的状态（Arc 共享所有权，Mutex 互斥访问）
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+// std::time::Instant - 用于统计清理耗时
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-// 垃圾文件列表常量
+// rayon - 工作窃取的线程池，用于并行遍历子目录
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+// 垃圾文件规则模块（精确文件名、通配符、正则表达式）
+pub mod rules;
+pub use rules::{JunkRule, JunkRules, MatchMode};
+
+// 清理报告模块
+pub mod report;
+pub use report::{CleanReport, RootStatus, SkipReason, SkippedEntry, SyscallCounts};
+
+// 错误类型模块
+pub mod error;
+pub use error::{EptdirError, Operation};
+
+// 排除和包含规则（gitignore 风格）
+pub mod filter;
+pub use filter::PathFilter;
+
+// 非 UTF-8 路径的转义和编码
+pub mod escape;
+pub use escape::escape_path;
+
+// 隔离目录（回收站）模块
+pub mod trash;
+pub use trash::{restore, Quarantine, RestoreReport};
+use trash::Mover;
+
+// 撤销日志模块
+pub mod journal;
+pub use journal::{undo, UndoReport};
+use journal::{EntryKind, Journal};
+
+// 清理过程的观察者（通知和否决删除）
+pub mod observer;
+pub use observer::{CleanupObserver, RemoveKind};
+
+// 清理过程中的事件（实时进度）
+pub mod events;
+pub use events::{Event, EventSink};
+
+// 清理计划：先计划、审阅，再执行
+pub mod plan;
+pub use plan::{execute, plan, CleanupPlan, PlanEntry};
+
+// 平台相关的目录操作（Unix 上相对于目录的文件描述符进行）
+mod sys;
+use sys::{DirHandle, DirId, FileKind, Meta};
+
+// 内置的垃圾文件列表常量，也是 JunkRules::default() 的内容
 pub const JUNK_FILES: &[&str] = &["thumbs.db", ".DS_Store"];
 
+// 内置的保留标记文件：目录中有这些文件时，目录永远不会被删除，标记文件本身也不会被当作垃圾文件
+pub const KEEP_MARKERS: &[&str] = &[".gitkeep", ".keep", ".eptdir-keep"];
+
+// options.empty_files 开启时也不删除的 0 字节文件：这些文件为空是正常的
+pub const EMPTY_FILE_SENTINELS: &[&str] =
+    &["__init__.py", "py.typed", ".gitkeep", ".keep", ".eptdir-keep", ".gitignore", ".nojekyll"];
+
+// 列出要保留的目录的文件（gitignore 风格，路径相对于这个文件所在的目录，语法见 filter.rs）
+// 可以放在任意一层目录中；它本身也算一个保留标记
+pub const IGNORE_FILE: &str = ".eptdirignore";
+
+// 清理选项
+// #[derive(Default)] 自动生成默认值：所有 bool 字段默认为 false
+// 这样调用者可以写 CleanOptions::default() 得到"正常删除"的行为
+#[derive(Debug, Clone, Default)]
+pub struct CleanOptions {
+    // 预览模式（dry-run）：只遍历并报告将要删除的垃圾文件和空文件夹，不修改磁盘
+    pub dry_run: bool,
+    // 遇到符号链接时怎么办，默认跳过
+    pub symlinks: SymlinkPolicy,
+    // 目标目录（根目录）清理后为空时是否也删除它
+    // 默认 false：保留根目录，避免把用户当前所在的目录删掉
+    pub remove_root: bool,
+    // 遇到错误（无法读取的目录、被占用的文件等）时是否继续
+    // true：把错误记录到报告的 errors 中，跳过出错的条目继续遍历
+    // false（默认）：遇到第一个错误就停止，clean_directory 返回 Err
+    pub continue_on_error: bool,
+    // 最大遍历深度：根目录中的条目深度为 1，None 表示不限制，Some(0) 表示不处理任何条目
+    // 达到深度限制的子目录不会被进入，只有它本来就是空的时候才会被删除
+    pub max_depth: Option<usize>,
+    // 排除规则（gitignore 风格，相对于目标目录，例如 node_modules、*.bak、/build、tmp/），语法见 filter.rs
+    // 匹配的文件不会被删除，匹配的目录不会被进入，也不会被删除；排除的目录会记录到报告的 skipped 中
+    pub exclude: Vec<String>,
+    // 重新包含的规则：匹配的条目即使匹配 exclude 也照常处理（相当于排在最后的 !规则）
+    pub include: Vec<String>,
+    // 同时删除 0 字节的普通文件（下载失败、同步工具留下的占位文件），报告中单独记录在 removed_empty_files
+    // 名字在 EMPTY_FILE_SENTINELS 中或者匹配 empty_file_sentinels 的文件不删除；remove_empty_dirs() 不使用这个选项
+    pub empty_files: bool,
+    // 额外的不删除的 0 字节文件名（通配符，例如 *.lock）
+    pub empty_file_sentinels: Vec<String>,
+    // 额外的保留标记文件名（精确匹配），和内置的 KEEP_MARKERS 一起使用
+    // 有保留标记的目录不会被删除：其中的垃圾文件照常删除，标记文件和目录本身留下
+    pub keep_markers: Vec<String>,
+    // 不使用保留标记和 .eptdirignore（标记文件当作普通文件，匹配垃圾文件规则时照样删除）
+    pub no_keep_markers: bool,
+    // "实际上为空"的条件：设置后，只剩下满足条件的文件的目录也当作空目录，这些文件和目录一起删除；
+    // 目录里还有其他内容时，这些文件原样保留。None（默认）表示只删除真正的空目录
+    // 主要用于 remove_empty_dirs()；clean_directory() 中垃圾文件仍然先删除，条件只作用于剩下的文件
+    pub empty_predicate: Option<EmptyPredicate>,
+    // 隔离位置：设置后垃圾文件和空文件夹被移动到这里，而不是永久删除，可以用 restore() 放回
+    // 隔离位置在目标目录里面时会被自动跳过
+    pub quarantine: Option<Quarantine>,
+    // 撤销日志文件：设置后每次（非预览的）清理都会把删除的条目追加到这个文件，可以用 undo() 撤销
+    pub journal: Option<PathBuf>,
+    // 最小年龄：只删除时间（见 age_time）早于"现在减去 min_age"的文件和目录，None 表示不限制
+    // 刚刚变空的目录可能属于一个还在运行的任务；目录的时间在进入它、删除其中的内容之前读取
+    // 太新而没有删除的条目记录到报告的 skipped 中
+    pub min_age: Option<Duration>,
+    // min_age 比较哪一个时间，默认是修改时间
+    pub age_time: AgeTime,
+    // 观察者：清理过程中得到通知（进入目录、发现和删除条目、错误等），还可以否决删除，见 observer.rs
+    // None 表示没有观察者
+    pub observer: Option<Arc<dyn CleanupObserver>>,
+    // 并行遍历使用的线程数，0 和 1 都表示单线程（默认）
+    // 多线程时各个子目录分给线程池中的线程处理；删除空文件夹仍然严格自底向上：
+    // 一个目录的所有子目录都处理完以后，才会检查它本身是否为空
+    pub threads: usize,
+}
+
+// 符号链接的处理策略
+// Windows 上的目录联接（junction）在 Rust 标准库里同样被识别为符号链接
+// 符号链接本身永远不会被删除，也不会被当作垃圾文件
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    // 静默跳过
+    #[default]
+    Skip,
+    // 跳过，并记录到报告的 skipped 列表中
+    Report,
+    // 进入链接指向的目录继续清理（链接可能指向目标目录以外的位置！）
+    // 通过记录当前遍历路径上的所有目录来检测循环
+    Follow,
+}
+
+// CleanOptions::min_age 使用的时间
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AgeTime {
+    // 修改时间（mtime）
+    #[default]
+    Modified,
+    // 状态改变时间（ctime）：改名、修改权限也会更新；没有 ctime 的平台使用修改时间
+    Changed,
+    // 访问时间（atime）；很多文件系统用 relatime 或 noatime 挂载，访问时间不一定准确
+    Accessed,
+}
+
+impl AgeTime {
+    // 命令行和配置文件中的写法：mtime、ctime、atime
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "mtime" => Some(AgeTime::Modified),
+            "ctime" => Some(AgeTime::Changed),
+            "atime" => Some(AgeTime::Accessed),
+            _ => None,
+        }
+    }
+}
+
+// 解析时长：数字加单位 s（秒）、m（分钟）、h（小时）、d（天）、w（周），例如 90s、2h、7d
+// 格式不对时返回 None
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    // 最后一个字符是单位，前面是数字
+    let unit = text.chars().last()?;
+    let number: u64 = text[..text.len() - unit.len_utf8()].parse().ok()?;
+    let seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    number.checked_mul(seconds).map(Duration::from_secs)
+}
+
+// 目录"实际上为空"的条件：哪些文件可以和目录一起删除
+#[derive(Clone)]
+pub enum EmptyPredicate {
+    // 匹配这些规则的文件；EmptyPredicate::junk() 使用内置的垃圾文件规则
+    Rules(JunkRules),
+    // 0 字节的文件
+    ZeroByte,
+    // 自定义条件
+    Custom(FilePredicate),
+}
+
+// 自定义条件：参数是文件的完整路径和大小（字节）
+// 多线程清理时会在多个线程中调用，所以要求 Send + Sync
+pub type FilePredicate = Arc<dyn Fn(&Path, u64) -> bool + Send + Sync>;
+
+impl EmptyPredicate {
+    // 只有垃圾文件（thumbs.db、.DS_Store）的目录也算空目录
+    pub fn junk() -> Self {
+        EmptyPredicate::Rules(JunkRules::default())
+    }
+}
+
+// 闭包没有实现 Debug，手动实现（CleanOptions 需要 Debug）
+impl fmt::Debug for EmptyPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmptyPredicate::Rules(rules) => f.debug_tuple("Rules").field(rules).finish(),
+            EmptyPredicate::ZeroByte => f.write_str("ZeroByte"),
+            EmptyPredicate::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
 // 函数返回类型说明：
-// Result<(), Box<dyn std::error::Error>>
+// Result<CleanReport, EptdirError>
 //   - Result: Rust 的错误处理类型，表示可能成功或失败
-//   - (): 单元类型，表示成功时不需要返回值（只有成功/失败的状态）
-//   - Box<dyn std::error::Error>: 失败时盒子里是任意类型的错误对象
-//     * Box: 堆分配的智能指针，用于存储不同大小的错误对象
-pub fn clean_directory(target_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    //检查目录是否存在
-    if !target_dir.exists() {
-        // eprintln! 是错误输出宏
-        eprintln!("警告: 目录不存在，跳过: {}", target_dir.display());
-        return Ok(()); // 返回成功，但跳过这个目录
-    }
-    
-    // 检查是否是目录
-    if !target_dir.is_dir() {
-        eprintln!("警告: 该路径不是目录，跳过: {}", target_dir.display());
-        return Ok(()); // 返回成功，但跳过这个路径
-    }
-    
-    println!("开始清理目录: {}", target_dir.display());
-    println!(); // 空行
-    
-    //删除垃圾文件
-    println!("删除垃圾文件...");
-    
-    // match 表达式：模式匹配 Result 类型
-    // remove_junk_files() 返回 Result<usize, Error>
-    match remove_junk_files(target_dir) {
-        // Ok 分支：成功删除垃圾文件
-        Ok(count) => {
-            // count 是删除的文件数量
-            println!("已删除 {} 个垃圾文件", count);
-        }
-        // Err 分支：删除过程中出错
-        Err(e) => {
-            // e 是错误对象，自动转换为字符串
-            eprintln!("删除垃圾文件时出错: {}", e);
-            return Err(e);
-        }
-    }
-    
-    println!(); // 空行
-
-    println!("删除空文件夹...");
-    
-    // match 表达式：模式匹配 Result 类型
-    match remove_empty_dirs(target_dir) {
-        // Ok 分支：成功（不关心返回值，用 _ 忽略）
-        Ok(_) => {
-            println!("空文件夹清理完成");
-        }
-        // Err 分支：出错
-        Err(e) => {
-            eprintln!("删除空文件夹时出错: {}", e);
-            return Err(e); // 返回错误
-        }
-    }
-    
-    println!(); // 空行
-    println!("目录清理完成: {}", target_dir.display());
-    println!(); // 空行
-    
-    Ok(()) // 返回成功
-}
-
-
-pub fn remove_junk_files(dir: &Path) -> Result<usize, Box<dyn std::error::Error>> {
-    // mut 关键字表示这个变量可以修改（可变变量）
-    // usize 是 Rust 中的无符号整数类型，用于计数
-    let mut deleted_count = 0;
-
-    // fs::read_dir(dir) 读取目录内容返回 Result<ReadDir, Error>
-    // ? 操作符：如果出错就立即返回错误，如果成功就继续执行
-    let entries = fs::read_dir(dir)?;
-    
-
-    // 第一阶段：先删除垃圾文件（深度优先，从外到内）
-    // entries 是一个迭代器，可以逐个访问目录中的文件/文件夹
-    for entry in entries {
-        // entry 的类型是 Result<DirEntry
-        // 使用 ? 操作符：如果 entry 是 Err，就返回错误；如果是 Ok，则取出 DirEntry
-        let entry = entry?;
-        
-        // entry.path() 获取这个条目的完整路径
-        // path 的类型是 PathBuf（可变的路径类型）
-        let path = entry.path();
-        
-        //检查路径是否是文件，只处理文件，跳过目录
-        if path.is_file() {
-
-            // 获取文件名
-            // path.file_name() 返回 Option<&OsStr>
-            // Option 表示可能没有值（None）或有值（Some(值)）
-            // if let Some(file_name) = ... 如果是 Some，将值绑定到 file_name；如果是 None，跳过
-            // 这里处理路径可能没有文件名的情况
-            if let Some(file_name) = path.file_name() {
-
-                // file_name 是 &OsStr 类型，需要转换为字符串
-                // to_str() 返回 Option<&str>，因为文件名可能包含无效的 UTF-8
-                if let Some(name_str) = file_name.to_str() {
-
-                    // name_str 现在是 &str 类型（字符串切片）
-                    // JUNK_FILES.contains(&name_str) 检查文件名是否在垃圾文件列表中
-                    // &name_str 创建一个引用，因为 contains 需要引用类型
-                    if JUNK_FILES.contains(&name_str) {
-
-                        // 打印要删除的文件路径
-                        // path.display() 将路径转换为可显示的字符串
-                        println!("删除垃圾文件: {}", path.display());
-                        
-                        // 删除文件
-                        // fs::remove_file() 删除文件，返回 Result
-                        // ? 操作符处理可能的错误（比如文件被占用等）
-                        fs::remove_file(&path)?;
-                        
-                        // 增加删除计数
-                        deleted_count += 1;
+//   - CleanReport: 成功时返回清理报告（删除了什么、跳过了什么、根目录的去留等）
+//   - EptdirError: 失败时的错误，目标不存在、不是目录都有各自的变体
+// rules: 判断垃圾文件的规则集合，一般用 JunkRules::default()
+// 这个函数不打印任何内容，怎么展示报告由调用者决定
+pub fn clean_directory(
+    target_dir: &Path,
+    rules: &JunkRules,
+    options: &CleanOptions,
+) -> Result<CleanReport, EptdirError> {
+    // 记录开始时间，用于计算耗时
+    let start = Instant::now();
+
+    // 检查目录是否存在、是否是目录
+    // 一次 fs::metadata() 同时得到两个答案（它和 is_dir() 一样会跟随符号链接）
+    match fs::metadata(target_dir) {
+        Ok(metadata) if metadata.is_dir() => {}
+        Ok(_) => return Err(EptdirError::NotADirectory(target_dir.to_path_buf())),
+        Err(_) => return Err(EptdirError::TargetNotFound(target_dir.to_path_buf())),
+    }
+
+    let mut cleaner = Cleaner::new(target_dir, rules, options, true);
+    cleaner.report.syscalls.stat += 1;
+    // 只遍历一次（后序遍历）：进入目录时删除垃圾文件，
+    // 所有子目录都处理完以后，根据遍历时数出的剩余条目判断目录是否已经变空
+    cleaner.run(target_dir, start, |cleaner| cleaner.clean_root(target_dir))?;
+    Ok(cleaner.report) // 返回成功
+}
+
+
+// rules: 判断垃圾文件的规则集合，库的调用者可以传入自己的规则
+// 返回删除（预览模式下是将删除）的垃圾文件数量，开启 options.empty_files 时包括删除的 0 字节文件
+pub fn remove_junk_files(
+    dir: &Path,
+    rules: &JunkRules,
+    options: &CleanOptions,
+) -> Result<usize, EptdirError> {
+    // 只删除垃圾文件，不删除空文件夹
+    let mut cleaner = Cleaner::new(dir, rules, options, false);
+    cleaner.run(dir, Instant::now(), |cleaner| cleaner.clean_root(dir))?;
+    Ok(cleaner.report.removed_junk_files.len() + cleaner.report.removed_empty_files.len())
+}
+
+
+// 返回 true 表示 dir 本身被删除；options.remove_root 为 false 时 dir 永远不会被删除
+// dir 不存在或不是目录时返回 Ok(false)
+// 设置了 options.empty_predicate 时，只剩下满足条件的文件的目录连同这些文件一起删除（见 remove_with_files）
+// 不删除任何其他文件：options.empty_files 在这里不起作用，0 字节的文件由 remove_junk_files() 删除
+pub fn remove_empty_dirs(dir: &Path, options: &CleanOptions) -> Result<bool, EptdirError> {
+    // 检查路径是否存在并且是目录
+    // dir.is_dir() 在路径不存在时也返回 false
+    if !dir.is_dir() {
+        return Ok(false);
+    }
+
+    // 只删除空文件夹时用不到垃圾文件规则，传入一个空的规则集合；0 字节的文件也不删除
+    let rules = JunkRules::empty();
+    let options = CleanOptions { empty_files: false, ..options.clone() };
+    let mut cleaner = Cleaner::new(dir, &rules, &options, true);
+    let status = cleaner.run(dir, Instant::now(), |cleaner| cleaner.clean_root(dir))?;
+    Ok(status == RootStatus::Removed)
+}
+
+// 锁住 Mutex；持有锁的线程 panic 时锁会"中毒"，这里忽略中毒，继续使用里面的数据
+// 观察者的回调可能在 rayon 的工作线程上 panic，命令行中的观察者（console.rs）也用这个函数
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// 遍历栈中同时打开的目录数量上限
+// 更浅的目录暂时关闭，遍历回到它们时再通过子目录的 ".." 重新打开，
+// 这样目录树再深，占用的文件描述符数量也是固定的
+const OPEN_DIRS: usize = 32;
+
+// 并行任务最多嵌套的层数，更深的子目录在当前任务中依次处理
+// 每层嵌套都会占用一段线程栈，限制层数以后再深的目录树也不会栈溢出
+const MAX_FORKS: usize = 16;
+
+// 目录是怎么进入的，决定处理完以后怎么处理它本身
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    // 清理的目标目录：由 clean_root() 决定去留
+    Root,
+    // 普通子目录：变空时删除
+    Subdir,
+    // 跟随的符号链接：只清理链接指向的目录内部，不删除它，也不影响父目录是否为空
+    Link,
+}
+
+// 遍历栈中的一个目录
+struct Frame {
+    path: PathBuf,
+    visit: Visit,
+    // 打开的目录；None 表示为了限制打开的文件描述符数量暂时关闭了，
+    // 或者重新打开失败（这时不再处理它剩下的子目录）
+    handle: Option<DirHandle>,
+    // 关闭时记下的目录标识，重新打开时用来确认还是同一个目录
+    id: Option<DirId>,
+    // 栈中的下一层是通过符号链接进入的：那一层的 ".." 不是这个目录，所以这个目录不能关闭
+    pinned: bool,
+    // 还没有进入的子目录和符号链接，按名字倒序保存，pop() 得到名字最小的
+    pending: Vec<(OsString, Visit)>,
+    // 处理完成后仍然留在目录中的条目数量，0 表示目录已经（或将会）变空
+    // 预览模式下磁盘不会变化，只能靠这个计数判断目录"将会"变空（级联删除）
+    remaining: usize,
+    // remaining 中保留标记文件（包括 .eptdirignore）的数量
+    markers: usize,
+    // 这个目录有 .eptdirignore，离开时要从清理器的 pins 中移除它的规则
+    pins: bool,
+    // 满足 options.empty_predicate 的文件（不计入 remaining），目录删除时一起删除，按名字排序
+    disposable: Vec<OsString>,
+    // 目录比 options.min_age 新（时间在进入目录时读取），变空了也不删除
+    young: bool,
+}
+
+// 遍历栈：栈底是遍历的起点，栈顶是正在处理的目录（栈顶的目录一直是打开的）
+struct Stack {
+    frames: Vec<Frame>,
+    // 打开的目录数量
+    open: usize,
+    // 下标小于 closed 的目录都已经关闭（或者不能关闭），关闭目录时从这里开始找
+    closed: usize,
+}
+
+impl Stack {
+    fn push(&mut self, frame: Frame) {
+        if frame.handle.is_some() {
+            self.open += 1;
+        }
+        self.frames.push(frame);
+    }
+
+    fn pop(&mut self) -> Option<Frame> {
+        let frame = self.frames.pop()?;
+        if frame.handle.is_some() {
+            self.open -= 1;
+        }
+        self.closed = self.closed.min(self.frames.len().saturating_sub(1));
+        Some(frame)
+    }
+}
+
+// 清理器：保存一次清理过程中需要在各个目录之间共享的状态
+// 'a 是生命周期参数：Cleaner 借用的 rules 和 options 必须比 Cleaner 活得更久
+//
+// 多线程时每个并行处理的子目录使用一个 fork() 出来的清理器：
+// 报告、深度、祖先目录各自独立，处理完成后用 join() 合并回来；
+// 隔离、撤销日志等需要全局一致的状态放在 Arc 中共享
+struct Cleaner<'a> {
+    rules: &'a JunkRules,
+    options: &'a CleanOptions,
+    // 收集结果的报告
+    report: CleanReport,
+    // 是否删除空文件夹；remove_junk_files() 只删除垃圾文件
+    empty_dirs: bool,
+    // 跟随符号链接时，当前遍历路径上所有目录的标识（用于检测循环）
+    ancestors: Vec<DirId>,
+    // 当前所在目录的深度，根目录为 1（根目录中的条目深度为 1）
+    depth: usize,
+    // 由 options.exclude 和 options.include 编译出的规则
+    filter: Arc<PathFilter>,
+    // 当前遍历路径上各个 .eptdirignore 所在的目录和其中的规则
+    pins: Vec<(PathBuf, Arc<PathFilter>)>,
+    // 根目录变空时其中满足 options.empty_predicate 的文件，clean_root() 删除根目录时一起删除
+    root_files: Vec<OsString>,
+    // options.min_age 对应的截止时间：时间晚于它的条目不删除
+    cutoff: Option<SystemTime>,
+    // 这个清理器所在的并行任务嵌套了几层
+    forks: usize,
+    // 隔离模式下负责移动条目，None 表示永久删除
+    mover: Option<Arc<Mover>>,
+    // 撤销日志，None 表示不记录
+    journal: Option<Arc<Journal>>,
+    // 写撤销日志时，目录在清理改动它之前的修改时间
+    // 删除其中的垃圾文件或子目录会改变目录的修改时间，所以要在改动之前记下来
+    dir_mtimes: Arc<Mutex<HashMap<PathBuf, SystemTime>>>,
+    // 多线程时的线程池，None 表示单线程
+    pool: Option<Arc<ThreadPool>>,
+}
+
+impl<'a> Cleaner<'a> {
+    fn new(target: &Path, rules: &'a JunkRules, options: &'a CleanOptions, empty_dirs: bool) -> Self {
+        Cleaner {
+            rules,
+            options,
+            // to_path_buf() 把借用的 &Path 复制成拥有所有权的 PathBuf
+            report: CleanReport::new(target.to_path_buf(), options.dry_run),
+            empty_dirs,
+            ancestors: Vec::new(),
+            depth: 0,
+            filter: Arc::new(PathFilter::new(&options.exclude, &options.include)),
+            pins: Vec::new(),
+            root_files: Vec::new(),
+            // 年龄比现在还早的截止时间无法表示时，使用最早的时间（什么都不删除）
+            cutoff: options.min_age.map(|age| SystemTime::now().checked_sub(age).unwrap_or(UNIX_EPOCH)),
+            forks: 0,
+            // as_ref() 把 &Option<Quarantine> 变成 Option<&Quarantine>
+            mover: options.quarantine.as_ref().map(|quarantine| Arc::new(Mover::new(quarantine, target))),
+            journal: None,
+            dir_mtimes: Arc::default(),
+            pool: new_pool(options.threads).map(Arc::new),
+        }
+    }
+
+    // 为一个并行任务创建清理器：共享的状态复制 Arc，报告从空开始
+    fn fork(&self) -> Cleaner<'a> {
+        Cleaner {
+            rules: self.rules,
+            options: self.options,
+            report: CleanReport::new(self.report.target.clone(), self.options.dry_run),
+            empty_dirs: self.empty_dirs,
+            ancestors: self.ancestors.clone(),
+            depth: self.depth,
+            filter: Arc::clone(&self.filter),
+            pins: self.pins.clone(),
+            root_files: Vec::new(),
+            cutoff: self.cutoff,
+            forks: self.forks + 1,
+            mover: self.mover.clone(),
+            journal: self.journal.clone(),
+            dir_mtimes: Arc::clone(&self.dir_mtimes),
+            pool: self.pool.clone(),
+        }
+    }
+
+    // 对 items 中的每一项调用 f，按 items 的顺序返回结果
+    // 单线程时依次调用；多线程时每一项是线程池中的一个任务，使用 fork() 出来的清理器，
+    // 全部完成后按顺序把报告合并回来
+    // rayon 的线程池使用工作窃取：空闲的线程会从忙碌线程的队列中拿走还没开始处理的子目录，
+    // 所以一棵很深、很不平衡的目录树也能分散到所有线程上
+    fn for_each_dir<T, R, F>(&mut self, items: &[T], f: F) -> Result<Vec<R>, EptdirError>
+    where
+        T: Sync,
+        R: Send,
+        F: Fn(&mut Cleaner<'a>, &T) -> Result<R, EptdirError> + Sync,
+    {
+        // 只有一项时没有可以并行的工作，直接在当前线程处理
+        let pool = match self.pool {
+            Some(ref pool) if items.len() > 1 => Arc::clone(pool),
+            _ => return items.iter().map(|item| f(self, item)).collect(),
+        };
+
+        let this = &*self;
+        // install() 在线程池中运行；已经在这个线程池的线程中时直接运行
+        // collect::<Result<..>>() 遇到第一个错误就停止分配新的任务
+        let results = pool.install(|| {
+            items
+                .par_iter()
+                .map(|item| {
+                    let mut child = this.fork();
+                    let result = f(&mut child, item)?;
+                    Ok((child.report, result))
+                })
+                .collect::<Result<Vec<_>, EptdirError>>()
+        })?;
+
+        Ok(results
+            .into_iter()
+            .map(|(report, result)| {
+                self.report.merge(report);
+                result
+            })
+            .collect())
+    }
+
+    // 清理一个目标：打开撤销日志，调用 clean（清理根目录或者执行清理计划），填好报告中其余的字段
+    // 前后分别通知观察者开始和结束（出错终止时也会通知结束）
+    // start: 开始清理的时间，用于计算耗时
+    fn run<F>(&mut self, target: &Path, start: Instant, clean: F) -> Result<RootStatus, EptdirError>
+    where
+        F: FnOnce(&mut Self) -> Result<RootStatus, EptdirError>,
+    {
+        self.observe(|observer| observer.on_target_started(target, self.options.dry_run));
+        // 日志打不开时什么都不删除，保证删除的内容都有记录
+        let result = self.open_journal(target).and_then(|()| clean(self));
+        if let Ok(status) = result {
+            self.report.root_status = status;
+        }
+        self.report.quarantine = self.mover.as_ref().and_then(|mover| mover.location());
+        self.report.run_id = self.journal.as_ref().map(|journal| journal.run_id().to_string());
+        // 遍历时每个目录中的结果已经按名字排好，并行任务的报告也按顺序合并；
+        // 这里再把跳过的条目和错误排序，报告和线程数无关
+        self.report.sort();
+
+        // elapsed() 返回从 start 到现在经过的时间
+        self.report.elapsed = start.elapsed();
+        self.observe(|observer| observer.on_target_finished(&self.report, result.as_ref().err()));
+        result
+    }
+
+    // 通知 options.observer（没有设置时什么都不做）
+    fn observe(&self, f: impl FnOnce(&dyn CleanupObserver)) {
+        if let Some(observer) = &self.options.observer {
+            f(observer.as_ref());
+        }
+    }
+
+    // 询问观察者能不能删除 path；被否决时记录到报告中，返回 true
+    fn vetoed(&mut self, path: &Path, kind: RemoveKind) -> bool {
+        let vetoed = self.options.observer.as_ref().is_some_and(|observer| !observer.before_remove(path, kind));
+        if vetoed {
+            self.skip(path, SkipReason::Vetoed);
+        }
+        vetoed
+    }
+
+    // 记录一个删除（预览模式下是将删除）的垃圾文件
+    fn removed_junk(&mut self, path: PathBuf, bytes: u64) {
+        self.observe(|observer| observer.after_remove(&path, RemoveKind::JunkFile, bytes));
+        self.report.removed_junk_files.push(path);
+        self.report.bytes_freed += bytes;
+    }
+
+    // 记录一个删除（预览模式下是将删除）的 0 字节文件
+    fn removed_empty_file(&mut self, path: PathBuf) {
+        self.observe(|observer| observer.after_remove(&path, RemoveKind::EmptyFile, 0));
+        self.report.removed_empty_files.push(path);
+    }
+
+    // 记录一个删除（预览模式下是将删除）的空文件夹
+    fn removed_dir(&mut self, dir: &Path) {
+        self.observe(|observer| observer.after_remove(dir, RemoveKind::Dir, 0));
+        self.report.removed_dirs.push(dir.to_path_buf());
+    }
+
+    // 按选项打开撤销日志（预览模式不删除任何内容，不需要日志）
+    fn open_journal(&mut self, target: &Path) -> Result<(), EptdirError> {
+        if let Some(path) = &self.options.journal
+            && !self.options.dry_run
+        {
+            self.journal = Some(Arc::new(Journal::open(path, target)?));
+        }
+        Ok(())
+    }
+
+    // 把删除的条目写入撤销日志
+    // dest: 隔离模式下条目被移动到的位置
+    fn record(&mut self, path: &Path, kind: EntryKind, metadata: &Meta, dest: Option<&Path>) -> Result<(), EptdirError> {
+        // 目录优先使用改动之前记下的修改时间
+        let mtime = lock(&self.dir_mtimes).remove(path);
+        let result = match self.journal {
+            Some(ref journal) => journal.record(path, kind, metadata, mtime, dest),
+            None => return Ok(()),
+        };
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) => self.fail_with(e),
+        }
+    }
+
+    // 记录一个被跳过的条目（同一个路径只记录一次）
+    fn skip(&mut self, path: &Path, reason: SkipReason) {
+        let entry = SkippedEntry { path: path.to_path_buf(), reason };
+        if !self.report.skipped.contains(&entry) {
+            self.report.skipped.push(entry);
+        }
+    }
+
+    // 处理遍历过程中的一个错误
+    // continue_on_error 时把错误记录到报告中并返回 Ok，调用者跳过出错的条目继续遍历
+    // 否则原样返回错误，终止整个清理
+    // operation: 出错时正在执行的操作，会和路径一起保存在错误中
+    fn fail(&mut self, path: &Path, operation: Operation, error: io::Error) -> Result<(), EptdirError> {
+        self.fail_with(EptdirError::io(path, operation, error))
+    }
+
+    // 和 fail() 相同，用于已经构造好的错误
+    fn fail_with(&mut self, error: EptdirError) -> Result<(), EptdirError> {
+        if !self.options.continue_on_error {
+            return Err(error);
+        }
+        self.observe(|observer| observer.on_error(&error));
+        self.report.errors.push(error);
+        Ok(())
+    }
+
+    // 清理根目录，并按 options.remove_root 决定根目录本身的去留
+    fn clean_root(&mut self, dir: &Path) -> Result<RootStatus, EptdirError> {
+        let emptied = self.walk(None, dir.to_path_buf(), Visit::Root)?;
+        if !emptied || !self.empty_dirs {
+            return Ok(RootStatus::NotEmpty);
+        }
+
+        if !self.options.remove_root || self.vetoed(dir, RemoveKind::Dir) {
+            return Ok(RootStatus::KeptEmpty);
+        }
+
+        let files = std::mem::take(&mut self.root_files);
+        if self.remove_empty(None, dir, &files)? {
+            Ok(RootStatus::Removed)
+        } else {
+            Ok(RootStatus::NotEmpty)
+        }
+    }
+
+    // 后序遍历以 dir 为起点的目录树：进入目录时删除垃圾文件，
+    // 所有子目录都处理完以后，根据遍历时数出的剩余条目判断目录是否已经变空
+    // 使用显式的栈而不是递归，目录树的深度不受线程栈大小的限制
+    // parent: dir 的上级目录，根目录为 None
+    // 返回值：Subdir 表示 dir 是否被删除（预览模式下表示"将被删除"），Root 表示 dir 是否已经变空
+    fn walk(&mut self, parent: Option<&DirHandle>, dir: PathBuf, visit: Visit) -> Result<bool, EptdirError> {
+        let mut stack = Stack { frames: Vec::new(), open: 0, closed: 0 };
+        match self.open_frame(parent, dir, visit)? {
+            Some(frame) => stack.push(frame),
+            // 无法进入的目录当作"不为空"，保证不会误删
+            None => return Ok(false),
+        }
+
+        loop {
+            // last_mut() 返回栈顶元素的可变引用，栈为空时是 None
+            let Some(top) = stack.frames.last_mut() else {
+                unreachable!("遍历栈在处理完起点之前不会为空");
+            };
+
+            // 还有没进入的子目录：多线程时一起分给线程池，否则进入名字最小的一个
+            if top.pending.len() > 1 && self.pool.is_some() && self.forks < MAX_FORKS {
+                self.fork_pending(&mut stack)?;
+                continue;
+            }
+            if let Some((name, child)) = top.pending.pop() {
+                let path = top.path.join(&name);
+                match self.open_frame(top.handle.as_ref(), path, child)? {
+                    Some(frame) => {
+                        top.pinned |= child == Visit::Link;
+                        stack.push(frame);
+                        self.close_shallow(&mut stack, OPEN_DIRS);
                     }
+                    // 无法进入的子目录当作"不为空"
+                    None if child == Visit::Subdir => top.remaining += 1,
+                    None => {}
                 }
+                continue;
             }
+
+            // 栈顶目录的所有子目录都处理完了：出栈，必要时删除它，把结果交给上一层
+            let Some(frame) = stack.pop() else {
+                unreachable!("遍历栈在处理完起点之前不会为空");
+            };
+            if stack.frames.is_empty() {
+                return self.finish(frame, parent);
+            }
+            self.reopen_top(&mut stack, &frame)?;
+            let Some(top) = stack.frames.last_mut() else {
+                unreachable!("上面已经检查过栈不为空");
+            };
+            let visit = frame.visit;
+            let removed = self.finish(frame, top.handle.as_ref())?;
+            match visit {
+                Visit::Link => top.pinned = false,
+                // 子目录没有被删除，说明当前目录也不会变空
+                _ if !removed => top.remaining += 1,
+                _ => {}
+            }
+        }
+    }
+
+    // 进入目录：打开它，读取所有条目，删除其中的垃圾文件，把子目录和要跟随的符号链接留到以后处理
+    // 每个目录只读取一次：遍历条目时顺便数出删除后仍然留在目录中的条目
+    // 返回 None 表示无法进入（错误已经记录）
+    fn open_frame(&mut self, parent: Option<&DirHandle>, dir: PathBuf, visit: Visit) -> Result<Option<Frame>, EptdirError> {
+        // 目录中的条目已经超过最大深度（只有 max_depth 为 0 时才会发生）
+        if self.options.max_depth.is_some_and(|max| self.depth >= max) {
+            return Ok(None);
         }
-        //如果是目录，递归处理
-        else if path.is_dir() {
-            // 递归调用：自己调用自己
-            // 这就是递归函数的核心：函数调用自己来处理子目录
-            // &path 传递路径的引用（注意这里的生命周期）
-            // ? 操作符处理可能的错误
-            // 累加子目录中删除的文件数
-            deleted_count += remove_junk_files(&path)?;
+
+        // 读取失败时交给 fail() 处理：要么记录后跳过这个目录，要么终止清理
+        // 子目录相对于上级目录打开，不跟随符号链接；只有跟随的符号链接才打开它指向的目录
+        self.report.syscalls.read_dir += 1;
+        let opened = match parent {
+            Some(parent) => parent.open_dir(sys::file_name(&dir), visit == Visit::Link),
+            None => DirHandle::open(&dir),
+        };
+        let mut handle = match opened {
+            Ok(handle) => handle,
+            // 指向文件或者已经失效的链接不需要进入
+            Err(e) if visit == Visit::Link && sys::not_a_dir(&e) => return Ok(None),
+            Err(e) => {
+                self.fail(&dir, Operation::ReadDir, e)?;
+                return Ok(None);
+            }
+        };
+
+        if self.options.symlinks == SymlinkPolicy::Follow {
+            self.report.syscalls.stat += 1;
+            let id = match handle.id() {
+                Ok(id) => id,
+                Err(e) => {
+                    self.fail(&dir, Operation::Metadata, e)?;
+                    return Ok(None);
+                }
+            };
+            // 目标已经在当前遍历路径上，再进入就会无限循环
+            if visit == Visit::Link && self.ancestors.contains(&id) {
+                self.skip(&dir, SkipReason::SymlinkLoop);
+                return Ok(None);
+            }
+            self.ancestors.push(id);
         }
+        self.depth += 1;
+        self.observe(|observer| observer.on_enter_dir(&dir));
+
+        // 设置了最小年龄时，在读取和改动目录之前读取它的时间（读取目录可能更新访问时间）
+        // 只有可能被删除的目录才需要：跟随的符号链接不删除，根目录只在 remove_root 时删除
+        let removable = self.empty_dirs && (visit == Visit::Subdir || (visit == Visit::Root && self.options.remove_root));
+        let young = match self.cutoff {
+            Some(_) if removable => {
+                self.report.syscalls.stat += 1;
+                match handle.stat() {
+                    Ok(metadata) => self.is_too_new(&metadata),
+                    // 读取不到时间时当作太新，不删除
+                    Err(e) => {
+                        self.fail(&dir, Operation::Metadata, e)?;
+                        true
+                    }
+                }
+            }
+            _ => false,
+        };
+
+        let mut remaining = 0;
+        let mut markers = 0;
+        // 需要进入的子目录和符号链接，当前目录的条目都处理完以后再处理
+        let mut subdirs: Vec<OsString> = Vec::new();
+        let mut links: Vec<OsString> = Vec::new();
+        let mut disposable: Vec<OsString> = Vec::new();
+        // 这个目录中删除的条目在报告中的起始位置，处理完以后按名字排序
+        let junk_start = self.report.removed_junk_files.len();
+        let empty_start = self.report.removed_empty_files.len();
+        let dirs_start = self.report.removed_dirs.len();
+
+        // 先读出所有条目再处理：删除条目需要用到同一个目录句柄
+        let entries: Vec<_> = handle.read().collect();
+        // .eptdirignore 要在处理条目之前读取：达到最大深度的子目录在下面的循环中就决定去留
+        let pins = !self.options.no_keep_markers
+            && entries.iter().any(|entry| {
+                entry.as_ref().is_ok_and(|entry| {
+                    entry.name == IGNORE_FILE && matches!(entry.kind, Some(FileKind::File) | None)
+                })
+            });
+        if pins {
+            let filter = self.load_pins(&handle, &dir)?;
+            self.pins.push((dir.clone(), Arc::new(filter)));
+        }
+        for entry in entries {
+            // 读取失败的条目无法确认是什么，当作"不为空"，然后 continue 跳到下一个条目
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    remaining += 1;
+                    self.fail(&dir, Operation::ReadDir, e)?;
+                    continue;
+                }
+            };
+            let path = dir.join(&entry.name);
+
+            // 隔离位置本身不清理，避免把刚移进去的内容又清理一遍
+            if self.is_quarantine(&path) {
+                remaining += 1;
+                continue;
+            }
+
+            // 条目本身的类型，不跟随符号链接
+            // 大多数文件系统在读取目录时就提供了类型；没有提供时再 lstat 一次
+            let kind = match entry.kind {
+                Some(kind) => kind,
+                None => {
+                    self.report.syscalls.stat += 1;
+                    match handle.stat_at(&entry.name) {
+                        Ok(metadata) => metadata.kind,
+                        Err(e) => {
+                            remaining += 1;
+                            self.fail(&path, Operation::Metadata, e)?;
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            // 被排除的条目：不删除，也不进入，当前目录因此不为空
+            if self.is_excluded(&path, kind) {
+                remaining += 1;
+                continue;
+            }
+
+            match kind {
+                // 符号链接本身永远不删除，所以当前目录不会变空；根据策略决定是否进入
+                FileKind::Symlink => {
+                    remaining += 1;
+                    if self.can_descend() && self.should_follow(&path) {
+                        links.push(entry.name);
+                    }
+                }
+                // 达到最大深度：不进入，只有本来就为空时才删除
+                FileKind::Dir if !self.can_descend() => {
+                    if !self.remove_unvisited(&handle, &path)? {
+                        remaining += 1;
+                    }
+                }
+                FileKind::Dir => subdirs.push(entry.name),
+                // 保留标记文件：不当作垃圾文件，所在的目录也不会被删除
+                FileKind::File if self.is_keep_marker(&entry.name) => {
+                    markers += 1;
+                    remaining += 1;
+                }
+                FileKind::File if self.remove_junk(&handle, &dir, &path)? => {}
+                // 只有目录整个删除时才一起删除
+                FileKind::File if self.is_disposable(&handle, &path)? => disposable.push(entry.name),
+                // 普通文件（且不是被删除的垃圾文件）和其他类型的条目会让目录保持非空
+                _ => remaining += 1,
+            }
+        }
+
+        // 同一个目录中的条目按名字排序，报告的顺序不取决于文件系统返回条目的顺序，也和线程数无关
+        self.report.removed_junk_files[junk_start..].sort();
+        self.report.removed_empty_files[empty_start..].sort();
+        self.report.removed_dirs[dirs_start..].sort();
+        subdirs.sort();
+        links.sort();
+        disposable.sort();
+        // 先处理子目录，再处理符号链接；pop() 从末尾取，所以倒序保存
+        let pending = links
+            .into_iter()
+            .rev()
+            .map(|name| (name, Visit::Link))
+            .chain(subdirs.into_iter().rev().map(|name| (name, Visit::Subdir)))
+            .collect();
+
+        Ok(Some(Frame {
+            path: dir,
+            visit,
+            handle: Some(handle),
+            id: None,
+            pinned: false,
+            pending,
+            remaining,
+            markers,
+            pins,
+            disposable,
+            young,
+        }))
     }
-    
-    // Ok() 表示成功，返回删除的文件数量
-    Ok(deleted_count)
-}
 
+    // 离开目录：子目录已经变空时删除它
+    // parent: 上级目录的句柄，重新打开失败时为 None（这时按完整路径删除）
+    // 返回值和 walk() 相同
+    fn finish(&mut self, frame: Frame, parent: Option<&DirHandle>) -> Result<bool, EptdirError> {
+        if self.options.symlinks == SymlinkPolicy::Follow {
+            self.ancestors.pop();
+        }
+        self.depth -= 1;
+        if frame.pins {
+            self.pins.pop();
+        }
 
-pub fn remove_empty_dirs(dir: &Path) -> Result<bool, Box<dyn std::error::Error>> {
-    // 检查路径是否存在
-    // dir.exists() 检查路径是否存在
-    if !dir.exists() {
-        // Ok(false) 表示目录不存在，返回 false（未删除）
-        return Ok(false);
+        match frame.visit {
+            Visit::Root => {
+                if frame.remaining == 0 && frame.young {
+                    self.skip(&frame.path, SkipReason::TooNew);
+                    return Ok(false);
+                }
+                if frame.remaining == 0 {
+                    self.root_files = frame.disposable;
+                }
+                Ok(frame.remaining == 0)
+            }
+            Visit::Link => Ok(false),
+            Visit::Subdir => {
+                // 先关闭目录再删除它
+                drop(frame.handle);
+                // 除了保留标记以外已经变空的目录：因为保留标记或者 .eptdirignore 留下，记录到报告中
+                // && 是短路求值：还有其他条目时不需要检查 .eptdirignore
+                let kept = frame.remaining == frame.markers && (frame.markers > 0 || self.is_pinned(&frame.path));
+                if kept && self.empty_dirs {
+                    self.skip(&frame.path, SkipReason::Kept);
+                }
+                // 已经变空、但是比最小年龄新的目录
+                let young = !kept && frame.young && frame.remaining == 0;
+                if young {
+                    self.skip(&frame.path, SkipReason::TooNew);
+                }
+                // remaining 不为 0 时不会调用 remove_empty()
+                let removed = !kept
+                    && !young
+                    && frame.remaining == 0
+                    && self.empty_dirs
+                    && !self.vetoed(&frame.path, RemoveKind::Dir)
+                    && self.remove_empty(parent, &frame.path, &frame.disposable)?;
+                // 没有删除的目录不需要再保存修改时间
+                if !removed && self.journal.is_some() {
+                    lock(&self.dir_mtimes).remove(&frame.path);
+                }
+                Ok(removed)
+            }
+        }
     }
-    
-    // 检查是否是目录
-    if !dir.is_dir() {
-        // 如果是文件而不是目录，返回 false
-        return Ok(false);
+
+    // 把栈顶目录剩下的子目录分给线程池并行处理，全部完成后再回到这个目录
+    // for_each_dir() 等所有子目录都处理完才返回，所以父目录一定在子目录之后检查
+    fn fork_pending(&mut self, stack: &mut Stack) -> Result<(), EptdirError> {
+        // 等待期间只保留栈顶的目录打开，限制所有线程加起来同时打开的文件描述符数量
+        self.close_shallow(stack, 1);
+        let Some(top) = stack.frames.last_mut() else {
+            return Ok(());
+        };
+        // pending 是倒序保存的，反过来按名字顺序处理
+        let pending: Vec<(OsString, Visit)> = top.pending.drain(..).rev().collect();
+        let handle = top.handle.as_ref();
+        let dir = &top.path;
+        let removed = self.for_each_dir(&pending, |cleaner, (name, visit)| cleaner.walk(handle, dir.join(name), *visit))?;
+        // 子目录没有被删除，说明当前目录也不会变空
+        top.remaining += pending
+            .iter()
+            .zip(removed)
+            .filter(|((_, visit), removed)| *visit == Visit::Subdir && !removed)
+            .count();
+        Ok(())
     }
-    
-    // 先递归处理所有子目录
-    // if let 模式匹配：如果读取目录成功，就进入这个分支
-    // Ok(entries) 表示成功读取目录
-    if let Ok(entries) = fs::read_dir(dir) {
-        // Vec<PathBuf> 是一个可增长的数组（动态数组）
-        // mut 表示可变，可以添加元素
-        // PathBuf 是路径的可变类型（String 是字符串的可变类型，PathBuf 是路径的可变类型）
-        let mut subdirs: Vec<std::path::PathBuf> = Vec::new();
-        
-        // 收集所有子目录路径
-        // 为什么先收集？因为如果我们在遍历时删除目录，迭代器可能会失效
-        // 所以先收集所有子目录路径，然后再处理
-        for entry in entries {
-            let entry = entry?;
-            let path = entry.path();
-            
-            // 只收集子目录
-            if path.is_dir() {
-                // push() 方法将元素添加到数组末尾
-                subdirs.push(path);
-            }
-            // 注意：如果存在文件，我们暂时不处理
-            // 等递归删除所有子目录后，再检查当前目录是否为空
-            // 如果目录中有文件，那目录肯定不为空，不需要删除
-        }
-        
-        // 递归处理每个子目录
-        // for 循环遍历所有收集到的子目录
-        for subdir in subdirs {
-            // 递归调用：自己调用自己处理子目录
-            // let _ = 表示我们不关心返回值（用 _ 忽略）
-            // ? 操作符处理错误
-            let _ = remove_empty_dirs(&subdir)?;
-        }
-    }
-    
-    // 递归删除子目录后，再次检查当前目录是否为空
-    // 为什么再次检查？ 因为删除子目录后，当前目录可能也变成空的了
-    // match 表达式：模式匹配，类似于 switch 语句，但更强大
-    match fs::read_dir(dir) {
-        // Ok 分支：成功读取目录
-        Ok(mut entries) => {
-            // mut entries 表示 entries 是可变的（因为我们要调用 next()）
-            // entries.next() 获取迭代器的下一个元素
-            // is_none() 检查是否是 None（没有更多元素）
-            // 如果目录为空，迭代器就没有任何元素
-            if entries.next().is_none() {
-                // 目录为空，可以删除
-                println!("删除空文件夹: {}", dir.display());
-                
-                // 删除目录
-                fs::remove_dir(dir)?;
-                
-                // 返回 true 表示目录被删除
-                return Ok(true);
-            }
-            // 如果目录不为空（有文件或子目录），继续执行后面的代码
-        }
-        // Err 分支：读取目录失败
-        Err(_) => {
-            // _ 表示我们不关心具体的错误类型
-            // 如果读取失败，可能目录已经被删除了（被其他进程删除等）
-            // 返回 true 表示目录已经不存在了
+
+    // 关闭栈中最浅的目录，直到打开的目录不超过 keep 个；栈顶的目录一直保持打开
+    // 关闭前记下目录的标识（一次 fstat），重新打开时用来确认
+    fn close_shallow(&mut self, stack: &mut Stack, keep: usize) {
+        let top = stack.frames.len().saturating_sub(1);
+        while stack.open > keep && stack.closed < top {
+            let frame = &mut stack.frames[stack.closed];
+            stack.closed += 1;
+            let Some(handle) = frame.handle.as_ref().filter(|_| !frame.pinned) else {
+                continue;
+            };
+            // 读取标识失败时保持打开
+            self.report.syscalls.stat += 1;
+            if let Ok(id) = handle.id() {
+                frame.id = Some(id);
+                frame.handle = None;
+                stack.open -= 1;
+            }
+        }
+    }
+
+    // 出栈以后，新的栈顶目录如果已经关闭，就重新打开它
+    // 先通过刚处理完的子目录的 ".." 打开（不需要完整路径），不行再按完整路径打开，
+    // 两种方式都要确认打开的还是原来的那个目录；
+    // 都失败说明目录在清理过程中被移动或删除了，不再处理它剩下的子目录，也不删除它
+    fn reopen_top(&mut self, stack: &mut Stack, child: &Frame) -> Result<(), EptdirError> {
+        let Some(top) = stack.frames.last_mut() else {
+            return Ok(());
+        };
+        if top.handle.is_some() {
+            return Ok(());
+        }
+
+        let handle = child
+            .handle
+            .as_ref()
+            .and_then(|handle| self.check_reopened(handle.parent(), top.id))
+            .or_else(|| self.check_reopened(DirHandle::open(&top.path), top.id));
+        if let Some(handle) = handle {
+            top.handle = Some(handle);
+            stack.open += 1;
+            return Ok(());
+        }
+
+        top.pending.clear();
+        top.remaining += 1;
+        let error = io::Error::other("目录在清理过程中被移动或删除了，无法重新打开");
+        self.fail(&top.path, Operation::ReadDir, error)
+    }
+
+    // 重新打开的目录和关闭前记下的标识相同时才使用它
+    fn check_reopened(&mut self, opened: io::Result<DirHandle>, id: Option<DirId>) -> Option<DirHandle> {
+        self.report.syscalls.read_dir += 1;
+        let handle = opened.ok()?;
+        self.report.syscalls.stat += 1;
+        (handle.id().ok() == id).then_some(handle)
+    }
+
+    // 如果文件是垃圾文件（或者开启 options.empty_files 时是 0 字节的文件）就删除它（预览模式下只记录）
+    // handle 是文件所在目录 dir 的句柄
+    // 返回 true 表示文件已经（或将会）被删除，不再计入目录中的剩余条目
+    fn remove_junk(&mut self, handle: &DirHandle, dir: &Path, path: &Path) -> Result<bool, EptdirError> {
+        // file_name 是 &OsStr 类型，可能包含无效的 UTF-8（Unix 上文件名可以是任意字节）
+        // rules.is_junk_os() 直接在 OsStr 上匹配，不会因为无法转换成字符串而跳过
+        let file_name = sys::file_name(path);
+        // 垃圾文件只看名字；0 字节的文件要读取元数据以后才知道
+        let junk = self.rules.is_junk_os(file_name);
+        if !junk && !self.may_be_empty_file(file_name) {
+            return Ok(false);
+        }
+
+        // 元数据不跟随符号链接，len 是文件大小（字节）
+        // 元数据同时写入撤销日志
+        self.report.syscalls.stat += 1;
+        let metadata = match handle.stat_at(file_name) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                self.fail(path, Operation::Metadata, e)?;
+                return Ok(false);
+            }
+        };
+        if !junk && metadata.len != 0 {
+            return Ok(false);
+        }
+        let kind = if junk { RemoveKind::JunkFile } else { RemoveKind::EmptyFile };
+        self.observe(|observer| observer.on_junk_found(path, kind, metadata.len));
+        if self.is_too_new(&metadata) {
+            self.skip(path, SkipReason::TooNew);
+            return Ok(false);
+        }
+        if self.vetoed(path, kind) {
+            return Ok(false);
+        }
+
+        // 预览模式：只记录，不删除
+        if !self.options.dry_run {
+            // 删除文件
+            // 失败（比如文件被占用）时记录错误，这个文件不算作已删除
+            self.remember_mtime(handle, dir);
+            match self.remove_file(handle, path) {
+                Ok(dest) => self.record(path, EntryKind::File, &metadata, dest.as_deref())?,
+                Err(e) => {
+                    self.fail_with(e)?;
+                    return Ok(false);
+                }
+            }
+        }
+
+        if junk {
+            self.removed_junk(path.to_path_buf(), metadata.len);
+        } else {
+            self.removed_empty_file(path.to_path_buf());
+        }
+        Ok(true)
+    }
+
+    // 开启 options.empty_files 时，名字不是已知的"空文件也有意义"的文件，如果是 0 字节就删除
+    fn may_be_empty_file(&self, name: &OsStr) -> bool {
+        self.options.empty_files
+            && !EMPTY_FILE_SENTINELS.iter().any(|sentinel| name == *sentinel)
+            && !self.options.empty_file_sentinels.iter().any(|pattern| rules::glob_match_os(pattern, name))
+    }
+
+    // 文件是否满足 options.empty_predicate（只删除垃圾文件时不使用）
+    fn is_disposable(&mut self, handle: &DirHandle, path: &Path) -> Result<bool, EptdirError> {
+        let options = self.options;
+        let Some(predicate) = options.empty_predicate.as_ref().filter(|_| self.empty_dirs) else {
+            return Ok(false);
+        };
+        let disposable = match predicate {
+            EmptyPredicate::Rules(rules) => rules.is_junk_os(sys::file_name(path)),
+            EmptyPredicate::ZeroByte => self.file_len(handle, path)? == Some(0),
+            EmptyPredicate::Custom(predicate) => self.file_len(handle, path)?.is_some_and(|len| predicate(path, len)),
+        };
+        if !disposable || self.cutoff.is_none() {
+            return Ok(disposable);
+        }
+        // 太新的文件留在目录中，目录也就不会被删除
+        match self.stat_entry(handle, path)? {
+            Some(metadata) if !self.is_too_new(&metadata) => Ok(true),
+            Some(_) => {
+                self.skip(path, SkipReason::TooNew);
+                Ok(false)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // 文件的大小；读取失败时返回 None（错误已经记录）
+    fn file_len(&mut self, handle: &DirHandle, path: &Path) -> Result<Option<u64>, EptdirError> {
+        Ok(self.stat_entry(handle, path)?.map(|metadata| metadata.len))
+    }
+
+    // 读取目录 handle 中条目 path 的元数据；读取失败时返回 None（错误已经记录）
+    fn stat_entry(&mut self, handle: &DirHandle, path: &Path) -> Result<Option<Meta>, EptdirError> {
+        self.report.syscalls.stat += 1;
+        match handle.stat_at(sys::file_name(path)) {
+            Ok(metadata) => Ok(Some(metadata)),
+            Err(e) => {
+                self.fail(path, Operation::Metadata, e)?;
+                Ok(None)
+            }
+        }
+    }
+
+    // 条目是否比 options.min_age 新：按 options.age_time 选择的时间晚于截止时间
+    // 读取不到时间时当作太新，不删除
+    fn is_too_new(&self, metadata: &Meta) -> bool {
+        let Some(cutoff) = self.cutoff else {
+            return false;
+        };
+        let time = match self.options.age_time {
+            AgeTime::Modified => metadata.modified,
+            AgeTime::Changed => metadata.changed,
+            AgeTime::Accessed => metadata.accessed,
+        };
+        time.is_none_or(|time| time > cutoff)
+    }
+
+    // 删除一个遍历时已经确认为空的目录（预览模式下只记录）
+    // 不再重新读取目录：如果其他程序在这期间写入了内容，删除会失败并报告 DirectoryNotEmpty
+    // parent: 上级目录的句柄，None 时按完整路径删除（根目录）
+    // files: 目录中满足 options.empty_predicate、要和目录一起删除的文件
+    // 返回 true 表示目录被删除
+    fn remove_empty(&mut self, parent: Option<&DirHandle>, dir: &Path, files: &[OsString]) -> Result<bool, EptdirError> {
+        if !files.is_empty() {
+            return self.remove_with_files(parent, dir, files);
+        }
+
+        // 预览模式：磁盘没有变化，直接记录
+        if self.options.dry_run {
+            self.removed_dir(dir);
             return Ok(true);
         }
+
+        // 写撤销日志需要删除前的权限和修改时间；不写日志时省掉这次 stat
+        let metadata = match self.journal {
+            Some(_) => {
+                self.report.syscalls.stat += 1;
+                let metadata = match parent {
+                    Some(parent) => parent.stat_at(sys::file_name(dir)),
+                    None => sys::stat_path(dir),
+                };
+                match metadata {
+                    Ok(metadata) => Some(metadata),
+                    Err(e) => {
+                        self.fail(dir, Operation::Metadata, e)?;
+                        return Ok(false);
+                    }
+                }
+            }
+            None => None,
+        };
+        // 删除会改变父目录的修改时间，先记下来（根目录的父目录不在清理范围内）
+        if let Some(parent) = parent
+            && let Some(parent_path) = dir.parent()
+        {
+            self.remember_mtime(parent, parent_path);
+        }
+
+        // 删除失败时记录错误，当作没有删除
+        match self.remove_dir(parent, dir) {
+            Ok(dest) => {
+                if let Some(metadata) = metadata {
+                    self.record(dir, EntryKind::Dir, &metadata, dest.as_deref())?;
+                }
+            }
+            // 目录已经不存在了（被其他进程删除等），当作已经删除
+            Err(e) if e.kind() == Some(io::ErrorKind::NotFound) => return Ok(true),
+            Err(e) => {
+                self.fail_with(e)?;
+                return Ok(false);
+            }
+        }
+        self.removed_dir(dir);
+
+        // 返回 true 表示目录被删除
+        Ok(true)
+    }
+
+    // 删除"实际上为空"的目录：里面只剩下满足 options.empty_predicate 的文件 files
+    // 对调用者来说是原子的：先把目录改成上级目录中的一个临时名字，原来的路径立即消失；
+    // 确认里面仍然只有这些文件以后，再删除文件和目录本身。
+    // 改名以后发现目录里有了其他内容，或者删除其中的文件失败时，把目录改回原来的名字
+    // 程序在中途被终止时，目录会以 .eptdir-staging-* 的名字留在上级目录中
+    fn remove_with_files(&mut self, parent: Option<&DirHandle>, dir: &Path, files: &[OsString]) -> Result<bool, EptdirError> {
+        let name = sys::file_name(dir);
+
+        // 观察者否决其中任何一个文件时，目录不为空，什么都不删除
+        // 不用 any()：每个被否决的文件都要记录到报告中
+        let vetoed = files.iter().filter(|file| self.vetoed(&dir.join(file), RemoveKind::JunkFile)).count();
+        if vetoed > 0 {
+            return Ok(false);
+        }
+
+        // 预览模式：磁盘没有变化，读取文件大小后直接记录
+        if self.options.dry_run {
+            self.report.syscalls.read_dir += 1;
+            let opened = match parent {
+                Some(parent) => parent.open_dir(name, false),
+                None => DirHandle::open(dir),
+            };
+            let handle = match opened {
+                Ok(handle) => handle,
+                Err(e) => {
+                    self.fail(dir, Operation::ReadDir, e)?;
+                    return Ok(false);
+                }
+            };
+            for file in files {
+                let path = dir.join(file);
+                let Some(len) = self.file_len(&handle, &path)? else {
+                    return Ok(false);
+                };
+                self.removed_junk(path, len);
+            }
+            self.removed_dir(dir);
+            return Ok(true);
+        }
+
+        // 根目录没有上级目录的句柄，按路径打开
+        let opened;
+        let parent = match parent {
+            Some(parent) => {
+                self.remember_mtime(parent, sys::parent_path(dir));
+                parent
+            }
+            None => {
+                self.report.syscalls.read_dir += 1;
+                opened = match DirHandle::open(sys::parent_path(dir)) {
+                    Ok(handle) => handle,
+                    Err(e) => {
+                        self.fail(dir, Operation::RemoveDir, e)?;
+                        return Ok(false);
+                    }
+                };
+                &opened
+            }
+        };
+
+        let staging = match self.stage(parent, name) {
+            Ok(staging) => staging,
+            // 目录已经不存在了（被其他进程删除等），当作已经删除
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(true),
+            Err(e) => {
+                self.fail(dir, Operation::RemoveDir, e)?;
+                return Ok(false);
+            }
+        };
+        let result = self.remove_staged(parent, &staging, dir, files);
+        if !matches!(result, Ok(true)) {
+            self.report.syscalls.remove += 1;
+            if let Err(e) = parent.rename(&staging, name) {
+                self.fail(&sys::parent_path(dir).join(&staging), Operation::RemoveDir, e)?;
+            }
+        }
+        match result {
+            Ok(removed) => Ok(removed),
+            Err(e) => {
+                self.fail_with(e)?;
+                Ok(false)
+            }
+        }
+    }
+
+    // 在上级目录中找一个没有被占用的临时名字，把目录 name 改成这个名字
+    fn stage(&mut self, parent: &DirHandle, name: &OsStr) -> io::Result<OsString> {
+        // 同一个进程中的多个线程也不会用到同一个名字
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        loop {
+            let staging = OsString::from(format!(
+                ".eptdir-staging-{}-{}",
+                process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed)
+            ));
+            // 改名会覆盖已经存在的空目录，先确认这个名字没有被占用
+            self.report.syscalls.stat += 1;
+            match parent.stat_at(&staging) {
+                Ok(_) => continue,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+            self.report.syscalls.remove += 1;
+            parent.rename(name, &staging)?;
+            return Ok(staging);
+        }
+    }
+
+    // 删除改名以后的目录 staging（原来的路径是 dir）和其中的文件
+    // 返回 false 表示目录里有了 files 以外的内容，什么都没有删除
+    fn remove_staged(
+        &mut self,
+        parent: &DirHandle,
+        staging: &OsStr,
+        dir: &Path,
+        files: &[OsString],
+    ) -> Result<bool, EptdirError> {
+        self.report.syscalls.read_dir += 1;
+        let mut handle = parent.open_dir(staging, false).map_err(|e| EptdirError::io(dir, Operation::ReadDir, e))?;
+
+        // 改名以后其他程序不会再通过原来的路径写入，这时再确认一次：只能有 files 中的普通文件
+        let entries: Vec<_> = handle.read().collect();
+        let mut present: Vec<(OsString, Meta)> = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| EptdirError::io(dir, Operation::ReadDir, e))?;
+            if !files.contains(&entry.name) {
+                return Ok(false);
+            }
+            self.report.syscalls.stat += 1;
+            let metadata =
+                handle.stat_at(&entry.name).map_err(|e| EptdirError::io(&dir.join(&entry.name), Operation::Metadata, e))?;
+            if metadata.kind != FileKind::File {
+                return Ok(false);
+            }
+            present.push((entry.name, metadata));
+        }
+        present.sort_by(|a, b| a.0.cmp(&b.0));
+        // 删除其中的文件会改变目录的修改时间，先记下来
+        self.remember_mtime(&handle, dir);
+
+        for (name, metadata) in &present {
+            let path = dir.join(name);
+            self.report.syscalls.remove += 1;
+            let dest = match self.mover {
+                Some(ref mover) => Some(mover.move_file(&handle, name, &path)?),
+                None => {
+                    handle.remove_file(name).map_err(|e| EptdirError::io(&path, Operation::RemoveFile, e))?;
+                    None
+                }
+            };
+            self.record(&path, EntryKind::File, metadata, dest.as_deref())?;
+            self.removed_junk(path, metadata.len);
+        }
+
+        // 写撤销日志需要目录的权限；修改时间使用上面记下的值
+        let metadata = match self.journal {
+            Some(_) => {
+                self.report.syscalls.stat += 1;
+                Some(handle.stat().map_err(|e| EptdirError::io(dir, Operation::Metadata, e))?)
+            }
+            None => None,
+        };
+        drop(handle);
+        self.report.syscalls.remove += 1;
+        let dest = match self.mover {
+            Some(ref mover) => Some(mover.move_dir(Some(parent), staging, dir)?),
+            None => {
+                parent.remove_dir(staging).map_err(|e| EptdirError::io(dir, Operation::RemoveDir, e))?;
+                None
+            }
+        };
+        if let Some(metadata) = metadata {
+            self.record(dir, EntryKind::Dir, &metadata, dest.as_deref())?;
+        }
+        self.removed_dir(dir);
+        Ok(true)
+    }
+
+    // 达到最大深度的子目录：不进入清理，只有它本来就为空时才删除
+    // 读取一次判断是否为空，这也是这个目录唯一的一次读取；读取失败时当作不为空
+    fn remove_unvisited(&mut self, handle: &DirHandle, dir: &Path) -> Result<bool, EptdirError> {
+        if !self.empty_dirs {
+            return Ok(false);
+        }
+        // 最小年龄：在读取目录之前检查时间
+        if self.cutoff.is_some() {
+            match self.stat_entry(handle, dir)? {
+                Some(metadata) if !self.is_too_new(&metadata) => {}
+                Some(_) => {
+                    self.skip(dir, SkipReason::TooNew);
+                    return Ok(false);
+                }
+                None => return Ok(false),
+            }
+        }
+        self.report.syscalls.read_dir += 1;
+        match handle.open_dir(sys::file_name(dir), false).and_then(|mut subdir| subdir.is_empty()) {
+            Ok(true) if self.is_pinned(dir) => {
+                self.skip(dir, SkipReason::Kept);
+                Ok(false)
+            }
+            Ok(true) if self.vetoed(dir, RemoveKind::Dir) => Ok(false),
+            Ok(true) => self.remove_empty(Some(handle), dir, &[]),
+            Ok(false) => Ok(false),
+            Err(e) => {
+                self.fail(dir, Operation::ReadDir, e)?;
+                Ok(false)
+            }
+        }
+    }
+
+    // 写撤销日志时，在改动 dir 之前记下它的修改时间（已经记过的保留最早的值）
+    // handle 是 dir 的句柄；每个目录只由一个线程处理，读取修改时间时不需要一直持有锁
+    fn remember_mtime(&mut self, handle: &DirHandle, dir: &Path) {
+        if self.journal.is_none() || lock(&self.dir_mtimes).contains_key(dir) {
+            return;
+        }
+        // 读取失败时不记录，写日志时使用删除前的修改时间
+        self.report.syscalls.stat += 1;
+        if let Some(mtime) = handle.stat().ok().and_then(|metadata| metadata.modified) {
+            lock(&self.dir_mtimes).entry(dir.to_path_buf()).or_insert(mtime);
+        }
+    }
+
+    // 删除文件，隔离模式下移动到隔离位置
+    // 返回隔离位置中的路径，永久删除时返回 None
+    fn remove_file(&mut self, handle: &DirHandle, path: &Path) -> Result<Option<PathBuf>, EptdirError> {
+        self.report.syscalls.remove += 1;
+        match self.mover {
+            Some(ref mover) => mover.move_file(handle, sys::file_name(path), path).map(Some),
+            None => match handle.remove_file(sys::file_name(path)) {
+                Ok(()) => Ok(None),
+                Err(e) => Err(EptdirError::io(path, Operation::RemoveFile, e)),
+            },
+        }
+    }
+
+    // 删除空目录，隔离模式下移动到隔离位置
+    fn remove_dir(&mut self, parent: Option<&DirHandle>, dir: &Path) -> Result<Option<PathBuf>, EptdirError> {
+        self.report.syscalls.remove += 1;
+        let result = match (&self.mover, parent) {
+            (Some(mover), parent) => return mover.move_dir(parent, sys::file_name(dir), dir).map(Some),
+            (None, Some(parent)) => parent.remove_dir(sys::file_name(dir)),
+            (None, None) => fs::remove_dir(dir),
+        };
+        match result {
+            Ok(()) => Ok(None),
+            Err(e) => Err(EptdirError::io(dir, Operation::RemoveDir, e)),
+        }
+    }
+
+    // 当前目录中的子目录是否还可以进入（没有达到最大深度）
+    fn can_descend(&self) -> bool {
+        // is_none_or()：None 时返回 true，Some(值) 时用闭包判断
+        self.options.max_depth.is_none_or(|max| self.depth < max)
+    }
+
+    // 条目是不是隔离位置
+    fn is_quarantine(&self, path: &Path) -> bool {
+        self.mover.as_ref().is_some_and(|mover| mover.is_quarantine(path))
+    }
+
+    // 条目是否被排除规则排除（规则使用相对于目标目录的路径）
+    // 排除的目录（整棵子树都不处理）和本来会被删除的垃圾文件记录到报告中
+    fn is_excluded(&mut self, path: &Path, kind: FileKind) -> bool {
+        if self.filter.is_empty() {
+            return false;
+        }
+        let relative = path.strip_prefix(&self.report.target).unwrap_or(path);
+        if !self.filter.is_excluded(relative, kind == FileKind::Dir) {
+            return false;
+        }
+        if kind == FileKind::Dir || (kind == FileKind::File && self.rules.is_junk_os(sys::file_name(path))) {
+            self.skip(path, SkipReason::Excluded);
+        }
+        true
+    }
+
+    // 文件是不是保留标记（内置的 KEEP_MARKERS、options.keep_markers 或者 .eptdirignore）
+    fn is_keep_marker(&self, name: &OsStr) -> bool {
+        !self.options.no_keep_markers
+            && (name == IGNORE_FILE
+                || KEEP_MARKERS.iter().any(|marker| name == *marker)
+                || self.options.keep_markers.iter().any(|marker| name == marker.as_str()))
+    }
+
+    // 读取 dir 中的 .eptdirignore
+    // 读取失败时保守处理：把 dir 下面的所有目录都当作要保留的目录
+    fn load_pins(&mut self, handle: &DirHandle, dir: &Path) -> Result<PathFilter, EptdirError> {
+        match handle.read_file(OsStr::new(IGNORE_FILE)) {
+            Ok(content) => Ok(PathFilter::from_lines(&String::from_utf8_lossy(&content))),
+            Err(e) => {
+                self.fail(&dir.join(IGNORE_FILE), Operation::ReadFile, e)?;
+                Ok(PathFilter::from_lines("*/"))
+            }
+        }
+    }
+
+    // 目录是否列在当前遍历路径上的某个 .eptdirignore 中（规则相对于 .eptdirignore 所在的目录）
+    fn is_pinned(&self, dir: &Path) -> bool {
+        self.pins
+            .iter()
+            .any(|(base, filter)| dir.strip_prefix(base).is_ok_and(|relative| filter.is_excluded(relative, true)))
+    }
+
+    // 根据策略判断是否进入符号链接
+    // Follow 策略下链接是否指向目录、是否形成循环，在打开它的时候再检查（open_frame）
+    fn should_follow(&mut self, link: &Path) -> bool {
+        match self.options.symlinks {
+            SymlinkPolicy::Skip => false,
+            SymlinkPolicy::Report => {
+                self.skip(link, SkipReason::Symlink);
+                false
+            }
+            SymlinkPolicy::Follow => true,
+        }
+    }
+}
+
+
+// 按线程数创建线程池，单线程时返回 None
+// 创建失败（例如系统不允许再创建线程）时也返回 None，退回单线程清理
+fn new_pool(threads: usize) -> Option<ThreadPool> {
+    if threads <= 1 {
+        return None;
     }
-    
-    // 如果执行到这里，说明目录不为空，没有被删除
-    Ok(false)
+    ThreadPoolBuilder::new().num_threads(threads).build().ok()
 }
 
 // 声明测试模块
@@ -1,23 +1,82 @@
 // std::env - 环境变量和命令行参数
 // std::path - 路径处理（PathBuf 等）
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::env;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::sync::Arc;
 
+// 命令行参数解析（只在可执行文件中使用）
+mod cli;
+// 控制台输出
+mod console;
+// --json 的输出格式
+mod json;
+// --interactive：删除前逐个确认
+mod interactive;
+
+use cli::{Cli, Command, RestoreArgs, UndoArgs, Verbosity};
+use console::ConsoleObserver;
+use interactive::Prompter;
+use json::Totals;
 // 导入库模块中的函数
-use eptdir::clean_directory;
+use eptdir::{
+    clean_directory, escape_path, execute, plan, restore, undo, CleanOptions, CleanReport, CleanupPlan, EptdirError, JunkRules,
+};
 
 fn main() {
-    // 获取当前程序启动时转入的命令行参数。
-    let args: Vec<String> = env::args().collect();
-    // println!("{:?}", args);["target\\debug\\eptdir.exe", "D:\\桌面\\草稿"]
-
-    // 如果命令行有参数
-    let target_dirs: Vec<PathBuf> = if args.len() > 1 {
-    // args[0] 通常是程序的名称，因此从 args[1] 开始就是用户传入的路径参数
-    // 闭包 是匿名的，可以捕获和使用定义它时所在作用域中的变量。
-    // 函数 是命名的，不能直接捕获外部变量，除非通过传递参数的方式。
-    // |s|闭包参数定义,配合map(),把迭代进来的每一个参数(&String类型)转换路径类型
-        args[1..].iter().map(|s| PathBuf::from(s)).collect()
+    // args_os() 获取命令行参数，skip(1) 跳过程序名
+    // 和 args() 不同，args_os() 遇到不是有效 UTF-8 的参数不会崩溃
+    let command = match cli::parse_args(env::args_os().skip(1)) {
+        Ok(command) => command,
+        Err(e) => usage_error(e),
+    };
+
+    let run = match command {
+        Command::Help => {
+            print!("{}", cli::HELP);
+            return;
+        }
+        Command::Version => {
+            // env! 在编译时读取 Cargo.toml 中的版本号
+            println!("eptdir {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Command::Restore(args) => run_restore(&args),
+        Command::Undo(args) => run_undo(&args),
+        Command::Run(run) => *run,
+    };
+    if let Err(e) = cli::check_interactive(&run, io::stdin().is_terminal()) {
+        usage_error(e);
+    }
+    let Cli { mut options, junk_rules, paths, verbosity, json, events, save_plan, plan, interactive } = run;
+    // --json 时标准输出上只有最后的 JSON 文档，--events 时只有事件，都不输出文字报告；--quiet 时只输出错误
+    let text = !json && !events && verbosity != Verbosity::Quiet;
+    // 清理过程中的输出都由观察者完成：--events 输出事件，否则是控制台文字（--json 时没有）
+    if events {
+        options.observer = Some(Arc::new(json::event_sink()));
+    } else if !json {
+        options.observer = Some(Arc::new(ConsoleObserver::new(&options, verbosity)));
+    }
+
+    if verbosity == Verbosity::Verbose && text {
+        println!("垃圾文件规则: {:?}", junk_rules.rules());
+        println!("清理选项: {:?}", options);
+    }
+
+    // --plan：读取清理计划，要清理的目录记录在计划中
+    let plan = plan.map(|file| match CleanupPlan::load(&file) {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            std::process::exit(1);
+        }
+    });
+
+    // 如果命令行有路径参数
+    let target_dirs: Vec<PathBuf> = if let Some(plan) = &plan {
+        vec![plan.target.clone()]
+    } else if !paths.is_empty() {
+        paths
     } else {
         // env::current_dir() 获取当前工作目录
         // env"程序运行时的环境接口",用来获取或修改环境信息,expect() 如果出错就打印消息并终止程序
@@ -29,53 +88,240 @@ fn main() {
 
     };
 
+    if let Some(file) = &save_plan {
+        run_save_plan(file, &target_dirs[0], &junk_rules, &options, text);
+    }
+
     //检查目录是否存在
     // .len()，它返回的是向量中元素的个数（有几个路径）
-    if target_dirs.len() == 1 {
-        println!("正在清理 1 个目录...");
-    } else {
+    if text {
         println!("正在清理 {} 个目录...", target_dirs.len());
     }
 
-    // 用于跟踪处理结果
-    let mut success_count = 0;
-    let mut error_count = 0;
+    // 用于跟踪处理结果：成功、失败、跳过的目标数量和删除的条目数量
+    let mut totals = Totals::default();
+    // 所有目录中处理失败的条目（路径 + 错误），最后统一汇总
+    let mut failures: Vec<EptdirError> = Vec::new();
+    // --json 时每个目标的结果
+    let mut json_targets = Vec::new();
+    // --interactive：问题写到标准错误，回答 a 或 q 的状态在目标之间保持
+    let mut prompter = interactive.then(|| Prompter::new(io::stdin().lock(), io::stderr()));
 
     //遍历所有目标目录,enumerate() 方法返回 (索引, 值) 的元组
     for (index, target_dir) in target_dirs.iter().enumerate() {
         // 如果有多个目录，显示当前处理的目录编号
-        if target_dirs.len() > 1 {
+        if text && target_dirs.len() > 1 {
             println!("========================================");
-            println!("处理目录 {}/{}: {}", index + 1, target_dirs.len(), target_dir.display());
+            println!("处理目录 {}/{}: {}", index + 1, target_dirs.len(), escape_path(&target_dir));
             println!("========================================");
         }
         
-        // 调用清理函数处理当前目录，match 表达式处理可能的错误
-        match clean_directory(target_dir) {
-            // Ok 分支：清理成功
-            Ok(_) => {
-                success_count += 1;
+        // 调用清理函数处理当前目录（或者执行清理计划），match 表达式处理可能的错误
+        let result = match (&mut prompter, &plan) {
+            (Some(prompter), plan) => confirm_and_execute(prompter, target_dir, plan.as_ref(), &junk_rules, &options),
+            (None, Some(plan)) => execute(plan, &options),
+            (None, None) => clean_directory(target_dir, &junk_rules, &options),
+        };
+        match result {
+            // Ok 分支：清理成功，报告已经由观察者输出过了
+            Ok(report) => {
+                let status = totals.add_report(&report);
+                if json {
+                    json_targets.push(json::target(target_dir, status, Some(&report), None));
+                }
+                // extend() 把报告中的错误追加到汇总列表
+                failures.extend(report.errors);
             }
-            // Err 分支：清理失败
+            // Err 分支：目标没有被清理，警告和错误写到标准错误（--json 时也一样）
             Err(e) => {
-                error_count += 1;
-                eprintln!("清理目录时出错: {} - {}", target_dir.display(), e);
-                // 继续处理下一个目录，不退出程序
+                let status = totals.add_error(&e);
+                match e {
+                    // 目标不存在或不是目录：只警告，不算失败
+                    // eprintln! 是错误输出宏
+                    EptdirError::TargetNotFound(ref path) => {
+                        eprintln!("警告: 目录不存在，跳过: {}", escape_path(path));
+                    }
+                    EptdirError::NotADirectory(ref path) => {
+                        eprintln!("警告: 该路径不是目录，跳过: {}", escape_path(path));
+                    }
+                    // 清理失败：继续处理下一个目录，不退出程序
+                    _ => eprintln!("清理目录时出错: {} - {}", escape_path(&target_dir), e),
+                }
+                if json {
+                    json_targets.push(json::target(target_dir, status, None, Some(&e)));
+                } else if events && status != "failed" {
+                    // 清理失败的目标已经有 target_finished 事件，只补上没有开始清理的目标
+                    json::write_line(&json::target_skipped(target_dir, status, &e));
+                }
             }
         }
+
+        // --interactive 时回答了 q：剩下的目录不再处理
+        if prompter.as_ref().is_some_and(|prompter| prompter.quit()) {
+            let rest = target_dirs.len() - index - 1;
+            if rest > 0 {
+                eprintln!("已退出，剩下 {} 个目录没有处理", rest);
+            }
+            break;
+        }
+    }
+
+    // 有目标或条目处理失败时以错误码 1 退出
+    let exit_status = if totals.failed > 0 { 1 } else { 0 };
+    if json {
+        // to_string_pretty() 把 JSON 值格式化成缩进的文本
+        let output = json::document(options.dry_run, exit_status, &totals, json_targets);
+        println!("{}", serde_json::to_string_pretty(&output).expect("JSON 序列化失败"));
+    } else if events {
+        json::write_line(&json::run_finished(options.dry_run, exit_status, &totals, target_dirs.len()));
     }
 
     //显示最终结果
+    if text {
+        print_summary(options.dry_run, &totals, &failures);
+    }
+    if exit_status != 0 {
+        std::process::exit(exit_status);
+    }
+}
+
+// 显示所有目录的汇总结果
+fn print_summary(dry_run: bool, totals: &Totals, failures: &[EptdirError]) {
     println!();
     println!("========================================");
-    println!("清理完成！");
-    println!("成功: {} 个目录", success_count);
-    if error_count > 0 {
-        println!("失败: {} 个目录", error_count);
+    if dry_run {
+        println!("预览完成！未删除任何内容");
+    } else {
+        println!("清理完成！");
+    }
+    println!("成功: {} 个目录", totals.succeeded);
+    if totals.failed > 0 {
+        println!("失败: {} 个目录", totals.failed);
+    }
+    if totals.skipped > 0 {
+        println!("跳过: {} 个目录", totals.skipped);
+    }
+    // 失败汇总：列出每个出错的路径和错误种类
+    if !failures.is_empty() {
+        println!("处理失败的条目: {} 个", failures.len());
+        for failure in failures {
+            // {:?} 使用 Debug 格式显示错误种类，例如 PermissionDenied
+            match failure.kind() {
+                Some(kind) => println!("  [{:?}] {}", kind, failure),
+                None => println!("  {}", failure),
+            }
+        }
     }
     println!("========================================");
-    // 如果有错误，以错误码退出
-    if error_count > 0 {
-        std::process::exit(1);
+}
+
+// --interactive：先制定清理计划（--plan 时使用读取的计划），逐组确认，再只执行确认了的条目
+// 制定计划时只输出错误，将要删除的内容在询问时列出；执行时照常输出
+// 制定计划时跳过的条目和读不了的部分不在计划中，合并到返回的报告里，和直接清理一样计入汇总、--json 和退出码
+fn confirm_and_execute<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    target: &Path,
+    loaded: Option<&CleanupPlan>,
+    junk_rules: &JunkRules,
+    options: &CleanOptions,
+) -> Result<CleanReport, EptdirError> {
+    let (planned, scan) = match loaded {
+        Some(plan) => (plan.clone(), None),
+        None => {
+            let quiet = CleanOptions { observer: Some(Arc::new(ConsoleObserver::new(options, Verbosity::Quiet))), ..options.clone() };
+            let (planned, scan) = plan(target, junk_rules, &quiet)?;
+            (planned, Some(scan))
+        }
+    };
+    let approved = match prompter.confirm(&planned) {
+        Ok(approved) => approved,
+        Err(e) => {
+            eprintln!("错误: 无法读取回答: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut report = execute(&approved, options)?;
+    if let Some(scan) = scan {
+        // 制定计划时的问题发生在执行之前，排在前面
+        report.skipped.splice(0..0, scan.skipped);
+        report.errors.splice(0..0, scan.errors);
+    }
+    Ok(report)
+}
+
+// 用法错误：打印错误和提示，用单独的退出码退出
+fn usage_error(e: cli::UsageError) -> ! {
+    eprintln!("错误: {}", e);
+    eprintln!("使用 eptdir --help 查看用法");
+    std::process::exit(cli::EXIT_USAGE);
+}
+
+// --save-plan：制定清理计划并保存到文件，不删除任何内容；出错时以错误码 1 退出
+fn run_save_plan(file: &Path, target: &Path, junk_rules: &JunkRules, options: &CleanOptions, text: bool) -> ! {
+    let result = plan(target, junk_rules, options).and_then(|(plan, scan)| plan.save(file).map(|()| (plan, scan)));
+    match result {
+        // 跳过的条目已经由观察者列出；读不了的部分不在计划中，计划照样保存，但和正常清理一样以错误码 1 退出
+        Ok((plan, scan)) => {
+            if text {
+                println!("已保存清理计划: {}（{} 个条目）", escape_path(file), plan.entries.len());
+                println!("审阅以后可以使用 eptdir --plan {} 执行", escape_path(file));
+            }
+            if !scan.errors.is_empty() {
+                eprintln!("错误: 有 {} 个条目无法读取，计划中不包含它们", scan.errors.len());
+                std::process::exit(1);
+            }
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// eptdir restore：把隔离的内容放回原位置，有条目恢复失败时以错误码 1 退出
+fn run_restore(args: &RestoreArgs) -> ! {
+    let report = match restore(&args.source, args.only_under.as_deref()) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if args.verbosity != Verbosity::Quiet {
+        for path in &report.restored {
+            println!("已恢复: {}", escape_path(&path));
+        }
+        println!("恢复完成: {} 个条目", report.restored.len());
+    }
+    for error in &report.errors {
+        eprintln!("错误: {}", error);
+    }
+    std::process::exit(if report.errors.is_empty() { 0 } else { 1 });
+}
+
+// eptdir undo：按撤销日志撤销一次清理，有条目恢复失败时以错误码 1 退出
+fn run_undo(args: &UndoArgs) -> ! {
+    let report = match undo(&args.journal, &args.run_id) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if args.verbosity != Verbosity::Quiet {
+        for path in &report.restored {
+            println!("已恢复: {}", escape_path(&path));
+        }
+        for path in &report.unrecoverable {
+            println!("已永久删除，无法恢复: {}", escape_path(&path));
+        }
+        println!("撤销完成: 恢复 {} 个条目", report.restored.len());
+    }
+    for error in &report.errors {
+        eprintln!("错误: {}", error);
     }
+    std::process::exit(if report.errors.is_empty() { 0 } else { 1 });
 }
@@ -0,0 +1,81 @@
+// ========================================
+// 清理过程的观察者
+// ========================================
+// 库本身不打印任何内容；想在清理过程中得到通知（显示进度、写日志）或者逐个决定删不删的调用者，
+// 实现 CleanupObserver 并放到 CleanOptions::observer 中
+// clean_directory()、remove_junk_files() 和 remove_empty_dirs() 都通过它报告
+//
+// 所有方法都有什么都不做的默认实现，只需要实现关心的那几个
+// 多线程清理时会在多个线程中同时调用，所以要求 Send + Sync；方法应该尽快返回，它们会拖慢遍历
+// 调用顺序见 events.rs（EventSink 就是一个把通知转换成事件的观察者）
+// 预览模式下 before_remove 和 after_remove 同样会被调用，表示"将要删除"的条目
+use std::fmt;
+use std::path::Path;
+
+use crate::{CleanReport, EptdirError};
+
+// 被删除的条目是什么
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoveKind {
+    // 匹配垃圾文件规则的文件，或者和"实际上为空"的目录一起删除的文件（CleanOptions::empty_predicate）
+    JunkFile,
+    // 0 字节的文件（CleanOptions::empty_files）
+    EmptyFile,
+    // 空文件夹
+    Dir,
+}
+
+impl RemoveKind {
+    // JSON 输出（例如清理计划）中使用的名字
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RemoveKind::JunkFile => "junk_file",
+            RemoveKind::EmptyFile => "empty_file",
+            RemoveKind::Dir => "dir",
+        }
+    }
+
+    // as_str() 的逆操作
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "junk_file" => Some(RemoveKind::JunkFile),
+            "empty_file" => Some(RemoveKind::EmptyFile),
+            "dir" => Some(RemoveKind::Dir),
+            _ => None,
+        }
+    }
+}
+
+pub trait CleanupObserver: Send + Sync {
+    // 开始清理一个目标目录（目标不存在或不是目录时不会调用）
+    fn on_target_started(&self, _target: &Path, _dry_run: bool) {}
+
+    // 进入一个目录（读取它的条目之前）
+    fn on_enter_dir(&self, _dir: &Path) {}
+
+    // 发现一个垃圾文件或者 0 字节的文件，bytes 是它的大小
+    // 之后它仍然可能因为 min_age 或者 before_remove 而保留
+    fn on_junk_found(&self, _path: &Path, _kind: RemoveKind, _bytes: u64) {}
+
+    // 即将删除一个条目；返回 false 表示否决：条目保留下来，记录到报告的 skipped 中（SkipReason::Vetoed）
+    // 否决的文件会让所在的目录保持非空
+    fn before_remove(&self, _path: &Path, _kind: RemoveKind) -> bool {
+        true
+    }
+
+    // 删除了一个条目（隔离模式下是移走了），bytes 是文件的大小，目录为 0
+    fn after_remove(&self, _path: &Path, _kind: RemoveKind, _bytes: u64) {}
+
+    // 一个条目处理失败，跳过它继续清理（只有 continue_on_error 时才有；否则错误由函数返回）
+    fn on_error(&self, _error: &EptdirError) {}
+
+    // 目标清理结束；error 是终止清理的错误，这时 report 只包含终止之前的结果
+    fn on_target_finished(&self, _report: &CleanReport, _error: Option<&EptdirError>) {}
+}
+
+// trait 对象没有实现 Debug，手动实现（CleanOptions 需要 Debug）
+impl fmt::Debug for dyn CleanupObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CleanupObserver(..)")
+    }
+}
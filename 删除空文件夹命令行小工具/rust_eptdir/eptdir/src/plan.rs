@@ -0,0 +1,426 @@
+// ========================================
+// 清理计划：先计划、审阅，再执行
+// ========================================
+// plan() 按 clean_directory() 的预览模式遍历一次，把将要删除的每个条目和它现在的样子
+// （类型、大小、修改时间、inode）记录到 CleanupPlan 中，不修改磁盘
+// 计划可以保存成 JSON 文件，审阅或者删掉其中的条目以后，再用 execute() 执行
+//
+// 执行时逐个条目重新确认：还存在、类型和 inode 相同；文件的大小和修改时间相同；
+// 目录里除了计划中已经删除的条目以外没有别的内容。有任何变化的条目都跳过，
+// 记录到报告的 skipped 中（SkipReason::Changed），它所在的目录也就不再为空，同样会被跳过
+// 条目所在的目录从目标目录出发逐层打开，不跟随符号链接：路径中间有符号链接的条目跳过
+// （SkipReason::Symlink），编辑过的计划不能借符号链接删除目标目录以外的内容
+//
+// 计划文件的格式（带版本号 plan_version，含义和 --json 的 schema_version 相同）：
+// {
+//   "plan_version": 1,
+//   "eptdir_version": "0.1.0",
+//   "created": "2026-10-17T09:30:00Z",     只供阅读，读取时忽略
+//   "target": "/data/photos",              绝对路径
+//   "entries": [                           删除的顺序：先是所有文件，再是目录（子目录在前）
+//     { "path": "/data/photos/a/thumbs.db", "type": "junk_file", "size": 5,
+//       "mtime_secs": 1760600000, "mtime_nanos": 0, "dev": 2049, "ino": 1234 }
+//   ]
+// }
+// type 是 junk_file / empty_file / dir；路径的编码和 --json 相同（见 escape.rs）
+// mtime_secs / mtime_nanos 和 dev / ino 都必须写出来：文件系统不提供修改时间时 mtime 写成 null，
+// 这样的条目无法重新确认，执行时跳过；dev / ino 在 Unix 上不能是 null，其他平台没有 inode，写成 null
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use serde_json::{json, Value};
+
+use crate::escape::{path_from_json, path_json};
+use crate::journal::{absolute, join_time, split_time, EntryKind};
+use crate::sys::{self, DirHandle, FileKind, Meta};
+use crate::trash::format_datetime;
+use crate::{
+    clean_directory, CleanOptions, CleanReport, Cleaner, EptdirError, JunkRules, Operation, RemoveKind, RootStatus,
+    SkipReason,
+};
+
+// 计划文件格式的版本号
+pub const PLAN_VERSION: u32 = 1;
+
+// 一次清理的计划
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CleanupPlan {
+    // 清理的目标目录（绝对路径）
+    pub target: PathBuf,
+    // 要删除的条目，按删除的顺序
+    pub entries: Vec<PlanEntry>,
+}
+
+// 计划中的一个条目，以及制定计划时它的样子
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanEntry {
+    pub path: PathBuf,
+    pub kind: RemoveKind,
+    pub size: u64,
+    pub mtime: Option<SystemTime>,
+    // (设备号, inode 号)
+    pub inode: Option<(u64, u64)>,
+}
+
+// 制定清理计划：参数和 clean_directory() 相同，options.dry_run 和隔离、撤销日志的设置在这里不起作用
+// 观察者会像预览模式一样收到通知
+// 同时返回这次预览的报告：将要删除的条目已经移到计划中，报告里留下的是没有放进计划的部分——
+// 跳过的条目（skipped），以及 continue_on_error 时读不了的部分（errors），调用者据此判断计划是否完整
+pub fn plan(target: &Path, rules: &JunkRules, options: &CleanOptions) -> Result<(CleanupPlan, CleanReport), EptdirError> {
+    // 计划中保存绝对路径，在任何目录下都能执行
+    let target = absolute(target);
+    let preview = CleanOptions { dry_run: true, quarantine: None, journal: None, ..options.clone() };
+    let mut report = clean_directory(&target, rules, &preview)?;
+
+    // 先删除文件，目录放在最后：removed_dirs 已经是子目录在前
+    // mem::take() 取出列表，报告中留下空列表
+    let removed = mem::take(&mut report.removed_junk_files)
+        .into_iter()
+        .map(|path| (path, RemoveKind::JunkFile))
+        .chain(mem::take(&mut report.removed_empty_files).into_iter().map(|path| (path, RemoveKind::EmptyFile)))
+        .chain(mem::take(&mut report.removed_dirs).into_iter().map(|path| (path, RemoveKind::Dir)));
+    let mut entries = Vec::new();
+    for (path, kind) in removed {
+        // 记下条目现在的样子，执行时用来确认它没有变化
+        match sys::stat_path(&path) {
+            Ok(metadata) => entries.push(PlanEntry {
+                size: metadata.len,
+                mtime: metadata.modified,
+                inode: metadata.inode,
+                path,
+                kind,
+            }),
+            // 预览以后就不见了的条目不需要删除
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                let error = EptdirError::io(&path, Operation::Metadata, e);
+                if !options.continue_on_error {
+                    return Err(error);
+                }
+                if let Some(observer) = &options.observer {
+                    observer.on_error(&error);
+                }
+                report.errors.push(error);
+            }
+        }
+    }
+    Ok((CleanupPlan { target, entries }, report))
+}
+
+// 执行清理计划，返回和 clean_directory() 相同的报告
+// options 中只有 dry_run、continue_on_error、隔离、撤销日志和观察者起作用：
+// 删哪些条目已经由计划决定，观察者仍然可以否决
+// 计划中有不在目标目录里的条目时什么都不删除，返回 InvalidPlan
+pub fn execute(plan: &CleanupPlan, options: &CleanOptions) -> Result<CleanReport, EptdirError> {
+    let start = Instant::now();
+
+    // 编辑过的计划可能指向任何地方：所有条目都必须在目标目录中，相对目标目录的部分只能是普通的名字
+    // （不能用 .. 跳出去）；路径中的符号链接在执行时逐层打开目录时检查
+    for entry in &plan.entries {
+        let inside = entry.path.strip_prefix(&plan.target).is_ok_and(|relative| {
+            relative.components().all(|component| matches!(component, Component::Normal(_)))
+        });
+        if !inside {
+            return Err(EptdirError::InvalidPlan {
+                path: entry.path.clone(),
+                message: "条目不在目标目录中".to_string(),
+            });
+        }
+    }
+
+    match fs::metadata(&plan.target) {
+        Ok(metadata) if metadata.is_dir() => {}
+        Ok(_) => return Err(EptdirError::NotADirectory(plan.target.clone())),
+        Err(_) => return Err(EptdirError::TargetNotFound(plan.target.clone())),
+    }
+
+    // 执行计划不需要垃圾文件规则
+    let rules = JunkRules::empty();
+    let mut cleaner = Cleaner::new(&plan.target, &rules, options, true);
+    cleaner.report.syscalls.stat += 1;
+    cleaner.run(&plan.target, start, |cleaner| cleaner.execute_plan(plan))?;
+    Ok(cleaner.report)
+}
+
+impl CleanupPlan {
+    // 转换成 JSON（格式见文件开头）
+    pub fn to_json(&self) -> Value {
+        let entries: Vec<Value> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let (mtime_secs, mtime_nanos) = match entry.mtime.map(split_time) {
+                    Some((secs, nanos)) => (Some(secs), Some(nanos)),
+                    None => (None, None),
+                };
+                json!({
+                    "path": path_json(&entry.path),
+                    "type": entry.kind.as_str(),
+                    "size": entry.size,
+                    "mtime_secs": mtime_secs,
+                    "mtime_nanos": mtime_nanos,
+                    "dev": entry.inode.map(|(dev, _)| dev),
+                    "ino": entry.inode.map(|(_, ino)| ino),
+                })
+            })
+            .collect();
+        json!({
+            "plan_version": PLAN_VERSION,
+            "eptdir_version": env!("CARGO_PKG_VERSION"),
+            "created": format!("{}Z", format_datetime(SystemTime::now(), "-", "T", ":")),
+            "target": path_json(&self.target),
+            "entries": entries,
+        })
+    }
+
+    // 从 JSON 读取计划；file 是计划文件的路径，只用于错误信息
+    pub fn from_json(value: &Value, file: &Path) -> Result<Self, EptdirError> {
+        let invalid = |message: String| EptdirError::InvalidPlan { path: file.to_path_buf(), message };
+
+        match value["plan_version"].as_u64() {
+            Some(version) if version == PLAN_VERSION as u64 => {}
+            Some(version) => return Err(invalid(format!("不支持的计划版本 {}", version))),
+            None => return Err(invalid("缺少 plan_version".to_string())),
+        }
+        let target = path_from_json(&value["target"]).ok_or_else(|| invalid("缺少 target".to_string()))?;
+        let items = value["entries"].as_array().ok_or_else(|| invalid("缺少 entries".to_string()))?;
+
+        let mut entries = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            // 出错时指出是第几个条目（从 1 开始）
+            let entry_error = |what: &str| invalid(format!("第 {} 个条目{}", index + 1, what));
+            let path = path_from_json(&item["path"]).ok_or_else(|| entry_error("缺少 path"))?;
+            let kind = item["type"].as_str().and_then(RemoveKind::parse).ok_or_else(|| entry_error("的 type 无效"))?;
+            let size = item["size"].as_u64().ok_or_else(|| entry_error("缺少 size"))?;
+            // 缺少这些字段的条目无法重新确认，不能当作"不用比较"；两个都是 null 是 to_json() 写的"没有修改时间"
+            let mtime = match (item.get("mtime_secs"), item.get("mtime_nanos")) {
+                (Some(Value::Null), Some(Value::Null)) => None,
+                (Some(secs), Some(nanos)) => match (secs.as_i64(), nanos.as_u64()) {
+                    (Some(secs), Some(nanos)) if nanos < 1_000_000_000 => Some(join_time(secs, nanos as u32)),
+                    _ => return Err(entry_error("的 mtime_secs / mtime_nanos 无效")),
+                },
+                _ => return Err(entry_error("缺少 mtime_secs / mtime_nanos")),
+            };
+            let inode = item["dev"].as_u64().zip(item["ino"].as_u64());
+            if cfg!(unix) && inode.is_none() {
+                return Err(entry_error("缺少 dev / ino"));
+            }
+            entries.push(PlanEntry { path, kind, size, mtime, inode });
+        }
+        Ok(CleanupPlan { target, entries })
+    }
+
+    // 保存到文件（缩进的 JSON，方便审阅和编辑）
+    pub fn save(&self, path: &Path) -> Result<(), EptdirError> {
+        let mut text = serde_json::to_string_pretty(&self.to_json()).expect("JSON 序列化失败");
+        text.push('\n');
+        fs::write(path, text).map_err(|e| EptdirError::io(path, Operation::WriteFile, e))
+    }
+
+    // 从文件读取
+    pub fn load(path: &Path) -> Result<Self, EptdirError> {
+        let text = fs::read_to_string(path).map_err(|e| EptdirError::io(path, Operation::ReadFile, e))?;
+        let value: Value = serde_json::from_str(&text)
+            .map_err(|e| EptdirError::InvalidPlan { path: path.to_path_buf(), message: e.to_string() })?;
+        Self::from_json(&value, path)
+    }
+}
+
+// 执行过程中已经删除（预览模式下是将删除）的条目
+#[derive(Default)]
+struct Executed {
+    removed: HashSet<PathBuf>,
+    // 计划删除了其中条目的目录：它们的修改时间当然会变
+    touched: HashSet<PathBuf>,
+}
+
+impl Cleaner<'_> {
+    // 按顺序执行计划中的条目，返回根目录最后的状态
+    fn execute_plan(&mut self, plan: &CleanupPlan) -> Result<RootStatus, EptdirError> {
+        // 目标目录本身按路径打开，其中的目录都从这里出发逐层打开
+        self.report.syscalls.read_dir += 1;
+        let root = DirHandle::open(&plan.target).map_err(|e| EptdirError::io(&plan.target, Operation::ReadDir, e))?;
+        let mut executed = Executed::default();
+        // 上一个条目所在的目录：同一个目录中的条目在计划中通常是挨着的，不用每次都从目标目录重新打开
+        let mut last: Option<(PathBuf, DirHandle)> = None;
+        for entry in &plan.entries {
+            let dir = sys::parent_path(&entry.path);
+            if last.as_ref().is_none_or(|(path, _)| path != dir) {
+                last = None;
+                let opened = match entry.path == plan.target {
+                    // 删除目标目录本身：上级目录不在清理范围内，按路径打开
+                    true => DirHandle::open(dir).map_err(|e| (dir.to_path_buf(), Some(e))),
+                    false => self.open_inside(&root, &plan.target, dir),
+                };
+                match opened {
+                    Ok(handle) => last = Some((dir.to_path_buf(), handle)),
+                    Err((path, e)) => {
+                        self.skip_unopened(&entry.path, &path, e)?;
+                        continue;
+                    }
+                }
+            }
+            let Some((_, parent)) = &last else { continue };
+            if self.execute_entry(entry, parent, &executed)? {
+                executed.removed.insert(entry.path.clone());
+                executed.touched.insert(sys::parent_path(&entry.path).to_path_buf());
+            }
+        }
+
+        if executed.removed.contains(&plan.target) {
+            return Ok(RootStatus::Removed);
+        }
+        self.report.syscalls.read_dir += 1;
+        match DirHandle::open(&plan.target).and_then(|mut handle| self.only_removed(&mut handle, &plan.target, &executed)) {
+            Ok(true) => Ok(RootStatus::KeptEmpty),
+            _ => Ok(RootStatus::NotEmpty),
+        }
+    }
+
+    // 从目标目录的句柄 root 出发逐层打开其中的目录 dir，每一层都不跟随符号链接
+    // 失败时返回打不开的那一层和错误；错误为 None 表示那一层是符号链接
+    fn open_inside(
+        &mut self,
+        root: &DirHandle,
+        target: &Path,
+        dir: &Path,
+    ) -> Result<DirHandle, (PathBuf, Option<io::Error>)> {
+        let relative = dir.strip_prefix(target).unwrap_or(Path::new(""));
+        let mut path = target.to_path_buf();
+        self.report.syscalls.read_dir += 1;
+        let mut handle = root.open_dir(OsStr::new("."), false).map_err(|e| (path.clone(), Some(e)))?;
+        for name in relative.iter() {
+            path.push(name);
+            self.report.syscalls.read_dir += 1;
+            match handle.open_dir(name, false) {
+                Ok(next) => handle = next,
+                Err(e) => {
+                    // 区分符号链接和其他原因，让报告说清楚为什么跳过
+                    self.report.syscalls.stat += 1;
+                    if handle.stat_at(name).is_ok_and(|metadata| metadata.kind == FileKind::Symlink) {
+                        return Err((path, None));
+                    }
+                    return Err((path, Some(e)));
+                }
+            }
+        }
+        Ok(handle)
+    }
+
+    // 条目所在的目录打不开：路径中间是符号链接时跳过（SkipReason::Symlink），目录不见了或者被换成了文件时
+    // 当作计划以后发生了变化，其他错误照常处理
+    fn skip_unopened(&mut self, path: &Path, dir: &Path, error: Option<io::Error>) -> Result<(), EptdirError> {
+        match error {
+            None => self.skip(path, SkipReason::Symlink),
+            Some(e) if sys::not_a_dir(&e) => self.skip(path, SkipReason::Changed),
+            Some(e) => self.fail(dir, Operation::ReadDir, e)?,
+        }
+        Ok(())
+    }
+
+    // 重新确认并删除一个条目，parent 是它所在目录的句柄；返回 true 表示条目被删除
+    fn execute_entry(&mut self, entry: &PlanEntry, parent: &DirHandle, executed: &Executed) -> Result<bool, EptdirError> {
+        let path = &entry.path;
+        let name = sys::file_name(path);
+        let dir = sys::parent_path(path);
+
+        // 之后的检查和删除都相对于所在目录的句柄进行
+        self.report.syscalls.stat += 1;
+        let metadata = match parent.stat_at(name) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                self.skip(path, SkipReason::Changed);
+                return Ok(false);
+            }
+            Err(e) => {
+                self.fail(path, Operation::Metadata, e)?;
+                return Ok(false);
+            }
+        };
+        if !self.unchanged(entry, &metadata, parent, executed)? {
+            self.skip(path, SkipReason::Changed);
+            return Ok(false);
+        }
+        if self.vetoed(path, entry.kind) {
+            return Ok(false);
+        }
+
+        // 预览模式：只记录，不删除
+        if !self.options.dry_run {
+            self.remember_mtime(parent, dir);
+            let (removed, kind) = match entry.kind {
+                RemoveKind::Dir => (self.remove_dir(Some(parent), path), EntryKind::Dir),
+                _ => (self.remove_file(parent, path), EntryKind::File),
+            };
+            match removed {
+                Ok(dest) => self.record(path, kind, &metadata, dest.as_deref())?,
+                Err(e) => {
+                    self.fail_with(e)?;
+                    return Ok(false);
+                }
+            }
+        }
+
+        match entry.kind {
+            RemoveKind::JunkFile => self.removed_junk(path.clone(), metadata.len),
+            RemoveKind::EmptyFile => self.removed_empty_file(path.clone()),
+            RemoveKind::Dir => self.removed_dir(path),
+        }
+        Ok(true)
+    }
+
+    // 条目是否还是制定计划时的样子
+    fn unchanged(
+        &mut self,
+        entry: &PlanEntry,
+        metadata: &Meta,
+        parent: &DirHandle,
+        executed: &Executed,
+    ) -> Result<bool, EptdirError> {
+        let kind = match entry.kind {
+            RemoveKind::Dir => FileKind::Dir,
+            _ => FileKind::File,
+        };
+        // 计划中没有修改时间（文件系统不提供）的条目无法确认没有被改写，当作已经变化
+        // 没有 inode 的条目（只有其他平台上没有 inode）同样要求一致，不会被当作"不用比较"
+        if entry.mtime.is_none() || metadata.kind != kind || metadata.inode != entry.inode {
+            return Ok(false);
+        }
+        let same_mtime = metadata.modified == entry.mtime;
+        if entry.kind != RemoveKind::Dir {
+            return Ok(metadata.len == entry.size && same_mtime);
+        }
+
+        // 目录：计划没有删除过其中的条目时，修改时间也必须相同
+        if !same_mtime && !executed.touched.contains(&entry.path) {
+            return Ok(false);
+        }
+        // 只剩下计划中已经删除的条目（预览模式下它们还在磁盘上）
+        self.report.syscalls.read_dir += 1;
+        let result = parent
+            .open_dir(sys::file_name(&entry.path), false)
+            .and_then(|mut handle| self.only_removed(&mut handle, &entry.path, executed));
+        match result {
+            Ok(empty) => Ok(empty),
+            Err(e) => {
+                self.fail(&entry.path, Operation::ReadDir, e)?;
+                Ok(false)
+            }
+        }
+    }
+
+    // 目录 dir（句柄 handle）中是否只有 executed.removed 中的条目
+    fn only_removed(&self, handle: &mut DirHandle, dir: &Path, executed: &Executed) -> io::Result<bool> {
+        for entry in handle.read() {
+            if !executed.removed.contains(&dir.join(entry?.name)) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,215 @@
+// ========================================
+// 清理报告
+// ========================================
+// clean_directory() 不再直接打印，而是把结果收集到 CleanReport 中返回
+// 由调用者（例如 main.rs）决定怎么展示
+use std::path::PathBuf;
+use std::time::Duration;
+
+// serde_json::json! 宏可以用类似 JSON 的语法构造 JSON 值
+use serde_json::{json, Value};
+
+use crate::escape::path_json;
+use crate::EptdirError;
+
+// 一次清理的完整结果
+// 预览模式（dry_run = true）下，"removed" 开头的字段表示"将被删除"的内容
+#[derive(Debug)]
+pub struct CleanReport {
+    // 清理的目标目录
+    pub target: PathBuf,
+    // 是否是预览模式
+    pub dry_run: bool,
+    // 删除的垃圾文件，按遍历的顺序：每个目录中的文件按名字排序，排在它的子目录中的文件前面
+    pub removed_junk_files: Vec<PathBuf>,
+    // 删除的 0 字节文件（CleanOptions::empty_files），顺序和 removed_junk_files 相同
+    pub removed_empty_files: Vec<PathBuf>,
+    // 删除的空文件夹，子目录在前，父目录在后（按目录树的后序排列，同一层按名字排序）
+    pub removed_dirs: Vec<PathBuf>,
+    // 跳过的条目（例如符号链接）
+    pub skipped: Vec<SkippedEntry>,
+    // 清理过程中遇到的错误（只有 continue_on_error 模式下才会有内容）
+    pub errors: Vec<EptdirError>,
+    // 删除垃圾文件释放的字节数
+    pub bytes_freed: u64,
+    // 清理耗时
+    pub elapsed: Duration,
+    // 根目录最后的状态
+    pub root_status: RootStatus,
+    // 隔离模式下，被移走的内容所在的位置（可以用 eptdir restore 放回）
+    pub quarantine: Option<PathBuf>,
+    // 写入撤销日志时的运行 ID（可以用 eptdir undo 撤销）
+    pub run_id: Option<String>,
+    // 清理过程中的文件系统调用次数
+    pub syscalls: SyscallCounts,
+}
+
+// 清理过程中各类文件系统调用的次数，用于性能分析（例如 benches/traversal.rs）
+// 每次读取目录算一次 read_dir（实际的 getdents 次数取决于目录大小）；
+// 隔离模式下移动一个条目算一次 remove；写撤销日志不计入
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyscallCounts {
+    // 打开并读取目录
+    pub read_dir: u64,
+    // 读取元数据（stat / lstat），解析真实路径（canonicalize）也算在这里
+    pub stat: u64,
+    // 删除文件或目录
+    pub remove: u64,
+}
+
+impl CleanReport {
+    // 创建一个空报告
+    pub fn new(target: PathBuf, dry_run: bool) -> Self {
+        CleanReport {
+            target,
+            dry_run,
+            removed_junk_files: Vec::new(),
+            removed_empty_files: Vec::new(),
+            removed_dirs: Vec::new(),
+            skipped: Vec::new(),
+            errors: Vec::new(),
+            bytes_freed: 0,
+            // Duration::ZERO 表示 0 秒
+            elapsed: Duration::ZERO,
+            root_status: RootStatus::NotEmpty,
+            quarantine: None,
+            run_id: None,
+            syscalls: SyscallCounts::default(),
+        }
+    }
+
+    // 合并并行任务的报告：路径列表追加在后面，重复的跳过条目只保留一个
+    pub(crate) fn merge(&mut self, other: CleanReport) {
+        self.removed_junk_files.extend(other.removed_junk_files);
+        self.removed_empty_files.extend(other.removed_empty_files);
+        self.removed_dirs.extend(other.removed_dirs);
+        for entry in other.skipped {
+            if !self.skipped.contains(&entry) {
+                self.skipped.push(entry);
+            }
+        }
+        self.errors.extend(other.errors);
+        self.bytes_freed += other.bytes_freed;
+        self.syscalls.read_dir += other.syscalls.read_dir;
+        self.syscalls.stat += other.syscalls.stat;
+        self.syscalls.remove += other.syscalls.remove;
+    }
+
+    // 删除的空文件夹数量，不包括目标目录本身（它的去留见 root_status）
+    // 文字汇总、--json 的 counts 和 target_finished 事件都用这个数量；removed_dirs 列表中仍然包括目标目录
+    pub fn removed_dir_count(&self) -> usize {
+        self.removed_dirs.iter().filter(|dir| **dir != self.target).count()
+    }
+
+    // 把跳过的条目和错误按路径排序，这样多线程清理的报告和单线程完全相同
+    // removed_junk_files、removed_empty_files 和 removed_dirs 在遍历时已经是固定的顺序，不需要再排序
+    // （很深的目录树中路径很长，逐个比较路径的代价很高）
+    pub(crate) fn sort(&mut self) {
+        // sort_by() 是稳定排序：同一个路径的多个错误保持原来的先后
+        self.skipped.sort_by(|a, b| a.path.cmp(&b.path));
+        self.errors.sort_by(|a, b| a.path().cmp(&b.path()));
+    }
+
+    // 转换成 JSON 值，供 --json 输出使用
+    pub fn to_json(&self) -> Value {
+        json!({
+            "target": path_json(&self.target),
+            "dry_run": self.dry_run,
+            // iter().map().collect() 把每个路径转换成 JSON 字符串
+            "removed_junk_files": self.removed_junk_files.iter().map(|p| path_json(p)).collect::<Vec<_>>(),
+            "removed_empty_files": self.removed_empty_files.iter().map(|p| path_json(p)).collect::<Vec<_>>(),
+            "removed_dirs": self.removed_dirs.iter().map(|p| path_json(p)).collect::<Vec<_>>(),
+            "skipped": self.skipped.iter().map(|entry| json!({
+                "path": path_json(&entry.path),
+                "reason": entry.reason.as_str(),
+            })).collect::<Vec<_>>(),
+            "errors": self.errors.iter().map(error_json).collect::<Vec<_>>(),
+            "bytes_freed": self.bytes_freed,
+            // as_millis() 返回 u128，JSON 数字用 u64 足够
+            "elapsed_ms": self.elapsed.as_millis() as u64,
+            "root_status": self.root_status.as_str(),
+            "quarantine": self.quarantine.as_deref().map(path_json),
+            "run_id": self.run_id,
+            "syscalls": {
+                "read_dir": self.syscalls.read_dir,
+                "stat": self.syscalls.stat,
+                "remove": self.syscalls.remove,
+            },
+        })
+    }
+}
+
+// 错误转换成 JSON 对象
+pub fn error_json(error: &EptdirError) -> Value {
+    json!({
+        // map() 把 Option<&Path> 转换成 Option<Value>，None 会变成 JSON 的 null
+        "path": error.path().map(path_json),
+        "operation": error.operation().map(|op| op.as_str()),
+        "kind": error.kind().map(|kind| format!("{:?}", kind)),
+        "message": error.to_string(),
+    })
+}
+
+// 清理结束后根目录的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootStatus {
+    // 根目录里还有内容，保留
+    NotEmpty,
+    // 根目录已经为空，但按照选项保留了下来
+    KeptEmpty,
+    // 根目录为空并且被删除（预览模式下表示"将被删除"）
+    Removed,
+}
+
+impl RootStatus {
+    // JSON 输出中使用的名字
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RootStatus::NotEmpty => "not_empty",
+            RootStatus::KeptEmpty => "kept_empty",
+            RootStatus::Removed => "removed",
+        }
+    }
+}
+
+// 被跳过的条目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedEntry {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+// 跳过的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    // 符号链接（策略为 Report 时记录）
+    Symlink,
+    // 跟随符号链接时发现循环
+    SymlinkLoop,
+    // 匹配排除规则（目录的整棵子树都没有处理）
+    Excluded,
+    // 除了保留标记以外已经为空的目录：有保留标记文件，或者列在 .eptdirignore 中，没有删除
+    Kept,
+    // 本来会被删除，但是比最小年龄（CleanOptions::min_age）新
+    TooNew,
+    // 本来会被删除，但是观察者（CleanOptions::observer）否决了
+    Vetoed,
+    // 执行清理计划时发现条目在制定计划以后变了（不存在了、不再为空、换成了另一个文件或者被修改过），
+    // 或者计划中没有它的修改时间，无法确认没有变
+    Changed,
+}
+
+impl SkipReason {
+    // JSON 输出中使用的名字
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::Symlink => "symlink",
+            SkipReason::SymlinkLoop => "symlink_loop",
+            SkipReason::Excluded => "excluded",
+            SkipReason::Kept => "kept",
+            SkipReason::TooNew => "too_new",
+            SkipReason::Vetoed => "vetoed",
+            SkipReason::Changed => "changed",
+        }
+    }
+}
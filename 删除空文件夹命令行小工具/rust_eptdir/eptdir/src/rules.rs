@@ -0,0 +1,455 @@
+// ========================================
+// 垃圾文件匹配规则
+// ========================================
+// 一条规则可以是：
+//   - 精确文件名：thumbs.db
+//   - 通配符（glob）：*.tmp、~$*.docx、._*
+//   - 正则表达式：regex:^~\$.*\.docx$
+// JunkRules 是规则的集合，只要有一条规则匹配，文件就被视为垃圾文件
+//
+// 每条规则还有一个匹配模式（MatchMode），决定大小写和 Unicode 规范化怎么处理，
+// 写在规则最前面：nocase:Thumbs.db、casefold:glob:*.TMP、nfc:café.txt
+//
+// Unix 上的文件名不一定是有效的 UTF-8，匹配直接在 OsStr 上进行：
+//   - 精确文件名规则是文本，不会匹配非 UTF-8 的文件名
+//   - 通配符中的 ? / * / [!...] 可以匹配无效的字节，其他字符只匹配自己
+//   - 正则表达式在原始字节上搜索（regex::bytes），. 只匹配有效的字符，
+//     匹配无效的字节要关闭 Unicode 模式：(?-u:.) 匹配任意一个字节，(?-u:\xFF) 匹配指定的字节
+//   - 正则表达式只能用默认的 exact: 和 nocase: 模式：casefold: 和 nfc: 要先转换规则再比较，
+//     而正则表达式的文本不能像文件名一样转换（\S 小写以后变成了 \s），这两种模式的正则规则会被拒绝
+//   - nocase: 正则表达式整个关闭了 Unicode 模式，这样大小写只对 ASCII 字母不敏感：
+//     . 匹配任意一个字节，\w、\d 只匹配 ASCII；需要按字符匹配的部分写成 (?u:...)
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+use regex::bytes::{Regex, RegexBuilder};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use crate::error::{EptdirError, Operation};
+use crate::{parse_duration, AgeTime, CleanOptions, JUNK_FILES};
+
+// 文件名的匹配模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    // 逐字符完全相同（默认）
+    #[default]
+    Exact,
+    // 忽略 ASCII 字母的大小写：Thumbs.db、THUMBS.DB 都匹配 thumbs.db
+    // Windows 和 macOS 的文件系统默认不区分大小写，内置规则使用这个模式
+    AsciiCaseInsensitive,
+    // Unicode 大小写折叠，同时忽略 NFC/NFD 的区别：ÄRGER 匹配 ärger，STRASSE 匹配 straße
+    UnicodeCaseFold,
+    // 区分大小写，但忽略 NFC/NFD 的区别
+    // macOS 的文件系统把 é 保存成 e + 组合用重音符（NFD），用户输入的规则通常是 NFC
+    Normalized,
+}
+
+impl MatchMode {
+    // 规则文本中的前缀
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            MatchMode::Exact => "exact:",
+            MatchMode::AsciiCaseInsensitive => "nocase:",
+            MatchMode::UnicodeCaseFold => "casefold:",
+            MatchMode::Normalized => "nfc:",
+        }
+    }
+
+    // 把文件名（或规则）转换成可以直接比较的形式
+    // Cow 在不需要转换时直接借用原字符串，避免复制
+    pub fn fold<'s>(&self, text: &'s str) -> Cow<'s, str> {
+        match self {
+            MatchMode::Exact => Cow::Borrowed(text),
+            MatchMode::AsciiCaseInsensitive => {
+                if text.bytes().any(|b| b.is_ascii_uppercase()) {
+                    Cow::Owned(text.to_ascii_lowercase())
+                } else {
+                    Cow::Borrowed(text)
+                }
+            }
+            // Unicode 标准的"规范无大小写匹配"：NFD → 大小写折叠 → NFC
+            MatchMode::UnicodeCaseFold => {
+                let decomposed: String = text.nfd().collect();
+                Cow::Owned(caseless::default_case_fold_str(&decomposed).nfc().collect())
+            }
+            MatchMode::Normalized => {
+                if is_nfc(text) {
+                    Cow::Borrowed(text)
+                } else {
+                    Cow::Owned(text.nfc().collect())
+                }
+            }
+        }
+    }
+}
+
+// 单条垃圾文件规则
+// enum 枚举：一个值只能是其中一种变体
+// 每个变体的第二个值是匹配模式
+#[derive(Debug, Clone)]
+pub enum JunkRule {
+    // 文件名必须完全相同
+    Name(String, MatchMode),
+    // 通配符：* 匹配任意多个字符，? 匹配一个字符，[abc] / [a-z] / [!abc] 匹配字符集合
+    Glob(String, MatchMode),
+    // 正则表达式：在文件名中搜索，需要完整匹配时请自行加上 ^ 和 $
+    // 模式只会是 exact: 或 nocase:；nocase: 编译成不区分大小写、关闭了 Unicode 模式的正则表达式
+    Regex(Regex, MatchMode),
+}
+
+impl JunkRule {
+    // 从文本解析一条规则
+    // 可选的匹配模式前缀：exact:（默认）/ nocase: / casefold: / nfc:
+    // 然后是规则种类前缀：name: / glob: / regex:
+    // 没有种类前缀时：包含通配符（* ? [）就当作 glob，否则当作精确文件名
+    pub fn parse(spec: &str) -> Result<JunkRule, EptdirError> {
+        let modes = [
+            MatchMode::Exact,
+            MatchMode::AsciiCaseInsensitive,
+            MatchMode::UnicodeCaseFold,
+            MatchMode::Normalized,
+        ];
+        // find_map() 找到第一个匹配的前缀，返回 (模式, 去掉前缀后的部分)
+        let (mode, rest) = modes
+            .iter()
+            .find_map(|mode| spec.strip_prefix(mode.prefix()).map(|rest| (*mode, rest)))
+            .unwrap_or((MatchMode::Exact, spec));
+
+        // strip_prefix() 如果字符串以指定前缀开头，返回去掉前缀后的部分
+        if let Some(name) = rest.strip_prefix("name:") {
+            return Ok(JunkRule::Name(name.to_string(), mode));
+        }
+        if let Some(pattern) = rest.strip_prefix("glob:") {
+            return Ok(JunkRule::Glob(pattern.to_string(), mode));
+        }
+        if let Some(pattern) = rest.strip_prefix("regex:") {
+            // 正则表达式自己的 (?i) 只是简单的大小写折叠（ß 不匹配 SS），也不处理 NFC/NFD，
+            // 和 casefold: / nfc: 对文件名的转换不一致，所以不支持这两种模式
+            if matches!(mode, MatchMode::UnicodeCaseFold | MatchMode::Normalized) {
+                return Err(EptdirError::InvalidRule {
+                    rule: spec.to_string(),
+                    message: format!("正则表达式规则不支持 {} 模式，只能使用 exact: 或 nocase:", mode.prefix()),
+                });
+            }
+            // RegexBuilder 编译正则表达式，语法错误时返回 Err
+            // Unicode 模式下的 (?i) 会折叠所有字母的大小写（Ä 匹配 ä，ſ 匹配 s），
+            // nocase: 只忽略 ASCII 大小写，所以关闭 Unicode 模式
+            let nocase = mode == MatchMode::AsciiCaseInsensitive;
+            return match RegexBuilder::new(pattern).case_insensitive(nocase).unicode(!nocase).build() {
+                Ok(regex) => Ok(JunkRule::Regex(regex, mode)),
+                Err(e) => Err(EptdirError::InvalidRule { rule: spec.to_string(), message: e.to_string() }),
+            };
+        }
+
+        if rest.is_empty() {
+            return Err(EptdirError::InvalidRule {
+                rule: spec.to_string(),
+                message: "垃圾文件规则不能为空".to_string(),
+            });
+        }
+
+        if rest.contains(['*', '?', '[']) {
+            Ok(JunkRule::Glob(rest.to_string(), mode))
+        } else {
+            Ok(JunkRule::Name(rest.to_string(), mode))
+        }
+    }
+
+    // 判断文件名是否匹配这条规则
+    pub fn matches(&self, name: &str) -> bool {
+        self.matches_os(OsStr::new(name))
+    }
+
+    // 判断文件名（可能不是有效的 UTF-8）是否匹配这条规则
+    // 规则和文件名先按匹配模式转换，再比较
+    pub fn matches_os(&self, name: &OsStr) -> bool {
+        match self {
+            JunkRule::Name(expected, mode) => match name.to_str() {
+                Some(name) => mode.fold(expected) == mode.fold(name),
+                None => false,
+            },
+            JunkRule::Glob(pattern, mode) => {
+                glob_match_units(&name_units(*mode, OsStr::new(pattern)), &name_units(*mode, name))
+            }
+            // 正则表达式自己处理大小写，文件名不需要转换
+            JunkRule::Regex(regex, _) => regex.is_match(name.as_encoded_bytes()),
+        }
+    }
+}
+
+// 把文件名拆成匹配用的单元：有效的字符是 Some(字符)，每个无效的字节是 None
+// 有效的部分先按匹配模式转换
+pub(crate) fn name_units(mode: MatchMode, name: &OsStr) -> Vec<Option<char>> {
+    let mut units = Vec::new();
+    for chunk in name.as_encoded_bytes().utf8_chunks() {
+        units.extend(mode.fold(chunk.valid()).chars().map(Some));
+        units.extend(chunk.invalid().iter().map(|_| None));
+    }
+    units
+}
+
+// 垃圾文件规则集合
+#[derive(Debug, Clone)]
+pub struct JunkRules {
+    rules: Vec<JunkRule>,
+    // rules 开头有几条是内置规则，default-junk = false 只去掉这几条
+    // 用户自己写的同名规则（例如 junk = thumbs.db）不受影响
+    defaults: usize,
+}
+
+// 默认规则集合就是内置的 JUNK_FILES 列表
+// 内置规则忽略 ASCII 大小写：Windows 资源管理器写的是 Thumbs.db
+impl Default for JunkRules {
+    fn default() -> Self {
+        JunkRules {
+            rules: JUNK_FILES
+                .iter()
+                .map(|name| JunkRule::Name(name.to_string(), MatchMode::AsciiCaseInsensitive))
+                .collect(),
+            defaults: JUNK_FILES.len(),
+        }
+    }
+}
+
+impl JunkRules {
+    // 创建一个空的规则集合（不包含内置规则）
+    pub fn empty() -> Self {
+        JunkRules { rules: Vec::new(), defaults: 0 }
+    }
+
+    // 添加一条规则
+    pub fn add(&mut self, rule: JunkRule) {
+        self.rules.push(rule);
+    }
+
+    // 解析并添加一条文本规则，语法见 JunkRule::parse
+    pub fn add_spec(&mut self, spec: &str) -> Result<(), EptdirError> {
+        self.rules.push(JunkRule::parse(spec)?);
+        Ok(())
+    }
+
+    // 所有规则（只读）
+    pub fn rules(&self) -> &[JunkRule] {
+        &self.rules
+    }
+
+    // 判断文件名是否是垃圾文件：任意一条规则匹配即可
+    pub fn is_junk(&self, name: &str) -> bool {
+        self.is_junk_os(OsStr::new(name))
+    }
+
+    // 和 is_junk 相同，文件名可以不是有效的 UTF-8
+    pub fn is_junk_os(&self, name: &OsStr) -> bool {
+        // iter().any() 只要有一个元素满足条件就返回 true
+        self.rules.iter().any(|rule| rule.matches_os(name))
+    }
+}
+
+// ========================================
+// 配置文件
+// ========================================
+// 配置文件是纯文本，每行一个 "键 = 值"，# 开头的行是注释：
+//
+//   # 额外的垃圾文件（可以加匹配模式前缀，例如 nocase:desktop.ini）
+//   junk = *.tmp
+//   junk = regex:^~\$.*\.docx$
+//   # 不使用内置的 thumbs.db / .DS_Store 规则
+//   default-junk = false
+//   # 排除和重新包含的路径（gitignore 风格，见 filter.rs）
+//   exclude = .git/
+//   exclude = /logs/**
+//   include = /logs/keep/
+//   # 额外的保留标记文件名
+//   keep-marker = .placeholder
+//   # 同时删除 0 字节的文件，以及不删除的 0 字节文件名
+//   empty-files = true
+//   keep-empty = *.lock
+//   # 只删除 7 天以前的条目，比较状态改变时间
+//   min-age = 7d
+//   age-time = ctime
+//
+// 配置文件里的规则追加到 rules 后面，排除和包含规则追加到 options.exclude / options.include 后面，
+// 保留标记和不删除的 0 字节文件名追加到 options 中对应的列表后面
+pub fn load_config(path: &Path, rules: &mut JunkRules, options: &mut CleanOptions) -> Result<(), EptdirError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => return Err(EptdirError::io(path, Operation::ReadFile, e)),
+    };
+    // 生成带行号的配置错误
+    let config_error = |index: usize, message: String| EptdirError::Config {
+        path: path.to_path_buf(),
+        line: index + 1,
+        message,
+    };
+
+    // lines() 按行拆分，enumerate() 提供行号（从 0 开始）
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        // 跳过空行和注释
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // split_once('=') 在第一个 = 处拆分成 (键, 值)
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(config_error(index, "缺少 '='".to_string()));
+        };
+        let value = value.trim();
+
+        match key.trim() {
+            "junk" => rules.add_spec(value).map_err(|e| config_error(index, e.to_string()))?,
+            "exclude" => options.exclude.push(value.to_string()),
+            "include" => options.include.push(value.to_string()),
+            "keep-marker" => options.keep_markers.push(value.to_string()),
+            "keep-empty" => options.empty_file_sentinels.push(value.to_string()),
+            "min-age" => match parse_duration(value) {
+                Some(age) => options.min_age = Some(age),
+                None => return Err(config_error(index, format!("min-age 需要一个时长，例如 2h、7d，实际是 {}", value))),
+            },
+            "age-time" => match AgeTime::parse(value) {
+                Some(age_time) => options.age_time = age_time,
+                None => {
+                    return Err(config_error(index, format!("age-time 只能是 mtime、ctime 或 atime，实际是 {}", value)));
+                }
+            },
+            "empty-files" => match value {
+                "true" => options.empty_files = true,
+                "false" => options.empty_files = false,
+                other => {
+                    return Err(config_error(index, format!("empty-files 只能是 true 或 false，实际是 {}", other)));
+                }
+            },
+            "default-junk" => match value {
+                "true" => {}
+                // 去掉内置规则，前面 junk = 添加的规则保留
+                "false" => {
+                    rules.rules.drain(..rules.defaults);
+                    rules.defaults = 0;
+                }
+                other => {
+                    return Err(config_error(
+                        index,
+                        format!("default-junk 只能是 true 或 false，实际是 {}", other),
+                    ));
+                }
+            },
+            other => {
+                return Err(config_error(index, format!("未知的配置项 {}", other)));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// ========================================
+// 通配符匹配
+// ========================================
+// 经典的回溯算法：遇到 * 时记住位置，后面匹配失败就让 * 多吃一个字符再试
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    glob_match_os(pattern, OsStr::new(name))
+}
+
+// 和 glob_match 相同，文件名可以不是有效的 UTF-8
+pub fn glob_match_os(pattern: &str, name: &OsStr) -> bool {
+    glob_match_units(&name_units(MatchMode::Exact, OsStr::new(pattern)), &name_units(MatchMode::Exact, name))
+}
+
+// 按单元（字符或无效字节，而不是 UTF-8 字节）比较，这样中文文件名也能正确匹配 ?
+// 模式里的 None 只会和文件名里的 None 相等
+pub(crate) fn glob_match_units(pattern: &[Option<char>], name: &[Option<char>]) -> bool {
+
+    let mut p = 0; // pattern 中的位置
+    let mut n = 0; // name 中的位置
+    // 最近一次 * 的位置，以及当时 name 的位置
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                Some('*') => {
+                    star = Some((p, n));
+                    p += 1;
+                    continue;
+                }
+                Some('?') => {
+                    p += 1;
+                    n += 1;
+                    continue;
+                }
+                Some('[') => {
+                    if let Some((matched, next)) = match_class(pattern, p, name[n]) {
+                        if matched {
+                            p = next;
+                            n += 1;
+                            continue;
+                        }
+                    } else if name[n] == Some('[') {
+                        // 没有闭合的 [ 当作普通字符
+                        p += 1;
+                        n += 1;
+                        continue;
+                    }
+                }
+                c => {
+                    if c == name[n] {
+                        p += 1;
+                        n += 1;
+                        continue;
+                    }
+                }
+            }
+        }
+
+        // 当前字符匹配失败：回到上一个 *，让它多匹配一个字符
+        match star {
+            Some((star_p, star_n)) => {
+                p = star_p + 1;
+                n = star_n + 1;
+                star = Some((star_p, star_n + 1));
+            }
+            None => return false,
+        }
+    }
+
+    // name 已经用完，pattern 剩下的只能是 *
+    pattern[p..].iter().all(|&c| c == Some('*'))
+}
+
+// 匹配字符集合 [abc] / [a-z] / [!abc]
+// 返回 Some((是否匹配, 集合结束后的位置))；没有闭合的 ] 时返回 None
+// 无效的字节（c 是 None）不属于任何集合，只有 [!...] 能匹配它
+fn match_class(pattern: &[Option<char>], start: usize, c: Option<char>) -> Option<(bool, usize)> {
+    let mut i = start + 1;
+    let negate = i < pattern.len() && (pattern[i] == Some('!') || pattern[i] == Some('^'));
+    if negate {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    while i < pattern.len() {
+        // 第一个字符是 ] 时当作普通字符，例如 []abc]
+        if pattern[i] == Some(']') && !first {
+            return Some((matched != negate, i + 1));
+        }
+        first = false;
+
+        // 范围 a-z
+        if i + 2 < pattern.len() && pattern[i + 1] == Some('-') && pattern[i + 2] != Some(']') {
+            if c.is_some() && pattern[i] <= c && c <= pattern[i + 2] {
+                matched = true;
+            }
+            i += 3;
+        } else {
+            if c.is_some() && pattern[i] == c {
+                matched = true;
+            }
+            i += 1;
+        }
+    }
+
+    None
+}
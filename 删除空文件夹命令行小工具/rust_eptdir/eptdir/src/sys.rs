@@ -0,0 +1,430 @@
+// ========================================
+// 平台相关的目录操作
+// ========================================
+// 遍历时每个目录打开为一个 DirHandle，读取、删除其中的条目都相对于这个句柄进行：
+//   - Unix：句柄是目录的文件描述符，使用 openat / fstatat / unlinkat（通过 rustix），
+//     不需要拼出完整路径，目录树的深度和路径长度只受文件系统本身的限制（不会遇到 ENAMETOOLONG）；
+//     打开子目录时使用 O_NOFOLLOW，遍历过程中目录被换成符号链接也不会跑到目标目录以外
+//   - 其他平台：句柄只保存目录的路径，所有操作使用完整路径（std::fs）
+// 报告和错误中使用的完整路径由调用者自己维护
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+// 条目的类型（不跟随符号链接）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileKind {
+    File,
+    Dir,
+    Symlink,
+    // 设备文件、管道、套接字等
+    Other,
+}
+
+// 目录中的一个条目
+pub(crate) struct Entry {
+    pub name: OsString,
+    // 读取目录时得到的类型；None 表示文件系统没有提供，需要用 stat_at() 再查一次
+    pub kind: Option<FileKind>,
+}
+
+// 删除前读取的元数据（报告和撤销日志用到的部分）
+pub(crate) struct Meta {
+    pub kind: FileKind,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+    // 状态改变时间（ctime）；没有 ctime 的平台使用修改时间代替
+    pub changed: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    // Unix 权限位（例如 0o755），其他平台为 None
+    pub mode: Option<u32>,
+    pub readonly: bool,
+    // (设备号, inode 号)，用来确认还是同一个条目；其他平台为 None
+    pub inode: Option<(u64, u64)>,
+}
+
+impl From<&fs::Metadata> for Meta {
+    fn from(metadata: &fs::Metadata) -> Self {
+        let file_type = metadata.file_type();
+        Meta {
+            kind: if file_type.is_symlink() {
+                FileKind::Symlink
+            } else if file_type.is_dir() {
+                FileKind::Dir
+            } else if file_type.is_file() {
+                FileKind::File
+            } else {
+                FileKind::Other
+            },
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            changed: changed(metadata),
+            accessed: metadata.accessed().ok(),
+            mode: mode(metadata),
+            readonly: metadata.permissions().readonly(),
+            inode: inode(metadata),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn mode(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn mode(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(unix)]
+fn changed(metadata: &fs::Metadata) -> Option<SystemTime> {
+    use std::os::unix::fs::MetadataExt;
+    Some(crate::journal::join_time(metadata.ctime(), metadata.ctime_nsec() as u32))
+}
+
+#[cfg(not(unix))]
+fn changed(metadata: &fs::Metadata) -> Option<SystemTime> {
+    metadata.modified().ok()
+}
+
+// ========================================
+// Unix：基于文件描述符
+// ========================================
+#[cfg(unix)]
+pub(crate) use self::unix::{not_a_dir, DirHandle, DirId};
+
+#[cfg(unix)]
+mod unix {
+    use std::ffi::OsStr;
+    use std::fs::File;
+    use std::io::{self, Read};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    use rustix::fs::{self as rfs, AtFlags, Dir, FileType, Mode, OFlags, RawMode, Stat, CWD};
+    use rustix::io::Errno;
+
+    use super::{Entry, FileKind, Meta};
+    use crate::journal::join_time;
+
+    // 目录的标识：(设备号, inode 号)
+    pub(crate) type DirId = (u64, u64);
+
+    // 打开的目录；Dir 同时保存文件描述符和读取目录用的缓冲区
+    pub(crate) struct DirHandle {
+        dir: Dir,
+    }
+
+    impl DirHandle {
+        // 按路径打开目录（清理的目标目录），会跟随符号链接
+        pub(crate) fn open(path: &Path) -> io::Result<Self> {
+            let fd = rfs::openat(CWD, path, OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC, Mode::empty())?;
+            Ok(DirHandle { dir: Dir::new(fd)? })
+        }
+
+        // 打开其中的子目录；follow 为 false 时 name 是符号链接会失败，而不是打开链接指向的目录
+        pub(crate) fn open_dir(&self, name: &OsStr, follow: bool) -> io::Result<Self> {
+            let mut flags = OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC;
+            if !follow {
+                flags |= OFlags::NOFOLLOW;
+            }
+            let fd = rfs::openat(self.dir.fd()?, name, flags, Mode::empty())?;
+            Ok(DirHandle { dir: Dir::new(fd)? })
+        }
+
+        // 通过 ".." 打开上级目录
+        // 目录通过符号链接进入时，".." 是链接目标的上级目录，不是遍历路径上的上一层
+        pub(crate) fn parent(&self) -> io::Result<Self> {
+            self.open_dir(OsStr::new(".."), false)
+        }
+
+        // 读取目录中的条目（不包括 . 和 ..）
+        // 出错以后不再返回后面的条目
+        pub(crate) fn read(&mut self) -> impl Iterator<Item = io::Result<Entry>> + '_ {
+            std::iter::from_fn(|| self.dir.read()).filter_map(|entry| match entry {
+                Ok(entry) => {
+                    let name = entry.file_name().to_bytes();
+                    if name == b"." || name == b".." {
+                        return None;
+                    }
+                    Some(Ok(Entry { name: OsStr::from_bytes(name).to_os_string(), kind: kind(entry.file_type()) }))
+                }
+                Err(e) => Some(Err(e.into())),
+            })
+        }
+
+        // 目录是否为空
+        pub(crate) fn is_empty(&mut self) -> io::Result<bool> {
+            self.read().next().transpose().map(|entry| entry.is_none())
+        }
+
+        // 目录本身的元数据
+        pub(crate) fn stat(&self) -> io::Result<Meta> {
+            Ok(meta(&rfs::fstat(self.dir.fd()?)?))
+        }
+
+        // 其中一个条目的元数据（不跟随符号链接）
+        pub(crate) fn stat_at(&self, name: &OsStr) -> io::Result<Meta> {
+            Ok(meta(&rfs::statat(self.dir.fd()?, name, AtFlags::SYMLINK_NOFOLLOW)?))
+        }
+
+        // 目录的标识，用来判断两个句柄是不是同一个目录
+        pub(crate) fn id(&self) -> io::Result<DirId> {
+            let stat = rfs::fstat(self.dir.fd()?)?;
+            Ok(id(&stat))
+        }
+
+        // 读取其中一个文件的全部内容（不跟随符号链接）
+        pub(crate) fn read_file(&self, name: &OsStr) -> io::Result<Vec<u8>> {
+            let flags = OFlags::RDONLY | OFlags::NOFOLLOW | OFlags::CLOEXEC;
+            let mut file = File::from(rfs::openat(self.dir.fd()?, name, flags, Mode::empty())?);
+            let mut content = Vec::new();
+            file.read_to_end(&mut content)?;
+            Ok(content)
+        }
+
+        pub(crate) fn remove_file(&self, name: &OsStr) -> io::Result<()> {
+            Ok(rfs::unlinkat(self.dir.fd()?, name, AtFlags::empty())?)
+        }
+
+        pub(crate) fn remove_dir(&self, name: &OsStr) -> io::Result<()> {
+            Ok(rfs::unlinkat(self.dir.fd()?, name, AtFlags::REMOVEDIR)?)
+        }
+
+        // 在这个目录中把条目 from 改名为 to
+        pub(crate) fn rename(&self, from: &OsStr, to: &OsStr) -> io::Result<()> {
+            let fd = self.dir.fd()?;
+            Ok(rfs::renameat(fd, from, fd, to)?)
+        }
+
+        // 把条目 from 移动到另一个目录 to_dir 中，改名为 to（不能跨文件系统）
+        pub(crate) fn rename_to(&self, from: &OsStr, to_dir: &DirHandle, to: &OsStr) -> io::Result<()> {
+            Ok(rfs::renameat(self.dir.fd()?, from, to_dir.dir.fd()?, to)?)
+        }
+
+        // 把普通文件 from 复制到另一个目录 to_dir 中的 to；to 已经存在时失败
+        pub(crate) fn copy_to(&self, from: &OsStr, to_dir: &DirHandle, to: &OsStr) -> io::Result<()> {
+            let flags = OFlags::RDONLY | OFlags::NOFOLLOW | OFlags::CLOEXEC;
+            let mut source = File::from(rfs::openat(self.dir.fd()?, from, flags, Mode::empty())?);
+            let flags = OFlags::WRONLY | OFlags::CREATE | OFlags::EXCL | OFlags::NOFOLLOW | OFlags::CLOEXEC;
+            let mut dest = File::from(rfs::openat(to_dir.dir.fd()?, to, flags, Mode::from_raw_mode(0o666))?);
+            io::copy(&mut source, &mut dest)?;
+            Ok(())
+        }
+
+        // 在这个目录中创建子目录
+        pub(crate) fn create_dir(&self, name: &OsStr) -> io::Result<()> {
+            Ok(rfs::mkdirat(self.dir.fd()?, name, Mode::from_raw_mode(0o777))?)
+        }
+    }
+
+    // 打开符号链接指向的目录失败，是不是因为它不指向目录（指向文件、已经失效、链接层数太多）
+    pub(crate) fn not_a_dir(error: &io::Error) -> bool {
+        [Errno::NOTDIR, Errno::NOENT, Errno::LOOP].iter().any(|errno| error.raw_os_error() == Some(errno.raw_os_error()))
+    }
+
+    fn kind(file_type: FileType) -> Option<FileKind> {
+        match file_type {
+            FileType::RegularFile => Some(FileKind::File),
+            FileType::Directory => Some(FileKind::Dir),
+            FileType::Symlink => Some(FileKind::Symlink),
+            FileType::Unknown => None,
+            _ => Some(FileKind::Other),
+        }
+    }
+
+    // stat 结构中字段的整数类型随平台不同，统一转换
+    #[allow(clippy::unnecessary_cast)]
+    fn meta(stat: &Stat) -> Meta {
+        let mode = stat.st_mode as RawMode;
+        Meta {
+            kind: kind(FileType::from_raw_mode(mode)).unwrap_or(FileKind::Other),
+            len: stat.st_size as u64,
+            modified: Some(join_time(stat.st_mtime as i64, stat.st_mtime_nsec as u32)),
+            changed: Some(join_time(stat.st_ctime as i64, stat.st_ctime_nsec as u32)),
+            accessed: Some(join_time(stat.st_atime as i64, stat.st_atime_nsec as u32)),
+            mode: Some(mode as u32 & 0o7777),
+            readonly: mode as u32 & 0o222 == 0,
+            inode: Some(id(stat)),
+        }
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    fn id(stat: &Stat) -> DirId {
+        (stat.st_dev as u64, stat.st_ino as u64)
+    }
+}
+
+// ========================================
+// 其他平台：基于路径
+// ========================================
+#[cfg(not(unix))]
+pub(crate) use self::path::{not_a_dir, DirHandle, DirId};
+
+#[cfg(not(unix))]
+mod path {
+    use std::ffi::OsStr;
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    use super::{Entry, FileKind, Meta};
+
+    // 目录的标识：解析所有符号链接以后的真实路径
+    pub(crate) type DirId = PathBuf;
+
+    pub(crate) struct DirHandle {
+        path: PathBuf,
+        // 打开时就读取目录，这样打不开的目录和 Unix 上一样在 open 时报错
+        entries: Option<fs::ReadDir>,
+    }
+
+    impl DirHandle {
+        pub(crate) fn open(path: &Path) -> io::Result<Self> {
+            let entries = fs::read_dir(path)?;
+            Ok(DirHandle { path: path.to_path_buf(), entries: Some(entries) })
+        }
+
+        pub(crate) fn open_dir(&self, name: &OsStr, _follow: bool) -> io::Result<Self> {
+            DirHandle::open(&self.path.join(name))
+        }
+
+        pub(crate) fn parent(&self) -> io::Result<Self> {
+            match self.path.parent() {
+                Some(parent) => DirHandle::open(parent),
+                None => Err(io::Error::from(io::ErrorKind::NotFound)),
+            }
+        }
+
+        pub(crate) fn read(&mut self) -> impl Iterator<Item = io::Result<Entry>> + '_ {
+            self.entries.take().into_iter().flatten().map(|entry| {
+                let entry = entry?;
+                let kind = entry.file_type().ok().map(|file_type| {
+                    if file_type.is_symlink() {
+                        FileKind::Symlink
+                    } else if file_type.is_dir() {
+                        FileKind::Dir
+                    } else if file_type.is_file() {
+                        FileKind::File
+                    } else {
+                        FileKind::Other
+                    }
+                });
+                Ok(Entry { name: entry.file_name(), kind })
+            })
+        }
+
+        pub(crate) fn is_empty(&mut self) -> io::Result<bool> {
+            self.read().next().transpose().map(|entry| entry.is_none())
+        }
+
+        pub(crate) fn stat(&self) -> io::Result<Meta> {
+            Ok(Meta::from(&fs::symlink_metadata(&self.path)?))
+        }
+
+        pub(crate) fn stat_at(&self, name: &OsStr) -> io::Result<Meta> {
+            Ok(Meta::from(&fs::symlink_metadata(self.path.join(name))?))
+        }
+
+        pub(crate) fn id(&self) -> io::Result<DirId> {
+            fs::canonicalize(&self.path)
+        }
+
+        pub(crate) fn read_file(&self, name: &OsStr) -> io::Result<Vec<u8>> {
+            fs::read(self.path.join(name))
+        }
+
+        pub(crate) fn remove_file(&self, name: &OsStr) -> io::Result<()> {
+            fs::remove_file(self.path.join(name))
+        }
+
+        pub(crate) fn remove_dir(&self, name: &OsStr) -> io::Result<()> {
+            fs::remove_dir(self.path.join(name))
+        }
+
+        pub(crate) fn rename(&self, from: &OsStr, to: &OsStr) -> io::Result<()> {
+            fs::rename(self.path.join(from), self.path.join(to))
+        }
+
+        pub(crate) fn rename_to(&self, from: &OsStr, to_dir: &DirHandle, to: &OsStr) -> io::Result<()> {
+            fs::rename(self.path.join(from), to_dir.path.join(to))
+        }
+
+        pub(crate) fn copy_to(&self, from: &OsStr, to_dir: &DirHandle, to: &OsStr) -> io::Result<()> {
+            let dest = to_dir.path.join(to);
+            if dest.symlink_metadata().is_ok() {
+                return Err(io::Error::from(io::ErrorKind::AlreadyExists));
+            }
+            fs::copy(self.path.join(from), dest).map(|_| ())
+        }
+
+        pub(crate) fn create_dir(&self, name: &OsStr) -> io::Result<()> {
+            fs::create_dir(self.path.join(name))
+        }
+    }
+
+    pub(crate) fn not_a_dir(error: &io::Error) -> bool {
+        matches!(error.kind(), io::ErrorKind::NotFound | io::ErrorKind::NotADirectory)
+    }
+}
+
+// 没有上级目录的句柄时（清理的目标目录本身）按路径读取元数据
+pub(crate) fn stat_path(path: &Path) -> io::Result<Meta> {
+    Ok(Meta::from(&fs::symlink_metadata(path)?))
+}
+
+// path 所在文件系统的设备号（跟随符号链接）；其他平台没有设备号，返回 None
+pub(crate) fn device(path: &Path) -> Option<u64> {
+    fs::metadata(path).ok().and_then(|metadata| inode(&metadata)).map(|(dev, _)| dev)
+}
+
+// 当前用户的 uid（分区上的回收站 .Trash-$uid 用到）；其他平台没有 uid，返回 None
+#[cfg(unix)]
+pub(crate) fn uid() -> Option<u32> {
+    Some(rustix::process::getuid().as_raw())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn uid() -> Option<u32> {
+    None
+}
+
+// 创建只有自己能访问（0700）的目录，分区上的回收站要求这样的权限
+#[cfg(unix)]
+pub(crate) fn create_private_dir(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    fs::DirBuilder::new().mode(0o700).create(path)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn create_private_dir(path: &Path) -> io::Result<()> {
+    fs::create_dir(path)
+}
+
+// 上级目录的路径；相对路径只有一层时（例如 "a"）是当前目录
+pub(crate) fn parent_path(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+// 条目的名字；路径没有最后一个组成部分（例如 ".."）时使用整个路径
+pub(crate) fn file_name(path: &Path) -> &OsStr {
+    path.file_name().unwrap_or(path.as_os_str())
+}
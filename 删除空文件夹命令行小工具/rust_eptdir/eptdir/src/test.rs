@@ -12,7 +12,7 @@ mod tests {
     // 导入文件系统操作（创建、删除文件等）
     use std::fs;
     //导入路径类型
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
     use std::io::Write;
     
     // ========================================
@@ -57,7 +57,7 @@ mod tests {
         fs::File::create(&junk_file).unwrap();
         
         // 执行被测试的函数
-        let result = remove_junk_files(&test_dir);
+        let result = remove_junk_files(&test_dir, &JunkRules::default(), &CleanOptions::default());
         // assert! 宏：如果条件为 false，测试失败
         // 注意：这里的消息是在断言失败（panic）时显示的，所以应该描述"期望什么但实际失败了"
         assert!(result.is_ok(), "期望函数执行成功，但实际失败了");
@@ -75,7 +75,7 @@ mod tests {
         fs::File::create(&junk_file).unwrap();
         
         // 执行被测试的函数
-        let result = remove_junk_files(&test_dir);
+        let result = remove_junk_files(&test_dir, &JunkRules::default(), &CleanOptions::default());
         // 检查操作是否成功执行，并验证删除的文件数量
         // expect 会在出错时 panic，assert_eq! 会验证返回值是否正确
         let deleted_count = result.expect("删除文件失败");
@@ -97,7 +97,7 @@ mod tests {
          // 写入测试内容
         file.write_all(b"test").unwrap();
         
-        let result = remove_junk_files(&test_dir);
+        let result = remove_junk_files(&test_dir, &JunkRules::default(), &CleanOptions::default());
         // result.is_ok() 检查操作是否成功执行（没有出错）
         // 注意：Ok 只表示"执行成功"，不表示"删除了文件"
         assert!(result.is_ok(), "期望函数执行成功，但实际失败了");
@@ -129,7 +129,7 @@ mod tests {
         let junk_file2 = test_dir.join(".DS_Store");
         fs::File::create(&junk_file2).unwrap();
         
-        let result = remove_junk_files(&test_dir);
+        let result = remove_junk_files(&test_dir, &JunkRules::default(), &CleanOptions::default());
         assert!(result.is_ok(), "期望函数执行成功，但实际失败了");
         // 应该递归删除 2 个垃圾文件（1个在主目录，1个在子目录）
         //assert_eq! 的作用
@@ -151,7 +151,7 @@ mod tests {
         // 测试空目录应该返回 0
         let test_dir = create_test_dir();
         
-        let result = remove_junk_files(&test_dir);
+        let result = remove_junk_files(&test_dir, &JunkRules::default(), &CleanOptions::default());
         assert!(result.is_ok(), "期望函数执行成功，但实际失败了");
         let deleted_count = result.unwrap();
         assert_eq!(deleted_count, 0, "期望空目录返回0，但实际返回了{}", deleted_count);
@@ -159,6 +159,167 @@ mod tests {
         fs::remove_dir_all(&test_dir).ok();
     }
     
+    // ========================================
+    // 测试垃圾文件规则
+    // ========================================
+
+    // 通配符规则：*、?、字符集合
+    #[test]
+    fn test_glob_match() {
+        use crate::rules::glob_match;
+        assert!(glob_match("*.tmp", "download.tmp"), "期望 *.tmp 匹配 download.tmp");
+        assert!(!glob_match("*.tmp", "download.tmp.txt"), "期望 *.tmp 不匹配 download.tmp.txt");
+        assert!(glob_match("~$*.docx", "~$报告.docx"), "期望 ~$*.docx 匹配 ~$报告.docx");
+        assert!(glob_match("._*", "._photo.jpg"), "期望 ._* 匹配 ._photo.jpg");
+        assert!(glob_match("file?.log", "file1.log"), "期望 file?.log 匹配 file1.log");
+        assert!(glob_match("[a-c]*.bak", "b.bak"), "期望 [a-c]*.bak 匹配 b.bak");
+        assert!(!glob_match("[!a-c]*.bak", "b.bak"), "期望 [!a-c]*.bak 不匹配 b.bak");
+    }
+
+    // 规则文本的解析：没有前缀时自动区分文件名和通配符
+    #[test]
+    fn test_junk_rule_parse() {
+        let rule = JunkRule::parse("*.tmp").unwrap();
+        assert!(matches!(rule, JunkRule::Glob(..)), "期望 *.tmp 被解析为通配符规则");
+        let rule = JunkRule::parse("desktop.ini").unwrap();
+        assert!(matches!(rule, JunkRule::Name(..)), "期望 desktop.ini 被解析为文件名规则");
+        let rule = JunkRule::parse(r"regex:^~\$.*\.docx$").unwrap();
+        assert!(rule.matches("~$报告.docx"), "期望正则规则匹配 ~$报告.docx");
+        assert!(JunkRule::parse("regex:(").is_err(), "期望无效的正则表达式返回错误");
+    }
+
+    // 内置规则忽略 ASCII 大小写：Windows 写的 Thumbs.db、全大写的 THUMBS.DB 都会被删除
+    #[test]
+    fn test_remove_junk_files_case_insensitive() {
+        let test_dir = create_test_dir();
+        let windows_file = test_dir.join("Thumbs.db");
+        fs::File::create(&windows_file).unwrap();
+        fs::create_dir(test_dir.join("sub")).unwrap();
+        let upper_file = test_dir.join("sub").join("THUMBS.DB");
+        fs::File::create(&upper_file).unwrap();
+
+        let deleted_count = remove_junk_files(&test_dir, &JunkRules::default(), &CleanOptions::default())
+            .expect("删除文件失败");
+        assert_eq!(deleted_count, 2, "期望删除2个垃圾文件，但实际删除了{}个", deleted_count);
+        assert!(!windows_file.exists(), "期望 Thumbs.db 被删除");
+        assert!(!upper_file.exists(), "期望 THUMBS.DB 被删除");
+
+        // exact: 前缀的规则区分大小写
+        let rule = JunkRule::parse("exact:thumbs.db").unwrap();
+        assert!(!rule.matches("Thumbs.db"), "期望 exact: 规则不匹配 Thumbs.db");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 每条规则可以选择匹配模式：nocase / casefold / nfc
+    #[test]
+    fn test_junk_rule_match_modes() {
+        // é 的两种写法：一个字符（NFC），或者 e + 组合用重音符（NFD，macOS 文件名常见）
+        let composed = "caf\u{e9}.tmp";
+        let decomposed = "cafe\u{301}.tmp";
+
+        let exact = JunkRule::parse(composed).unwrap();
+        assert!(!exact.matches(decomposed), "期望默认模式区分 NFC 和 NFD");
+        let nfc = JunkRule::parse(&format!("nfc:{}", composed)).unwrap();
+        assert!(nfc.matches(decomposed), "期望 nfc: 规则匹配分解形式的文件名");
+        assert!(!nfc.matches("CAF\u{c9}.tmp"), "期望 nfc: 规则仍然区分大小写");
+        let glob = JunkRule::parse("nfc:glob:caf?.tmp").unwrap();
+        assert!(glob.matches(decomposed), "期望 ? 匹配规范化后的一个字符");
+
+        // ASCII 忽略大小写不处理非 ASCII 字母，Unicode 大小写折叠会处理
+        let nocase = JunkRule::parse("nocase:\u{e4}rger.txt").unwrap();
+        assert!(nocase.matches("\u{e4}RGER.TXT"), "期望 nocase: 忽略 ASCII 大小写");
+        assert!(!nocase.matches("\u{c4}RGER.TXT"), "期望 nocase: 不处理 Ä");
+        let casefold = JunkRule::parse("casefold:\u{e4}rger.txt").unwrap();
+        assert!(casefold.matches("\u{c4}RGER.TXT"), "期望 casefold: 匹配 ÄRGER.TXT");
+        assert!(casefold.matches("A\u{308}RGER.TXT"), "期望 casefold: 同时忽略 NFC/NFD 的区别");
+        let strasse = JunkRule::parse("casefold:STRASSE.txt").unwrap();
+        assert!(strasse.matches("stra\u{df}e.txt"), "期望 casefold: 使用完整的大小写折叠（ß → ss）");
+
+        // 正则表达式无法按 casefold: / nfc: 转换，这两种模式的正则规则报错
+        assert!(JunkRule::parse("casefold:regex:^stra\u{df}e$").is_err(), "期望拒绝 casefold: 正则规则");
+        assert!(JunkRule::parse(r"nfc:regex:^caf\u{e9}\.tmp$").is_err(), "期望拒绝 nfc: 正则规则");
+        let regex = JunkRule::parse("nocase:regex:^\u{e4}rger\\.txt$").unwrap();
+        assert!(regex.matches("\u{e4}RGER.TXT"), "期望 nocase: 正则规则忽略 ASCII 大小写");
+        assert!(!regex.matches("\u{c4}RGER.TXT"), "期望 nocase: 正则规则不处理 Ä");
+    }
+
+    // 库的调用者可以传入自己的规则集合
+    #[test]
+    fn test_remove_junk_files_custom_rules() {
+        let test_dir = create_test_dir();
+        let tmp_file = test_dir.join("download.tmp");
+        fs::File::create(&tmp_file).unwrap();
+        let office_file = test_dir.join("~$报告.docx");
+        fs::File::create(&office_file).unwrap();
+        // 不在自定义规则中的内置垃圾文件应该保留
+        let thumbs = test_dir.join("thumbs.db");
+        fs::File::create(&thumbs).unwrap();
+
+        let mut rules = JunkRules::empty();
+        rules.add_spec("*.tmp").unwrap();
+        rules.add_spec(r"regex:^~\$.*\.docx$").unwrap();
+
+        let deleted_count = remove_junk_files(&test_dir, &rules, &CleanOptions::default())
+            .expect("删除文件失败");
+        assert_eq!(deleted_count, 2, "期望删除2个文件，但实际删除了{}个", deleted_count);
+        assert!(!tmp_file.exists(), "期望 download.tmp 被删除，但文件仍然存在");
+        assert!(!office_file.exists(), "期望 ~$报告.docx 被删除，但文件仍然存在");
+        assert!(thumbs.exists(), "期望 thumbs.db 保留，但文件不存在");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 从配置文件加载规则
+    #[test]
+    fn test_load_config() {
+        let test_dir = create_test_dir();
+        let config = test_dir.join("eptdir.conf");
+        fs::write(&config, "# 注释\njunk = *.tmp\n\ndefault-junk = false\n").unwrap();
+
+        let mut rules = JunkRules::default();
+        rules::load_config(&config, &mut rules, &mut CleanOptions::default()).expect("读取配置文件失败");
+        assert!(rules.is_junk("a.tmp"), "期望配置文件中的 *.tmp 规则生效");
+        assert!(!rules.is_junk("thumbs.db"), "期望 default-junk = false 去掉内置规则");
+
+        // 用户自己写的和内置规则同名的规则不受 default-junk = false 影响
+        fs::write(&config, "junk = thumbs.db\ndefault-junk = false\n").unwrap();
+        let mut rules = JunkRules::default();
+        rules::load_config(&config, &mut rules, &mut CleanOptions::default()).expect("读取配置文件失败");
+        assert!(rules.is_junk("thumbs.db"), "期望保留用户写的 junk = thumbs.db");
+        assert!(!rules.is_junk("Thumbs.db"), "期望用户写的规则仍然区分大小写");
+        assert!(!rules.is_junk(".DS_Store"), "期望其他内置规则被去掉");
+
+        // 排除和包含规则写入清理选项
+        fs::write(&config, "exclude = .git/\nexclude = /logs/**\ninclude = /logs/keep/\n").unwrap();
+        let mut options = CleanOptions::default();
+        rules::load_config(&config, &mut rules, &mut options).expect("读取配置文件失败");
+        assert_eq!(options.exclude, vec![".git/".to_string(), "/logs/**".to_string()], "期望读取排除规则");
+        assert_eq!(options.include, vec!["/logs/keep/".to_string()], "期望读取包含规则");
+
+        fs::write(&config, "keep-marker = .placeholder\n").unwrap();
+        rules::load_config(&config, &mut rules, &mut options).expect("读取配置文件失败");
+        assert_eq!(options.keep_markers, vec![".placeholder".to_string()], "期望读取保留标记");
+
+        fs::write(&config, "empty-files = true\nkeep-empty = *.lock\n").unwrap();
+        rules::load_config(&config, &mut rules, &mut options).expect("读取配置文件失败");
+        assert!(options.empty_files, "期望读取 empty-files");
+        assert_eq!(options.empty_file_sentinels, vec!["*.lock".to_string()], "期望读取不删除的 0 字节文件名");
+
+        fs::write(&config, "min-age = 2h\nage-time = atime\n").unwrap();
+        rules::load_config(&config, &mut rules, &mut options).expect("读取配置文件失败");
+        assert_eq!(options.min_age, Some(std::time::Duration::from_secs(2 * 60 * 60)), "期望读取 min-age");
+        assert_eq!(options.age_time, AgeTime::Accessed, "期望读取 age-time");
+        fs::write(&config, "min-age = soon\n").unwrap();
+        assert!(rules::load_config(&config, &mut rules, &mut options).is_err(), "期望无效的时长报错");
+
+        // 未知的配置项应该报错
+        fs::write(&config, "unknown = 1\n").unwrap();
+        assert!(rules::load_config(&config, &mut rules, &mut CleanOptions::default()).is_err(), "期望未知配置项返回错误");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
     // ========================================
     // 测试 remove_empty_dirs 函数
     // ========================================
@@ -174,7 +335,7 @@ mod tests {
         let empty_subdir = test_dir.join("empty_subdir");
         fs::create_dir(&empty_subdir).unwrap();
         
-        let result = remove_empty_dirs(&test_dir);
+        let result = remove_empty_dirs(&test_dir, &CleanOptions::default());
         assert!(result.is_ok(), "期望函数执行成功，但实际失败了");
         // 空子目录应该被删除
         assert!(!empty_subdir.exists(), "期望空子目录被删除，但目录仍然存在");
@@ -199,7 +360,7 @@ mod tests {
         
         fs::create_dir_all(&subdir3).unwrap();
         
-        let result = remove_empty_dirs(&test_dir);
+        let result = remove_empty_dirs(&test_dir, &CleanOptions::default());
         assert!(result.is_ok(), "期望函数执行成功，但实际失败了");
         // 所有嵌套的空目录都应该被删除
         assert!(!subdir3.exists(), "期望subdir3被删除，但目录仍然存在");
@@ -226,26 +387,530 @@ mod tests {
         let file = subdir.join("file.txt");
         fs::File::create(&file).unwrap();
         
-        let result = remove_empty_dirs(&test_dir);
+        let result = remove_empty_dirs(&test_dir, &CleanOptions::default());
         assert!(result.is_ok(), "期望函数执行成功，但实际失败了");
         // 因为有文件，子目录不应该被删除
         assert!(subdir.exists(), "期望有文件的目录保留，但目录不存在");
+
+        // file.txt 是 0 字节的文件：remove_empty_dirs 不受 empty_files 影响，只删除空文件夹
+        let options = CleanOptions { empty_files: true, ..Default::default() };
+        remove_empty_dirs(&test_dir, &options).expect("删除空文件夹失败");
+        assert!(file.exists() && subdir.exists(), "期望 remove_empty_dirs 不删除 0 字节的文件");
         
         fs::remove_dir_all(&test_dir).ok();
     }
     
+    // 默认保留根目录，即使它清理后为空
+    #[test]
+    fn test_remove_empty_dirs_keeps_root() {
+        let test_dir = create_test_dir();
+        let subdir = test_dir.join("subdir");
+        fs::create_dir(&subdir).unwrap();
+
+        let removed = remove_empty_dirs(&test_dir, &CleanOptions::default()).expect("删除空文件夹失败");
+        assert!(!removed, "期望根目录不被删除，但实际返回了删除");
+        assert!(!subdir.exists(), "期望空子目录被删除，但目录仍然存在");
+        assert!(test_dir.exists(), "期望根目录保留，但目录不存在");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // remove_root 选项：根目录为空时也删除
+    #[test]
+    fn test_remove_empty_dirs_remove_root() {
+        let test_dir = create_test_dir();
+        fs::create_dir(test_dir.join("subdir")).unwrap();
+
+        let options = CleanOptions { remove_root: true, ..Default::default() };
+        let removed = remove_empty_dirs(&test_dir, &options).expect("删除空文件夹失败");
+        assert!(removed, "期望根目录被删除，但实际返回了未删除");
+        assert!(!test_dir.exists(), "期望根目录被删除，但目录仍然存在");
+    }
+
+    // "实际上为空"的条件：只剩下满足条件的文件的目录连同文件一起删除，其他目录中的这些文件原样保留
+    #[test]
+    fn test_remove_empty_dirs_predicate() {
+        let test_dir = create_test_dir();
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::File::create(test_dir.join("a/.DS_Store")).unwrap();
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::File::create(test_dir.join("b/.DS_Store")).unwrap();
+        fs::write(test_dir.join("b/notes.txt"), b"123").unwrap();
+        fs::create_dir_all(test_dir.join("c/d")).unwrap();
+        fs::File::create(test_dir.join("c/thumbs.db")).unwrap();
+        fs::File::create(test_dir.join("c/d/thumbs.db")).unwrap();
+
+        // 没有条件时只有真正的空目录才删除
+        assert!(!remove_empty_dirs(&test_dir, &CleanOptions::default()).unwrap());
+        assert!(test_dir.join("a/.DS_Store").exists(), "期望默认不删除任何文件");
+
+        // 预览模式：级联报告，不修改磁盘
+        let options = CleanOptions { empty_predicate: Some(EmptyPredicate::junk()), ..Default::default() };
+        let report = clean_directory(&test_dir, &JunkRules::empty(), &CleanOptions { dry_run: true, ..options.clone() })
+            .expect("预览失败");
+        assert_eq!(
+            report.removed_junk_files,
+            vec![test_dir.join("a/.DS_Store"), test_dir.join("c/d/thumbs.db"), test_dir.join("c/thumbs.db")],
+            "期望报告和目录一起删除的文件"
+        );
+        assert_eq!(
+            report.removed_dirs,
+            vec![test_dir.join("a"), test_dir.join("c/d"), test_dir.join("c")],
+            "期望级联报告只有垃圾文件的目录"
+        );
+        assert!(test_dir.join("c/d/thumbs.db").exists(), "期望预览模式不删除文件");
+
+        assert!(!remove_empty_dirs(&test_dir, &options).expect("删除空文件夹失败"), "期望根目录保留");
+        assert!(!test_dir.join("a").exists() && !test_dir.join("c").exists(), "期望只有垃圾文件的目录被删除");
+        assert!(test_dir.join("b/.DS_Store").exists(), "期望还有其他文件的目录中的垃圾文件保留");
+        let leftovers: Vec<_> = fs::read_dir(&test_dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(leftovers, vec![std::ffi::OsString::from("b")], "期望没有留下临时目录");
+
+        // 0 字节的文件和自定义条件
+        fs::create_dir_all(test_dir.join("z")).unwrap();
+        fs::File::create(test_dir.join("z/empty.txt")).unwrap();
+        fs::create_dir_all(test_dir.join("n")).unwrap();
+        fs::write(test_dir.join("n/data.txt"), b"123").unwrap();
+        let options = CleanOptions { empty_predicate: Some(EmptyPredicate::ZeroByte), ..Default::default() };
+        remove_empty_dirs(&test_dir, &options).expect("删除空文件夹失败");
+        assert!(!test_dir.join("z").exists(), "期望只有 0 字节文件的目录被删除");
+        assert!(test_dir.join("n/data.txt").exists(), "期望有内容的文件保留");
+
+        let predicate: FilePredicate = Arc::new(|path, _| path.extension().is_some_and(|ext| ext == "txt"));
+        let options =
+            CleanOptions { empty_predicate: Some(EmptyPredicate::Custom(predicate)), remove_root: true, ..Default::default() };
+        fs::remove_file(test_dir.join("b/.DS_Store")).unwrap();
+        assert!(remove_empty_dirs(&test_dir, &options).expect("删除空文件夹失败"), "期望根目录也被删除");
+        assert!(!test_dir.exists(), "期望根目录连同只剩下的 .txt 文件一起删除");
+    }
+
     // 测试不存在的目录应该返回 false
     #[test]
     fn test_remove_empty_dirs_nonexistent() {
         // 创建一个路径对象，该路径在文件系统中不存在
         let nonexistent = PathBuf::from("/nonexistent/path/that/does/not/exist");
-        let result = remove_empty_dirs(&nonexistent);
+        let result = remove_empty_dirs(&nonexistent, &CleanOptions::default());
         assert!(result.is_ok(), "期望函数执行成功，但实际失败了");
         let removed = result.unwrap();
-        assert_eq!(removed, false, "期望不存在的目录返回false，但实际返回了{}", removed);
+        assert!(!removed, "期望不存在的目录返回false，但实际返回了{}", removed);
     }
     
 
+    // 0 字节的文件：开启 empty_files 时和垃圾文件在同一次遍历中删除，报告中单独记录；已知的哨兵文件保留
+    #[test]
+    fn test_remove_empty_files() {
+        let test_dir = create_test_dir();
+        let sub = test_dir.join("pkg");
+        fs::create_dir(&sub).unwrap();
+        fs::File::create(sub.join("download.part")).unwrap();
+        fs::File::create(sub.join("__init__.py")).unwrap();
+        fs::File::create(sub.join("yarn.lock")).unwrap();
+        fs::write(sub.join("data.txt"), b"123").unwrap();
+        fs::write(test_dir.join("thumbs.db"), b"123").unwrap();
+        fs::File::create(test_dir.join("empty.log")).unwrap();
+
+        // 默认不删除 0 字节的文件
+        let count = remove_junk_files(&test_dir, &JunkRules::default(), &CleanOptions { dry_run: true, ..Default::default() })
+            .expect("预览失败");
+        assert_eq!(count, 1, "期望默认只删除垃圾文件");
+
+        let options = CleanOptions {
+            empty_files: true,
+            empty_file_sentinels: vec!["*.lock".to_string()],
+            ..Default::default()
+        };
+        let report = clean_directory(&test_dir, &JunkRules::default(), &options).expect("清理目录失败");
+        assert_eq!(report.removed_junk_files, vec![test_dir.join("thumbs.db")], "期望垃圾文件单独记录");
+        assert_eq!(
+            report.removed_empty_files,
+            vec![test_dir.join("empty.log"), sub.join("download.part")],
+            "期望 0 字节的文件单独记录"
+        );
+        assert_eq!(report.bytes_freed, 3, "期望释放的字节数只来自垃圾文件");
+        assert!(sub.join("__init__.py").exists() && sub.join("yarn.lock").exists(), "期望哨兵文件保留");
+        assert!(sub.join("data.txt").exists(), "期望有内容的文件保留");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 把条目的修改时间设为 modified_ago 之前，访问时间设为 accessed_ago 之前
+    fn set_age(path: &std::path::Path, modified_ago: std::time::Duration, accessed_ago: std::time::Duration) {
+        let now = std::time::SystemTime::now();
+        let times = fs::FileTimes::new().set_modified(now - modified_ago).set_accessed(now - accessed_ago);
+        journal::open_for_times(path).unwrap().set_times(times).unwrap();
+    }
+
+    // 最小年龄：只删除时间早于截止时间的垃圾文件和空目录，太新的条目记录到 skipped 中
+    // 目录的时间在删除其中的内容之前读取，删除垃圾文件以后变空的旧目录照样删除
+    #[test]
+    fn test_min_age() {
+        use std::time::Duration;
+        const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+        let test_dir = create_test_dir();
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::File::create(test_dir.join("a/thumbs.db")).unwrap();
+        fs::create_dir_all(test_dir.join("old")).unwrap();
+        fs::create_dir_all(test_dir.join("new")).unwrap();
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::File::create(test_dir.join("thumbs.db")).unwrap();
+        // 先设置文件，再设置目录（修改文件的时间不会改变目录的修改时间）
+        set_age(&test_dir.join("a/thumbs.db"), 3 * DAY, 3 * DAY);
+        set_age(&test_dir.join("a"), 3 * DAY, 3 * DAY);
+        set_age(&test_dir.join("old"), 3 * DAY, 3 * DAY);
+        // b 很久没有修改，但是刚刚被访问过
+        set_age(&test_dir.join("b"), 3 * DAY, Duration::ZERO);
+
+        let skipped = |report: &CleanReport| -> Vec<(PathBuf, SkipReason)> {
+            report.skipped.iter().map(|entry| (entry.path.clone(), entry.reason)).collect()
+        };
+
+        // 按访问时间：b 太新
+        let options =
+            CleanOptions { dry_run: true, min_age: Some(DAY), age_time: AgeTime::Accessed, ..Default::default() };
+        let report = clean_directory(&test_dir, &JunkRules::default(), &options).expect("预览失败");
+        assert!(!report.removed_dirs.contains(&test_dir.join("b")), "期望按访问时间保留 b");
+        assert!(skipped(&report).contains(&(test_dir.join("b"), SkipReason::TooNew)), "期望报告 b 太新");
+
+        // 按修改时间（默认）
+        let options = CleanOptions { min_age: Some(DAY), ..Default::default() };
+        let report = clean_directory(&test_dir, &JunkRules::default(), &options).expect("清理目录失败");
+        assert_eq!(report.removed_junk_files, vec![test_dir.join("a/thumbs.db")], "期望只删除旧的垃圾文件");
+        assert_eq!(
+            report.removed_dirs,
+            vec![test_dir.join("a"), test_dir.join("b"), test_dir.join("old")],
+            "期望只删除旧的空目录"
+        );
+        assert_eq!(
+            skipped(&report),
+            vec![(test_dir.join("new"), SkipReason::TooNew), (test_dir.join("thumbs.db"), SkipReason::TooNew)],
+            "期望报告太新的条目"
+        );
+        assert!(test_dir.join("new").exists() && test_dir.join("thumbs.db").exists(), "期望太新的条目保留");
+
+        // remove_junk_files 同样受最小年龄限制
+        assert_eq!(remove_junk_files(&test_dir, &JunkRules::default(), &options).unwrap(), 0, "期望不删除新的垃圾文件");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        use std::time::Duration;
+        assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(2 * 60 * 60)));
+        assert_eq!(parse_duration("7d"), Some(Duration::from_secs(7 * 24 * 60 * 60)));
+        assert_eq!(parse_duration("1w"), Some(Duration::from_secs(7 * 24 * 60 * 60)));
+        assert_eq!(parse_duration("7"), None, "期望缺少单位时返回 None");
+        assert_eq!(parse_duration("d"), None, "期望缺少数字时返回 None");
+        assert_eq!(parse_duration("-1h"), None, "期望负数返回 None");
+    }
+
+    // ========================================
+    // 测试预览模式（dry-run）
+    // ========================================
+
+    // 预览模式下垃圾文件只计数，不删除
+    #[test]
+    fn test_remove_junk_files_dry_run() {
+        let test_dir = create_test_dir();
+        let junk_file = test_dir.join("thumbs.db");
+        fs::File::create(&junk_file).unwrap();
+
+        let options = CleanOptions { dry_run: true, ..Default::default() };
+        let deleted_count = remove_junk_files(&test_dir, &JunkRules::default(), &options).expect("预览垃圾文件失败");
+        assert_eq!(deleted_count, 1, "期望预览报告1个垃圾文件，但实际报告了{}个", deleted_count);
+        assert!(junk_file.exists(), "期望预览模式保留垃圾文件，但文件被删除了");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 预览模式下嵌套的空目录会被级联报告，但不会被删除
+    #[test]
+    fn test_remove_empty_dirs_dry_run_cascade() {
+        let test_dir = create_test_dir();
+        let subdir3 = test_dir.join("subdir1").join("subdir2").join("subdir3");
+        fs::create_dir_all(&subdir3).unwrap();
+
+        let options = CleanOptions { dry_run: true, remove_root: true, ..Default::default() };
+        let removed = remove_empty_dirs(&test_dir, &options).expect("预览空文件夹失败");
+        // 主目录只包含空目录链，所以它也"将被删除"
+        assert!(removed, "期望主目录在预览中被报告为将删除，但实际没有");
+        assert!(subdir3.exists(), "期望预览模式保留目录，但目录被删除了");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 预览模式下只包含垃圾文件的目录也会被级联报告为将删除
+    #[test]
+    fn test_clean_directory_dry_run() {
+        let test_dir = create_test_dir();
+        let normal_file = test_dir.join("normal.txt");
+        fs::File::create(&normal_file).unwrap();
+        let subdir = test_dir.join("subdir");
+        fs::create_dir(&subdir).unwrap();
+        let junk_file = subdir.join(".DS_Store");
+        fs::File::create(&junk_file).unwrap();
+
+        let options = CleanOptions { dry_run: true, ..Default::default() };
+        let report = clean_directory(&test_dir, &JunkRules::default(), &options).expect("预览失败");
+        // 报告中列出将被删除的内容：垃圾文件，以及因此变空的子目录
+        assert_eq!(report.removed_junk_files, vec![junk_file.clone()], "期望报告列出将删除的垃圾文件");
+        assert_eq!(report.removed_dirs, vec![subdir.clone()], "期望报告列出级联变空的子目录");
+        // 磁盘上的内容完全没有变化
+        assert!(junk_file.exists(), "期望预览模式保留垃圾文件，但文件被删除了");
+        assert!(subdir.exists(), "期望预览模式保留子目录，但目录被删除了");
+        assert!(normal_file.exists(), "期望普通文件保留，但文件不存在");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // ========================================
+    // 测试符号链接处理
+    // ========================================
+    // 符号链接测试只在类 Unix 系统上运行（Windows 创建符号链接需要管理员权限）
+
+    // 默认策略：指向目标目录以外的链接不会被进入
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_outside_root_skipped() {
+        use std::os::unix::fs::symlink;
+        let test_dir = create_test_dir();
+        let root = test_dir.join("root");
+        let outside = test_dir.join("outside");
+        fs::create_dir(&root).unwrap();
+        fs::create_dir(&outside).unwrap();
+        let outside_junk = outside.join("thumbs.db");
+        fs::File::create(&outside_junk).unwrap();
+        let outside_empty = outside.join("empty");
+        fs::create_dir(&outside_empty).unwrap();
+        let link = root.join("link");
+        symlink(&outside, &link).unwrap();
+
+        let result = clean_directory(&root, &JunkRules::default(), &CleanOptions::default());
+        assert!(result.is_ok(), "期望清理成功，但实际失败了");
+        assert!(outside_junk.exists(), "期望链接外的垃圾文件保留，但文件被删除了");
+        assert!(outside_empty.exists(), "期望链接外的空目录保留，但目录被删除了");
+        // 链接本身保留，主目录也因此不为空
+        assert!(link.symlink_metadata().is_ok(), "期望符号链接保留，但链接被删除了");
+        assert!(root.exists(), "期望主目录保留，但目录不存在");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 名字像垃圾文件的符号链接不会被删除
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_named_like_junk_skipped() {
+        use std::os::unix::fs::symlink;
+        let test_dir = create_test_dir();
+        let target = test_dir.join("real.txt");
+        fs::File::create(&target).unwrap();
+        let link = test_dir.join("thumbs.db");
+        symlink(&target, &link).unwrap();
+
+        let deleted_count = remove_junk_files(&test_dir, &JunkRules::default(), &CleanOptions::default())
+            .expect("删除文件失败");
+        assert_eq!(deleted_count, 0, "期望不删除符号链接，但实际删除了{}个文件", deleted_count);
+        assert!(link.symlink_metadata().is_ok(), "期望符号链接保留，但链接被删除了");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 跟随策略：进入链接指向的目录，但链接本身保留
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_follow_outside_root() {
+        use std::os::unix::fs::symlink;
+        let test_dir = create_test_dir();
+        let root = test_dir.join("root");
+        let outside = test_dir.join("outside");
+        fs::create_dir(&root).unwrap();
+        fs::create_dir(&outside).unwrap();
+        let outside_junk = outside.join("thumbs.db");
+        fs::File::create(&outside_junk).unwrap();
+        let outside_empty = outside.join("empty");
+        fs::create_dir(&outside_empty).unwrap();
+        let link = root.join("link");
+        symlink(&outside, &link).unwrap();
+
+        let options = CleanOptions { symlinks: SymlinkPolicy::Follow, ..Default::default() };
+        let result = clean_directory(&root, &JunkRules::default(), &options);
+        assert!(result.is_ok(), "期望清理成功，但实际失败了");
+        assert!(!outside_junk.exists(), "期望链接指向目录中的垃圾文件被删除，但文件仍然存在");
+        assert!(!outside_empty.exists(), "期望链接指向目录中的空目录被删除，但目录仍然存在");
+        assert!(outside.exists(), "期望链接指向的目录本身保留，但目录不存在");
+        assert!(link.symlink_metadata().is_ok(), "期望符号链接保留，但链接被删除了");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 跟随策略：符号链接形成循环时不会无限递归
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_follow_cycle() {
+        use std::os::unix::fs::symlink;
+        let test_dir = create_test_dir();
+        let subdir = test_dir.join("a").join("b");
+        fs::create_dir_all(&subdir).unwrap();
+        // a/b/loop -> a，形成 a -> b -> loop -> a 的循环
+        symlink(test_dir.join("a"), subdir.join("loop")).unwrap();
+        // 指向自己所在目录的链接
+        symlink(&test_dir, test_dir.join("self")).unwrap();
+        let junk_file = subdir.join(".DS_Store");
+        fs::File::create(&junk_file).unwrap();
+
+        for policy in [SymlinkPolicy::Skip, SymlinkPolicy::Report, SymlinkPolicy::Follow] {
+            let options = CleanOptions { symlinks: policy, ..Default::default() };
+            let report = clean_directory(&test_dir, &JunkRules::default(), &options)
+                .unwrap_or_else(|e| panic!("期望 {:?} 策略下清理成功，但实际失败了: {}", policy, e));
+            // Report 策略记录链接，Follow 策略记录发现的循环
+            let expected = match policy {
+                SymlinkPolicy::Skip => None,
+                SymlinkPolicy::Report => Some(SkipReason::Symlink),
+                SymlinkPolicy::Follow => Some(SkipReason::SymlinkLoop),
+            };
+            if let Some(reason) = expected {
+                assert!(
+                    report.skipped.iter().any(|entry| entry.reason == reason),
+                    "期望 {:?} 策略在报告中记录 {:?}，实际是{:?}",
+                    policy,
+                    reason,
+                    report.skipped
+                );
+            } else {
+                assert!(report.skipped.is_empty(), "期望 Skip 策略不记录链接，实际是{:?}", report.skipped);
+            }
+        }
+        assert!(!junk_file.exists(), "期望垃圾文件被删除，但文件仍然存在");
+        // 包含链接的目录都不为空
+        assert!(subdir.exists(), "期望包含符号链接的目录保留，但目录不存在");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // ========================================
+    // 测试遇到错误后继续遍历
+    // ========================================
+
+    // 创建一个无法读取的子目录
+    // 以 root 身份运行时权限检查不起作用，返回 None，调用的测试直接跳过
+    #[cfg(unix)]
+    fn create_unreadable_dir(parent: &std::path::Path) -> Option<PathBuf> {
+        use std::os::unix::fs::PermissionsExt;
+        let locked = parent.join("locked");
+        fs::create_dir(&locked).unwrap();
+        fs::File::create(locked.join("thumbs.db")).unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        if fs::read_dir(&locked).is_ok() {
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+            return None;
+        }
+        Some(locked)
+    }
+
+    // 恢复权限，保证测试目录可以被删除
+    #[cfg(unix)]
+    fn unlock_dir(locked: &std::path::Path) {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(locked, fs::Permissions::from_mode(0o755)).ok();
+    }
+
+    // continue_on_error：无法读取的目录被记录下来，其余内容照常清理
+    #[cfg(unix)]
+    #[test]
+    fn test_continue_on_error_collects_errors() {
+        let test_dir = create_test_dir();
+        let Some(locked) = create_unreadable_dir(&test_dir) else {
+            fs::remove_dir_all(&test_dir).ok();
+            return;
+        };
+        let junk_file = test_dir.join("z").join(".DS_Store");
+        fs::create_dir(test_dir.join("z")).unwrap();
+        fs::File::create(&junk_file).unwrap();
+
+        let options = CleanOptions { continue_on_error: true, ..Default::default() };
+        let report = clean_directory(&test_dir, &JunkRules::default(), &options).expect("期望清理继续完成");
+        unlock_dir(&locked);
+
+        assert!(!junk_file.exists(), "期望其余目录中的垃圾文件被删除，但文件仍然存在");
+        assert!(
+            report.errors.iter().any(|e| matches!(
+                e,
+                EptdirError::Io { path, operation: Operation::ReadDir, source }
+                    if *path == locked && source.kind() == std::io::ErrorKind::PermissionDenied
+            )),
+            "期望报告记录无法读取的目录，实际是{:?}",
+            report.errors
+        );
+        // 无法读取的目录不能确认为空，必须保留
+        assert!(locked.exists(), "期望无法读取的目录保留，但目录不存在");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 默认模式：遇到第一个错误就返回 Err
+    #[cfg(unix)]
+    #[test]
+    fn test_fail_fast_returns_error() {
+        let test_dir = create_test_dir();
+        let Some(locked) = create_unreadable_dir(&test_dir) else {
+            fs::remove_dir_all(&test_dir).ok();
+            return;
+        };
+
+        let result = clean_directory(&test_dir, &JunkRules::default(), &CleanOptions::default());
+        unlock_dir(&locked);
+        let error = result.expect_err("期望默认模式遇到错误时返回 Err，但实际成功了");
+        assert_eq!(error.path(), Some(locked.as_path()), "期望错误中记录出错的路径");
+        assert_eq!(error.kind(), Some(std::io::ErrorKind::PermissionDenied), "期望错误种类为权限不足");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // ========================================
+    // 测试错误类型
+    // ========================================
+
+    // EptdirError 可以在线程之间传递
+    #[test]
+    fn test_error_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<EptdirError>();
+    }
+
+    // 删除目录时"目录不为空"被归类为 DirectoryNotEmpty，其他错误保留操作和路径
+    #[test]
+    fn test_error_classification() {
+        let path = PathBuf::from("some/dir");
+        let race = EptdirError::io(&path, Operation::RemoveDir, std::io::ErrorKind::DirectoryNotEmpty.into());
+        assert!(
+            matches!(race, EptdirError::DirectoryNotEmpty { .. }),
+            "期望归类为 DirectoryNotEmpty，实际是{:?}",
+            race
+        );
+
+        let denied = EptdirError::io(&path, Operation::RemoveFile, std::io::ErrorKind::PermissionDenied.into());
+        assert!(
+            matches!(denied, EptdirError::Io { operation: Operation::RemoveFile, .. }),
+            "期望归类为 Io，实际是{:?}",
+            denied
+        );
+        assert_eq!(denied.path(), Some(path.as_path()), "期望错误中记录路径");
+        // source() 返回底层的 io::Error
+        assert!(std::error::Error::source(&denied).is_some(), "期望错误带有底层的 io::Error");
+    }
+
+    // 无效的规则返回 InvalidRule 错误
+    #[test]
+    fn test_invalid_rule_error() {
+        let result = JunkRule::parse("regex:(");
+        assert!(
+            matches!(result, Err(EptdirError::InvalidRule { .. })),
+            "期望返回 InvalidRule 错误，实际是{:?}",
+            result
+        );
+    }
+
     // ========================================
     // 测试 clean_directory 函数
     // ========================================
@@ -268,7 +933,7 @@ mod tests {
         fs::create_dir(&empty_subdir).unwrap();
         
         // 执行清理
-        let result = clean_directory(&test_dir);
+        let result = clean_directory(&test_dir, &JunkRules::default(), &CleanOptions::default());
         assert!(result.is_ok(), "期望清理目录成功，但实际失败了");
         
         // 验证结果
@@ -281,27 +946,962 @@ mod tests {
         fs::remove_dir_all(&test_dir).ok();
     }
 
-    // 测试不存在的目录应该返回Ok同时跳过
+    // clean_directory 明确报告根目录的去留
+    #[test]
+    fn test_clean_directory_root_status() {
+        let test_dir = create_test_dir();
+        let junk_file = test_dir.join("thumbs.db");
+        fs::File::create(&junk_file).unwrap();
+
+        // 默认保留：根目录只剩垃圾文件，清理后为空但保留
+        let status = clean_directory(&test_dir, &JunkRules::default(), &CleanOptions::default())
+            .expect("清理目录失败")
+            .root_status;
+        assert_eq!(status, RootStatus::KeptEmpty, "期望根目录为空但保留，实际是{:?}", status);
+        assert!(test_dir.exists(), "期望根目录保留，但目录不存在");
+
+        // 打开 remove_root 后删除
+        let options = CleanOptions { remove_root: true, ..Default::default() };
+        let status = clean_directory(&test_dir, &JunkRules::default(), &options)
+            .expect("清理目录失败")
+            .root_status;
+        assert_eq!(status, RootStatus::Removed, "期望根目录被删除，实际是{:?}", status);
+        assert!(!test_dir.exists(), "期望根目录被删除，但目录仍然存在");
+    }
+
+    // 清理报告记录删除的内容、释放的字节数
+    #[test]
+    fn test_clean_directory_report() {
+        let test_dir = create_test_dir();
+        fs::File::create(test_dir.join("normal.txt")).unwrap();
+        let junk_file = test_dir.join("thumbs.db");
+        fs::write(&junk_file, b"12345").unwrap();
+        let nested = test_dir.join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+
+        let report = clean_directory(&test_dir, &JunkRules::default(), &CleanOptions::default())
+            .expect("清理目录失败");
+        assert_eq!(report.target, test_dir, "期望报告记录目标目录");
+        assert!(!report.dry_run, "期望报告标记为非预览模式");
+        assert_eq!(report.removed_junk_files, vec![junk_file], "期望报告列出删除的垃圾文件");
+        assert_eq!(report.bytes_freed, 5, "期望释放5字节，但实际是{}", report.bytes_freed);
+        // 删除顺序：子目录在前，父目录在后
+        assert_eq!(report.removed_dirs, vec![nested, test_dir.join("a")], "期望按删除顺序列出空目录");
+        assert!(report.errors.is_empty(), "期望没有错误，但实际有{:?}", report.errors);
+        assert_eq!(report.root_status, RootStatus::NotEmpty, "期望根目录不为空");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // max_depth 限制进入的层数：超过深度的垃圾文件保留，超过深度的目录不被进入
+    #[test]
+    fn test_clean_directory_max_depth() {
+        let test_dir = create_test_dir();
+        fs::File::create(test_dir.join("thumbs.db")).unwrap();
+        let deep_junk = test_dir.join("a").join("thumbs.db");
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::File::create(&deep_junk).unwrap();
+        // 本来就为空的目录即使在深度限制上也会被删除
+        fs::create_dir_all(test_dir.join("empty")).unwrap();
+
+        let options = CleanOptions { max_depth: Some(1), ..Default::default() };
+        let report = clean_directory(&test_dir, &JunkRules::default(), &options).expect("清理目录失败");
+        assert_eq!(report.removed_junk_files, vec![test_dir.join("thumbs.db")], "期望只删除第1层的垃圾文件");
+        assert!(deep_junk.exists(), "期望第2层的垃圾文件保留");
+        assert!(!test_dir.join("empty").exists(), "期望第1层的空目录被删除");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // exclude 排除的文件不会被删除，排除的目录不会被进入
+    #[test]
+    fn test_clean_directory_exclude() {
+        let test_dir = create_test_dir();
+        let excluded_dir = test_dir.join("node_modules");
+        fs::create_dir_all(excluded_dir.join("empty")).unwrap();
+        fs::File::create(excluded_dir.join("thumbs.db")).unwrap();
+        fs::create_dir_all(test_dir.join("other")).unwrap();
+
+        let options = CleanOptions { exclude: vec!["node_modules".to_string()], ..Default::default() };
+        let report = clean_directory(&test_dir, &JunkRules::default(), &options).expect("清理目录失败");
+        assert!(report.removed_junk_files.is_empty(), "期望排除目录中的垃圾文件保留");
+        assert!(excluded_dir.join("empty").exists(), "期望排除目录中的空目录保留");
+        assert!(!test_dir.join("other").exists(), "期望其他空目录被删除");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // gitignore 风格的排除规则：名字、只匹配目录、相对于根目录、**、! 重新包含
+    #[test]
+    fn test_path_filter_patterns() {
+        use std::path::Path;
+        let filter = PathFilter::new(
+            &["node_modules", "tmp/", "/build", "docs/*.md", "**/cache", "logs/**", "*.log", "!keep.log"],
+            &["/logs/important"],
+        );
+        let excluded = |path: &str, is_dir: bool| filter.is_excluded(Path::new(path), is_dir);
+
+        assert!(excluded("node_modules", true), "期望名字规则匹配根目录中的条目");
+        assert!(excluded("a/b/node_modules", true), "期望名字规则匹配任意深度");
+        assert!(excluded("a/tmp", true), "期望 tmp/ 匹配目录");
+        assert!(!excluded("a/tmp", false), "期望 tmp/ 不匹配同名的文件");
+        assert!(excluded("build", true), "期望 /build 匹配根目录中的 build");
+        assert!(!excluded("src/build", true), "期望 /build 不匹配更深的 build");
+        assert!(excluded("docs/a.md", false), "期望 docs/*.md 匹配");
+        assert!(!excluded("docs/sub/a.md", false), "期望 * 不匹配 /");
+        assert!(excluded("cache", true) && excluded("a/b/cache", true), "期望 **/cache 匹配任意深度");
+        assert!(!excluded("logs", true), "期望 logs/** 不匹配 logs 本身");
+        assert!(excluded("logs/a/b", true), "期望 logs/** 匹配里面的所有内容");
+        assert!(excluded("a/x.log", false), "期望 *.log 匹配");
+        assert!(!excluded("a/keep.log", false), "期望 !keep.log 重新包含");
+        assert!(!excluded("logs/important", true), "期望 include 的优先级最高");
+        assert!(!excluded("src/main.rs", false), "期望不匹配的条目不被排除");
+        assert!(PathFilter::new::<&str>(&[], &[]).is_empty(), "期望没有规则时为空");
+    }
+
+    // 排除规则在清理时生效：排除的目录不进入、不删除，并记录到报告中
+    #[test]
+    fn test_clean_directory_exclude_patterns() {
+        let test_dir = create_test_dir();
+        fs::create_dir_all(test_dir.join(".git/objects")).unwrap();
+        fs::File::create(test_dir.join(".git/thumbs.db")).unwrap();
+        fs::create_dir_all(test_dir.join("logs/old")).unwrap();
+        fs::create_dir_all(test_dir.join("logs/keep")).unwrap();
+        fs::create_dir_all(test_dir.join("src/tmp")).unwrap();
+        fs::File::create(test_dir.join("src/thumbs.db")).unwrap();
+        fs::File::create(test_dir.join("thumbs.db")).unwrap();
+
+        let options = CleanOptions {
+            exclude: vec![".git/".to_string(), "/logs/*".to_string(), "tmp/".to_string(), "/thumbs.db".to_string()],
+            include: vec!["/logs/old".to_string()],
+            ..Default::default()
+        };
+        let report = remove_junk_files(&test_dir, &JunkRules::default(), &options).expect("删除垃圾文件失败");
+        assert_eq!(report, 1, "期望只删除 src/thumbs.db");
+        assert!(test_dir.join(".git/thumbs.db").exists(), "期望排除目录中的垃圾文件保留");
+        assert!(test_dir.join("thumbs.db").exists(), "期望排除的垃圾文件保留");
+
+        let report = clean_directory(&test_dir, &JunkRules::default(), &options).expect("清理目录失败");
+        assert_eq!(report.removed_dirs, vec![test_dir.join("logs/old")], "期望只删除重新包含的空目录");
+        assert!(test_dir.join("src/tmp").exists() && test_dir.join("logs/keep").exists(), "期望排除的空目录保留");
+        let skipped: Vec<(PathBuf, SkipReason)> =
+            report.skipped.iter().map(|entry| (entry.path.clone(), entry.reason)).collect();
+        assert_eq!(
+            skipped,
+            vec![
+                (test_dir.join(".git"), SkipReason::Excluded),
+                (test_dir.join("logs/keep"), SkipReason::Excluded),
+                (test_dir.join("src/tmp"), SkipReason::Excluded),
+                (test_dir.join("thumbs.db"), SkipReason::Excluded),
+            ],
+            "期望报告排除的目录和垃圾文件"
+        );
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 保留标记：有标记的目录去掉垃圾文件后留下，标记文件即使匹配垃圾文件规则也不删除
+    // .eptdirignore 中列出的目录（相对于它所在的目录）即使变空也留下，里面照常清理
+    #[test]
+    fn test_clean_directory_keep_markers() {
+        let test_dir = create_test_dir();
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::File::create(test_dir.join("a/.gitkeep")).unwrap();
+        fs::File::create(test_dir.join("a/thumbs.db")).unwrap();
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::File::create(test_dir.join("b/.placeholder")).unwrap();
+        fs::create_dir_all(test_dir.join("c")).unwrap();
+        fs::File::create(test_dir.join("c/.keep")).unwrap();
+        fs::create_dir_all(test_dir.join("proj/cache/x")).unwrap();
+        fs::File::create(test_dir.join("proj/cache/x/thumbs.db")).unwrap();
+        fs::create_dir_all(test_dir.join("proj/other")).unwrap();
+        fs::create_dir_all(test_dir.join("proj/out")).unwrap();
+        fs::write(test_dir.join("proj/.eptdirignore"), "# 保留的目录\ncache/\n/out\n").unwrap();
+
+        // 垃圾文件规则匹配所有以 . 开头的文件，标记文件仍然保留
+        let mut rules = JunkRules::default();
+        rules.add_spec(".*").unwrap();
+        let options = CleanOptions { keep_markers: vec![".placeholder".to_string()], ..Default::default() };
+        let report = clean_directory(&test_dir, &rules, &options).expect("清理目录失败");
+        assert_eq!(
+            report.removed_junk_files,
+            vec![test_dir.join("a/thumbs.db"), test_dir.join("proj/cache/x/thumbs.db")],
+            "期望只删除垃圾文件，不删除标记文件"
+        );
+        assert_eq!(
+            report.removed_dirs,
+            vec![test_dir.join("proj/cache/x"), test_dir.join("proj/other")],
+            "期望只删除没有保留标记的空目录"
+        );
+        for kept in ["a/.gitkeep", "b/.placeholder", "c/.keep", "proj/.eptdirignore", "proj/cache", "proj/out"] {
+            assert!(test_dir.join(kept).exists(), "期望 {} 保留", kept);
+        }
+        let skipped: Vec<(PathBuf, SkipReason)> =
+            report.skipped.iter().map(|entry| (entry.path.clone(), entry.reason)).collect();
+        assert_eq!(
+            skipped,
+            ["a", "b", "c", "proj/cache", "proj/out"]
+                .iter()
+                .map(|path| (test_dir.join(path), SkipReason::Kept))
+                .collect::<Vec<_>>(),
+            "期望报告因为保留标记留下的目录"
+        );
+
+        // 不使用保留标记时按普通规则处理：.gitkeep 匹配 .* 被删除，目录随之删除
+        let options = CleanOptions { no_keep_markers: true, ..Default::default() };
+        let report = clean_directory(&test_dir, &rules, &options).expect("清理目录失败");
+        assert!(report.removed_junk_files.contains(&test_dir.join("a/.gitkeep")), "期望标记文件当作垃圾文件删除");
+        assert!(!test_dir.join("a").exists() && !test_dir.join("proj").exists(), "期望目录被删除");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 隔离目录：保留相对路径，restore 之后恢复原样
+    #[test]
+    fn test_quarantine_folder_and_restore() {
+        let test_dir = create_test_dir();
+        let target = test_dir.join("target");
+        let junk_file = target.join("a").join("thumbs.db");
+        fs::create_dir_all(target.join("a")).unwrap();
+        fs::write(&junk_file, b"123").unwrap();
+        fs::File::create(target.join("a").join("keep.txt")).unwrap();
+        let empty_dir = target.join("b").join("c");
+        fs::create_dir_all(&empty_dir).unwrap();
+
+        let quarantine_dir = test_dir.join("quarantine");
+        let options =
+            CleanOptions { quarantine: Some(Quarantine::Folder(quarantine_dir.clone())), ..Default::default() };
+        let report = clean_directory(&target, &JunkRules::default(), &options).expect("清理目录失败");
+        assert!(!junk_file.exists(), "期望垃圾文件被移走");
+        assert!(!target.join("b").exists(), "期望空目录被移走");
+
+        // 运行目录位于隔离目录中，内容保留相对目标目录的路径
+        let run_dir = report.quarantine.expect("期望报告记录隔离位置");
+        assert_eq!(run_dir.parent(), Some(quarantine_dir.as_path()), "期望运行目录位于隔离目录中");
+        assert_eq!(fs::read(run_dir.join("a").join("thumbs.db")).unwrap(), b"123", "期望垃圾文件保留相对路径");
+        assert!(run_dir.join("b").join("c").is_dir(), "期望空目录保留相对路径");
+
+        let restored = restore(&run_dir, None).expect("恢复失败");
+        assert!(restored.errors.is_empty(), "期望恢复没有错误，实际有{:?}", restored.errors);
+        assert_eq!(fs::read(&junk_file).unwrap(), b"123", "期望垃圾文件被放回原位置");
+        assert!(empty_dir.is_dir(), "期望空目录被恢复");
+        assert!(!run_dir.exists(), "期望全部恢复后运行目录被删除");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 回收站布局：files + info，同名文件自动改名
+    #[test]
+    fn test_quarantine_trash_layout() {
+        let test_dir = create_test_dir();
+        let target = test_dir.join("target");
+        fs::create_dir_all(target.join("a")).unwrap();
+        fs::create_dir_all(target.join("b")).unwrap();
+        fs::File::create(target.join("a").join("thumbs.db")).unwrap();
+        fs::File::create(target.join("b").join("thumbs.db")).unwrap();
+        fs::File::create(target.join("keep.txt")).unwrap();
+
+        let trash_dir = test_dir.join("Trash");
+        let options = CleanOptions { quarantine: Some(Quarantine::Trash(trash_dir.clone())), ..Default::default() };
+        let report = clean_directory(&target, &JunkRules::default(), &options).expect("清理目录失败");
+        assert_eq!(report.quarantine, Some(trash_dir.clone()), "期望报告记录回收站位置");
+
+        // 两个 thumbs.db 和两个空目录，一共 4 个条目
+        let count = fs::read_dir(trash_dir.join("files")).unwrap().count();
+        assert_eq!(count, 4, "期望回收站中有4个条目，实际是{}", count);
+        assert!(trash_dir.join("files").join("thumbs.db.2").exists(), "期望同名文件自动改名");
+        let info = fs::read_to_string(trash_dir.join("info").join("thumbs.db.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath=/"), "期望 .trashinfo 记录绝对路径，实际是{}", info);
+        assert!(info.contains("DeletionDate="), "期望 .trashinfo 记录删除时间");
+        assert!(info.contains(trash::TRASH_MARKER), "期望 .trashinfo 标记是 eptdir 放进去的");
+
+        // 其他程序放进回收站的文件，原来也在目标目录中
+        fs::write(trash_dir.join("files").join("report.txt"), b"data").unwrap();
+        let foreign = format!("[Trash Info]\nPath={}\nDeletionDate=2026-10-01T00:00:00\n", target.join("report.txt").display());
+        fs::write(trash_dir.join("info").join("report.txt.trashinfo"), foreign).unwrap();
+
+        // 不指定原目录：恢复整个回收站
+        let restored = restore(&trash_dir, None).expect("恢复失败");
+        assert!(restored.errors.is_empty(), "期望恢复没有错误，实际有{:?}", restored.errors);
+        assert_eq!(restored.restored.len(), 4, "期望只恢复 eptdir 移走的 4 个条目");
+        assert!(target.join("a").join("thumbs.db").exists(), "期望 a/thumbs.db 被放回");
+        assert!(target.join("b").join("thumbs.db").exists(), "期望 b/thumbs.db 被放回");
+        assert!(!target.join("report.txt").exists(), "期望其他程序删除的文件留在回收站中");
+        assert!(trash_dir.join("files").join("report.txt").exists());
+        let infos: Vec<_> = fs::read_dir(trash_dir.join("info")).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(infos, ["report.txt.trashinfo"], "期望恢复后只删除 eptdir 的 .trashinfo");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 回收站和目标目录不在同一个文件系统上：移动到目标目录所在分区的 .Trash-$uid，而不是复制到指定的回收站
+    // 需要 /dev/shm 是单独挂载的文件系统（常见的 Linux 环境），否则跳过
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_quarantine_volume_trash() {
+        let test_dir = create_test_dir();
+        let shm = Path::new("/dev/shm");
+        let device = sys::device(shm);
+        if device.is_none() || device == sys::device(&test_dir) || device == sys::device(Path::new("/dev")) {
+            fs::remove_dir_all(&test_dir).ok();
+            return;
+        }
+        let volume = shm.join(format!(".Trash-{}", sys::uid().unwrap()));
+        let target = shm.join(test_dir.file_name().unwrap());
+        fs::create_dir_all(target.join("a")).unwrap();
+        fs::write(target.join("a/thumbs.db"), b"123").unwrap();
+        fs::File::create(target.join("keep.txt")).unwrap();
+
+        let home = test_dir.join("Trash");
+        let options = CleanOptions { quarantine: Some(Quarantine::Trash(home.clone())), ..Default::default() };
+        let report = clean_directory(&target, &JunkRules::default(), &options).expect("清理目录失败");
+        assert_eq!(report.quarantine, Some(volume.clone()), "期望使用目标目录所在分区的回收站");
+        assert!(!home.exists(), "期望不使用另一个文件系统上的回收站");
+        assert!(!target.join("a").exists(), "期望移走 a 和其中的 thumbs.db");
+
+        let restored = restore(&volume, Some(&target)).expect("恢复失败");
+        assert!(restored.errors.is_empty(), "期望恢复没有错误，实际有{:?}", restored.errors);
+        assert!(target.join("a/thumbs.db").exists(), "期望从分区的回收站放回原位置");
+
+        fs::remove_dir_all(&target).ok();
+        // 回收站只在恢复以后为空时删除，不影响其他程序放进去的内容
+        for dir in [volume.join("files"), volume.join("info"), volume] {
+            fs::remove_dir(dir).ok();
+        }
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 恢复时不覆盖原位置已经存在的文件
+    #[test]
+    fn test_restore_does_not_overwrite() {
+        let test_dir = create_test_dir();
+        let target = test_dir.join("target");
+        fs::create_dir_all(&target).unwrap();
+        let junk_file = target.join("thumbs.db");
+        fs::write(&junk_file, b"old").unwrap();
+        fs::File::create(target.join("keep.txt")).unwrap();
+
+        let options =
+            CleanOptions { quarantine: Some(Quarantine::Folder(test_dir.join("q"))), ..Default::default() };
+        clean_directory(&target, &JunkRules::default(), &options).expect("清理目录失败");
+        fs::write(&junk_file, b"new").unwrap();
+
+        // 传入隔离目录本身，恢复其中所有的运行
+        let restored = restore(&test_dir.join("q"), None).expect("恢复失败");
+        assert_eq!(restored.errors.len(), 1, "期望同名文件恢复失败，实际是{:?}", restored.errors);
+        assert_eq!(fs::read(&junk_file).unwrap(), b"new", "期望原位置的文件没有被覆盖");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 撤销日志记录删除的条目，undo 重新创建空目录并恢复修改时间和权限
+    #[test]
+    fn test_journal_and_undo_dirs() {
+        let test_dir = create_test_dir();
+        let target = test_dir.join("target");
+        let nested = target.join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::File::create(target.join("keep.txt")).unwrap();
+        fs::File::create(target.join("thumbs.db")).unwrap();
+
+        // 给两个目录设置不同的修改时间，撤销后应该原样恢复
+        use std::time::{Duration, UNIX_EPOCH};
+        let a_time = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let b_time = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        fs::File::open(&nested).unwrap().set_modified(b_time).unwrap();
+        fs::File::open(target.join("a")).unwrap().set_modified(a_time).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(target.join("a"), fs::Permissions::from_mode(0o750)).unwrap();
+        }
+
+        let journal = test_dir.join("journal.jsonl");
+        let options = CleanOptions { journal: Some(journal.clone()), ..Default::default() };
+        let report = clean_directory(&target, &JunkRules::default(), &options).expect("清理目录失败");
+        let run_id = report.run_id.expect("期望报告记录运行 ID");
+        assert!(!target.join("a").exists(), "期望空目录被删除");
+
+        // 每行一个 JSON：start + thumbs.db + b + a
+        let content = fs::read_to_string(&journal).unwrap();
+        let records: Vec<serde_json::Value> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(records.len(), 4, "期望4条记录，实际是{}", content);
+        assert!(records.iter().all(|record| record["run_id"] == run_id.as_str()), "期望每条记录都有运行 ID");
+        assert_eq!(records[0]["event"], "start", "期望第一条是 start 记录");
+        assert_eq!(records[1]["type"], "file", "期望先记录垃圾文件");
+        assert_eq!(records[2]["path"], nested.display().to_string(), "期望子目录先于父目录记录");
+
+        let undone = undo(&journal, &run_id).expect("撤销失败");
+        assert!(undone.errors.is_empty(), "期望撤销没有错误，实际有{:?}", undone.errors);
+        assert_eq!(undone.unrecoverable, vec![target.join("thumbs.db")], "期望永久删除的文件报告为无法恢复");
+        assert!(nested.is_dir(), "期望空目录被重新创建");
+        assert_eq!(fs::metadata(&nested).unwrap().modified().unwrap(), b_time, "期望恢复子目录的修改时间");
+        assert_eq!(fs::metadata(target.join("a")).unwrap().modified().unwrap(), a_time, "期望恢复父目录的修改时间");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(target.join("a")).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode, 0o750, "期望恢复目录权限，实际是{:o}", mode);
+        }
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 隔离模式 + 撤销日志：undo 把隔离的文件也放回去
+    #[test]
+    fn test_undo_with_quarantine() {
+        let test_dir = create_test_dir();
+        let target = test_dir.join("target");
+        fs::create_dir_all(&target).unwrap();
+        let junk_file = target.join("thumbs.db");
+        fs::write(&junk_file, b"123").unwrap();
+
+        let journal = test_dir.join("journal.jsonl");
+        let options = CleanOptions {
+            journal: Some(journal.clone()),
+            quarantine: Some(Quarantine::Trash(test_dir.join("Trash"))),
+            ..Default::default()
+        };
+        let report = clean_directory(&target, &JunkRules::default(), &options).expect("清理目录失败");
+        assert!(!junk_file.exists(), "期望垃圾文件被移走");
+
+        let undone = undo(&journal, report.run_id.as_deref().unwrap()).expect("撤销失败");
+        assert!(undone.errors.is_empty(), "期望撤销没有错误，实际有{:?}", undone.errors);
+        assert_eq!(fs::read(&junk_file).unwrap(), b"123", "期望隔离的文件被放回");
+        assert_eq!(fs::read_dir(test_dir.join("Trash").join("info")).unwrap().count(), 0, "期望 .trashinfo 被删除");
+
+        // 预览模式不写日志
+        let options = CleanOptions { dry_run: true, journal: Some(journal.clone()), ..Default::default() };
+        let report = clean_directory(&target, &JunkRules::default(), &options).expect("清理目录失败");
+        assert_eq!(report.run_id, None, "期望预览模式没有运行 ID");
+
+        let result = undo(&journal, "no-such-run");
+        assert!(matches!(result, Err(EptdirError::RunNotFound(_))), "期望未知的运行 ID 报错，实际是{:?}", result);
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 测试不是有效 UTF-8 的文件名：能被规则匹配，输出中是无歧义的转义形式，也能撤销
+    // Linux 的文件系统允许任意字节的文件名，macOS 会拒绝，所以只在 Linux 上运行
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_non_utf8_names() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let test_dir = create_test_dir();
+        let target = test_dir.join("target");
+        let junk_file = target.join(OsStr::from_bytes(b"\xff\xfe.tmp"));
+        let kept_file = target.join(OsStr::from_bytes(b"keep\xff.txt"));
+        let empty_dir = target.join(OsStr::from_bytes(b"empty\x80"));
+        fs::create_dir_all(&empty_dir).unwrap();
+        fs::write(&junk_file, b"123").unwrap();
+        fs::write(&kept_file, b"keep").unwrap();
+
+        // 规则在原始字节上匹配
+        let mut rules = JunkRules::empty();
+        rules.add_spec("*.tmp").unwrap();
+        assert!(rules.is_junk_os(OsStr::from_bytes(b"\xff\xfe.tmp")), "期望 * 匹配无效的字节");
+        assert!(rules::glob_match_os("?.txt", OsStr::from_bytes(b"\xff.txt")), "期望 ? 匹配一个无效的字节");
+        assert!(rules::glob_match_os("[!a].txt", OsStr::from_bytes(b"\xff.txt")), "期望 [!a] 匹配无效的字节");
+        assert!(!rules::glob_match_os("[a-z].txt", OsStr::from_bytes(b"\xff.txt")), "期望 [a-z] 不匹配无效的字节");
+        assert!(JunkRule::parse("regex:^(?-u:\\xFF)").unwrap().matches_os(OsStr::from_bytes(b"\xffabc")), "期望正则表达式匹配原始字节");
+        assert!(!JunkRule::parse("name:keep.txt").unwrap().matches_os(OsStr::from_bytes(b"keep\xff.txt")), "期望精确文件名不匹配");
+
+        // 转义形式：无效的字节写成 \xHH，普通路径原样输出
+        assert_eq!(escape_path(OsStr::from_bytes(b"a\xff\xfe.tmp")), "$'a\\xff\\xfe.tmp'");
+        assert_eq!(escape_path("it's\n"), "$'it\\'s\\n'");
+        assert_eq!(escape_path("普通 文件.txt"), "普通 文件.txt");
+
+        let journal = test_dir.join("journal.jsonl");
+        let options = CleanOptions { journal: Some(journal.clone()), ..Default::default() };
+        let report = clean_directory(&target, &rules, &options).expect("清理目录失败");
+        assert_eq!(report.removed_junk_files, vec![junk_file.clone()], "期望非 UTF-8 的垃圾文件被删除");
+        assert_eq!(report.removed_dirs, vec![empty_dir.clone()], "期望非 UTF-8 的空文件夹被删除");
+        assert!(kept_file.exists(), "期望不匹配的文件保留");
+
+        // JSON 中同时有转义形式和原始字节的十六进制，可以无损还原
+        let value = report.to_json();
+        let removed = &value["removed_junk_files"][0];
+        assert_eq!(removed["hex"], escape::path_json(&junk_file)["hex"]);
+        assert!(removed["escaped"].as_str().unwrap().ends_with("/\\xff\\xfe.tmp'"), "实际是{}", removed);
+        assert_eq!(escape::path_from_json(removed), Some(junk_file.clone()), "期望能从 JSON 还原路径");
+
+        let undone = undo(&journal, report.run_id.as_deref().unwrap()).expect("撤销失败");
+        assert!(undone.errors.is_empty(), "期望撤销没有错误，实际有{:?}", undone.errors);
+        assert!(empty_dir.is_dir(), "期望非 UTF-8 的空文件夹被重新创建");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 只遍历一次：每个目录只读取一次，不写日志时除了检查根目录不需要额外的 stat
+    #[test]
+    fn test_single_pass_syscalls() {
+        let test_dir = create_test_dir();
+        fs::create_dir_all(test_dir.join("a").join("b")).unwrap();
+        fs::create_dir_all(test_dir.join("c")).unwrap();
+        fs::create_dir_all(test_dir.join("d")).unwrap();
+        fs::write(test_dir.join("a").join("b").join("thumbs.db"), b"junk").unwrap();
+        fs::write(test_dir.join("c").join("keep.txt"), b"keep").unwrap();
+
+        let options = CleanOptions { dry_run: true, ..Default::default() };
+        let preview = clean_directory(&test_dir, &JunkRules::default(), &options).expect("预览失败");
+        // 5 个目录各读取一次；stat 是检查根目录 + 读取垃圾文件的大小
+        let expected = SyscallCounts { read_dir: 5, stat: 2, remove: 0 };
+        assert_eq!(preview.syscalls, expected, "期望预览模式每个目录只读取一次");
+
+        let report = clean_directory(&test_dir, &JunkRules::default(), &CleanOptions::default()).expect("清理失败");
+        assert_eq!(report.syscalls, SyscallCounts { remove: 4, ..expected }, "期望只多出删除操作");
+        assert_eq!(report.removed_dirs, preview.removed_dirs, "期望实际删除和预览一致");
+        assert!(!test_dir.join("a").exists() && !test_dir.join("d").exists(), "期望空目录被删除");
+        assert!(test_dir.join("c").exists(), "期望有文件的目录保留");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 多线程清理：结果和单线程完全相同，空文件夹仍然自底向上删除，撤销日志也能正确撤销
+    #[test]
+    fn test_parallel_matches_single_thread() {
+        // 创建一棵同时有宽度和深度的目录树：
+        // dN/ 中一半只有垃圾文件（会级联删除），一半还有普通文件；每个 dN 下面还有一条空目录链
+        fn build_tree(root: &std::path::Path) {
+            for i in 0..16 {
+                let dir = root.join(format!("d{}", i));
+                let chain = dir.join("x").join("y").join("z");
+                fs::create_dir_all(&chain).unwrap();
+                fs::write(chain.join("thumbs.db"), b"junk").unwrap();
+                fs::write(dir.join(".DS_Store"), b"junk").unwrap();
+                if i % 2 == 1 {
+                    fs::write(dir.join("keep.txt"), b"keep").unwrap();
+                }
+            }
+        }
+        // 报告中的路径去掉根目录前缀，方便比较两次清理
+        fn relative(report: &CleanReport) -> (Vec<PathBuf>, Vec<PathBuf>, u64) {
+            let strip = |paths: &[PathBuf]| -> Vec<PathBuf> {
+                paths.iter().map(|p| p.strip_prefix(&report.target).unwrap().to_path_buf()).collect()
+            };
+            (strip(&report.removed_junk_files), strip(&report.removed_dirs), report.bytes_freed)
+        }
+
+        let test_dir = create_test_dir();
+        let single = test_dir.join("single");
+        let parallel = test_dir.join("parallel");
+        build_tree(&single);
+        build_tree(&parallel);
+
+        let options = CleanOptions { threads: 1, ..Default::default() };
+        let expected = clean_directory(&single, &JunkRules::default(), &options).expect("单线程清理失败");
+        assert_eq!(expected.removed_dirs.len(), 16 * 3 + 8, "期望删除所有空目录链和只有垃圾文件的目录");
+
+        // 预览模式的级联判断在多线程下也一样
+        let options = CleanOptions { threads: 8, dry_run: true, ..Default::default() };
+        let preview = clean_directory(&parallel, &JunkRules::default(), &options).expect("多线程预览失败");
+        assert_eq!(relative(&preview), relative(&expected), "期望多线程预览和单线程结果相同");
+
+        let journal = test_dir.join("journal.jsonl");
+        let options = CleanOptions { threads: 8, journal: Some(journal.clone()), ..Default::default() };
+        let report = clean_directory(&parallel, &JunkRules::default(), &options).expect("多线程清理失败");
+        assert_eq!(relative(&report), relative(&expected), "期望多线程清理和单线程结果相同");
+        assert!(report.errors.is_empty(), "期望没有错误，实际有{:?}", report.errors);
+        for i in 0..16 {
+            let dir = parallel.join(format!("d{}", i));
+            assert_eq!(dir.exists(), i % 2 == 1, "期望只保留有普通文件的目录 {:?}", dir);
+        }
+
+        // 日志中每个目录都在它的所有子目录之后记录
+        let content = fs::read_to_string(&journal).unwrap();
+        let removed: Vec<PathBuf> = content
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .filter(|record| record["type"] == "dir")
+            .map(|record| PathBuf::from(record["path"].as_str().unwrap()))
+            .collect();
+        for (index, dir) in removed.iter().enumerate() {
+            assert!(
+                removed[index + 1..].iter().all(|later| !later.starts_with(dir)),
+                "期望 {:?} 在子目录之后记录",
+                dir
+            );
+        }
+
+        let undone = undo(&journal, report.run_id.as_deref().unwrap()).expect("撤销失败");
+        assert!(undone.errors.is_empty(), "期望撤销没有错误，实际有{:?}", undone.errors);
+        assert!(parallel.join("d0/x/y/z").is_dir(), "期望撤销后空目录链被重新创建");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 在 root 下创建 depth 层深的目录链 root/name/name/.../name，并在最深的目录中创建 files 中的空文件
+    // 路径长度远远超过 PATH_MAX，只能用 mkdirat / openat 逐层相对创建
+    #[cfg(unix)]
+    fn create_deep_chain(root: &std::path::Path, name: &str, depth: usize, files: &[&str]) {
+        use rustix::fs::{mkdirat, openat, Mode, OFlags, CWD};
+        let flags = OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC;
+        let mut dir = openat(CWD, root, flags, Mode::empty()).unwrap();
+        for _ in 0..depth {
+            mkdirat(&dir, name, Mode::from_raw_mode(0o755)).unwrap();
+            dir = openat(&dir, name, flags, Mode::empty()).unwrap();
+        }
+        for file in files {
+            openat(&dir, *file, OFlags::WRONLY | OFlags::CREATE | OFlags::CLOEXEC, Mode::from_raw_mode(0o644)).unwrap();
+        }
+    }
+
+    // 10000 层深的目录树：遍历不会栈溢出，路径长度超过 PATH_MAX 也能正常删除
+    #[cfg(unix)]
+    #[test]
+    fn test_deep_tree() {
+        const DEPTH: usize = 10_000;
+        let test_dir = create_test_dir();
+
+        // 两条最深处只有垃圾文件的目录链：整棵树都会被删除
+        let junk_only = test_dir.join("junk_only");
+        fs::create_dir(&junk_only).unwrap();
+        create_deep_chain(&junk_only, "a", DEPTH, &["thumbs.db"]);
+        create_deep_chain(&junk_only, "b", DEPTH, &["thumbs.db"]);
+
+        let options = CleanOptions { dry_run: true, ..Default::default() };
+        let preview = clean_directory(&junk_only, &JunkRules::default(), &options).expect("预览失败");
+        assert_eq!(preview.removed_junk_files.len(), 2, "期望找到两个垃圾文件");
+        assert_eq!(preview.removed_junk_files[0].components().count(), junk_only.components().count() + DEPTH + 1);
+        assert_eq!(preview.removed_dirs.len(), 2 * DEPTH, "期望预览时两条目录链都将被删除");
+        // 后序：最深的目录在前，根目录下的 a 在 a 链的最后
+        assert_eq!(preview.removed_dirs[DEPTH - 1], junk_only.join("a"));
+        assert_eq!(preview.root_status, RootStatus::KeptEmpty);
+
+        // 多线程实际删除（两条链分给不同的任务），连同根目录一起
+        let options = CleanOptions { threads: 4, remove_root: true, ..Default::default() };
+        let report = clean_directory(&junk_only, &JunkRules::default(), &options).expect("清理失败");
+        assert!(report.errors.is_empty(), "期望没有错误，实际有{:?}", report.errors.first());
+        assert_eq!(report.removed_dirs, [preview.removed_dirs, vec![junk_only.clone()]].concat());
+        assert_eq!(report.root_status, RootStatus::Removed);
+        assert!(!junk_only.exists(), "期望根目录被删除");
+
+        // 最深处还有普通文件：只删除垃圾文件，整条目录链保留
+        let kept = test_dir.join("kept");
+        fs::create_dir(&kept).unwrap();
+        create_deep_chain(&kept, "d", DEPTH, &["thumbs.db", "keep.txt"]);
+        let report = clean_directory(&kept, &JunkRules::default(), &CleanOptions::default()).expect("清理失败");
+        assert_eq!(report.removed_junk_files.len(), 1, "期望删除最深处的垃圾文件");
+        assert!(report.removed_dirs.is_empty(), "期望保留整条目录链");
+        assert_eq!(report.root_status, RootStatus::NotEmpty);
+
+        // 把普通文件也当作垃圾文件清理掉整棵树（fs::remove_dir_all 不适合这么深的目录）
+        let mut rules = JunkRules::empty();
+        rules.add_spec("keep.txt").unwrap();
+        let options = CleanOptions { remove_root: true, ..Default::default() };
+        let report = clean_directory(&kept, &rules, &options).expect("清理失败");
+        assert_eq!(report.removed_dirs.len(), DEPTH + 1, "期望删除整条目录链和根目录");
+
+        // 隔离模式：隔离位置中要建出同样深的目录链，移动同样不能按完整路径进行
+        let quarantined = test_dir.join("quarantined");
+        fs::create_dir(&quarantined).unwrap();
+        create_deep_chain(&quarantined, "q", DEPTH, &["thumbs.db"]);
+        let quarantine = Quarantine::Folder(test_dir.join("quarantine"));
+        let options = CleanOptions { quarantine: Some(quarantine), threads: 2, ..Default::default() };
+        let report = clean_directory(&quarantined, &JunkRules::default(), &options).expect("清理失败");
+        assert!(report.errors.is_empty(), "期望没有错误，实际有{:?}", report.errors.first());
+        assert_eq!(report.removed_dirs.len(), DEPTH, "期望整条目录链被移走");
+        assert_eq!(report.root_status, RootStatus::KeptEmpty);
+
+        // 路径太长，无法按路径检查隔离位置：再清理一次运行目录，确认里面是同样的目录链和最深处的文件
+        let run_dir = report.quarantine.expect("期望报告记录隔离位置");
+        let mut rules = JunkRules::default();
+        rules.add_spec(&format!("name:{}", trash::ORIGIN_FILE)).unwrap();
+        let options = CleanOptions { remove_root: true, ..Default::default() };
+        let moved = clean_directory(&run_dir, &rules, &options).expect("清理隔离位置失败");
+        let moved_junk: Vec<_> = moved.removed_junk_files.iter().map(|path| path.strip_prefix(&run_dir).unwrap()).collect();
+        let original: Vec<_> = report.removed_junk_files.iter().map(|path| path.strip_prefix(&quarantined).unwrap()).collect();
+        assert_eq!(moved_junk.len(), 2, "期望隔离位置中有移走的文件和 .eptdir-origin");
+        assert!(moved_junk.contains(&original[0]), "期望文件在隔离位置中保留原来的相对路径");
+        assert_eq!(moved.removed_dirs.len(), DEPTH + 1, "期望隔离位置中有同样深的目录链");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 测试清理过程中的事件：按遍历的顺序产生，和报告一致
+    #[test]
+    fn test_events() {
+        use std::sync::{Arc, Mutex};
+
+        let test_dir = create_test_dir();
+        fs::create_dir_all(test_dir.join("a/b")).unwrap();
+        fs::write(test_dir.join("a/thumbs.db"), b"12345").unwrap();
+        fs::write(test_dir.join("keep.txt"), b"data").unwrap();
+
+        // 收集每个事件的名字和路径（相对于测试目录）
+        let events: Arc<Mutex<Vec<String>>> = Arc::default();
+        let sink = {
+            let events = Arc::clone(&events);
+            let root = test_dir.clone();
+            EventSink::new(move |event| {
+                let path = match *event {
+                    Event::TargetStarted { target: path, .. }
+                    | Event::DirEntered { path }
+                    | Event::JunkRemoved { path, .. }
+                    | Event::EmptyFileRemoved { path }
+                    | Event::DirRemoved { path } => path.strip_prefix(&root).unwrap().to_string_lossy().into_owned(),
+                    _ => String::new(),
+                };
+                events.lock().unwrap().push(format!("{} {}", event.name(), path));
+            })
+        };
+        let options = CleanOptions { observer: Some(Arc::new(sink)), ..Default::default() };
+        let report = clean_directory(&test_dir, &JunkRules::default(), &options).expect("清理失败");
+
+        let expected = [
+            "target_started ",
+            "dir_entered ",
+            "dir_entered a",
+            "junk_removed a/thumbs.db",
+            "dir_entered a/b",
+            "dir_removed a/b",
+            "dir_removed a",
+            "target_finished ",
+        ];
+        assert_eq!(*events.lock().unwrap(), expected, "期望按遍历的顺序产生事件");
+        assert_eq!(report.removed_dirs.len(), 2, "期望删除两个空文件夹");
+
+        // JSON 形式带有事件的名字
+        let event = Event::JunkRemoved { path: &test_dir, bytes: 5 }.to_json();
+        assert_eq!(event["event"], "junk_removed");
+        assert_eq!(event["bytes"], 5);
+        let event = Event::TargetFinished { report: &report, error: None }.to_json();
+        assert_eq!(event["removed_dirs"], 2, "期望结束事件包含数量");
+        assert_eq!(event["bytes_freed"], 5);
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 否决名字以 keep 开头的条目，并记下发现的垃圾文件
+    #[derive(Default)]
+    struct VetoKeep {
+        found: std::sync::Mutex<Vec<(PathBuf, RemoveKind, u64)>>,
+    }
+
+    impl CleanupObserver for VetoKeep {
+        fn on_junk_found(&self, path: &std::path::Path, kind: RemoveKind, bytes: u64) {
+            self.found.lock().unwrap().push((path.to_path_buf(), kind, bytes));
+        }
+
+        fn before_remove(&self, path: &std::path::Path, _kind: RemoveKind) -> bool {
+            !path.file_name().unwrap().to_string_lossy().starts_with("keep")
+        }
+    }
+
+    // 测试观察者否决删除：被否决的条目保留下来，记录到报告中，所在的目录也不会被删除
+    #[test]
+    fn test_observer_veto() {
+        let test_dir = create_test_dir();
+        fs::create_dir_all(test_dir.join("a/keep_dir")).unwrap();
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::create_dir_all(test_dir.join("c")).unwrap();
+        fs::write(test_dir.join("b/thumbs.db"), b"123").unwrap();
+        fs::write(test_dir.join("c/keep.tmp"), b"").unwrap();
+
+        let observer = Arc::new(VetoKeep::default());
+        let mut rules = JunkRules::default();
+        rules.add_spec("*.tmp").unwrap();
+        let options = CleanOptions { observer: Some(observer.clone()), remove_root: true, ..Default::default() };
+        let report = clean_directory(&test_dir, &rules, &options).expect("清理失败");
+
+        assert_eq!(report.removed_junk_files, vec![test_dir.join("b/thumbs.db")], "期望删除没有被否决的垃圾文件");
+        assert_eq!(report.removed_dirs, vec![test_dir.join("b")], "期望只删除没有被否决的目录");
+        let vetoed: Vec<_> =
+            report.skipped.iter().filter(|entry| entry.reason == SkipReason::Vetoed).map(|entry| &entry.path).collect();
+        assert_eq!(vetoed, [&test_dir.join("a/keep_dir"), &test_dir.join("c/keep.tmp")], "期望记录被否决的条目");
+        assert!(test_dir.join("a/keep_dir").exists(), "期望保留被否决的目录和它的上级目录");
+        assert!(test_dir.join("c/keep.tmp").exists(), "期望保留被否决的文件");
+        assert_eq!(report.root_status, RootStatus::NotEmpty);
+
+        let found = observer.found.lock().unwrap();
+        assert_eq!(found.len(), 2, "期望发现两个垃圾文件，实际是{:?}", found);
+        assert!(found.contains(&(test_dir.join("b/thumbs.db"), RemoveKind::JunkFile, 3)));
+
+        // 预览模式同样询问观察者；否决根目录时根目录按"已为空、保留"报告
+        let empty = test_dir.join("keep_root");
+        fs::create_dir(&empty).unwrap();
+        let options = CleanOptions { observer: Some(observer.clone()), remove_root: true, dry_run: true, ..Default::default() };
+        let report = clean_directory(&empty, &rules, &options).expect("清理失败");
+        assert_eq!(report.root_status, RootStatus::KeptEmpty, "期望否决删除根目录");
+        assert!(report.skipped.iter().any(|entry| entry.reason == SkipReason::Vetoed));
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 测试先计划再执行：计划不修改磁盘，可以保存和读取；执行时跳过计划以后变化了的条目
+    #[test]
+    fn test_plan_and_execute() {
+        let test_dir = create_test_dir();
+        fs::create_dir_all(test_dir.join("a/b")).unwrap();
+        fs::create_dir_all(test_dir.join("c")).unwrap();
+        fs::create_dir_all(test_dir.join("d")).unwrap();
+        fs::create_dir_all(test_dir.join("e")).unwrap();
+        fs::write(test_dir.join("a/thumbs.db"), b"12345").unwrap();
+        fs::write(test_dir.join("d/keep.txt"), b"data").unwrap();
+        fs::write(test_dir.join("e/thumbs.db"), b"123").unwrap();
+        fs::create_dir_all(test_dir.join("f")).unwrap();
+        fs::write(test_dir.join("f/.gitkeep"), b"").unwrap();
+
+        let (plan, scan) = plan(&test_dir, &JunkRules::default(), &CleanOptions::default()).expect("计划失败");
+        let planned: Vec<_> = plan.entries.iter().map(|entry| (entry.path.clone(), entry.kind)).collect();
+        assert_eq!(
+            planned,
+            vec![
+                (test_dir.join("a/thumbs.db"), RemoveKind::JunkFile),
+                (test_dir.join("e/thumbs.db"), RemoveKind::JunkFile),
+                (test_dir.join("a/b"), RemoveKind::Dir),
+                (test_dir.join("a"), RemoveKind::Dir),
+                (test_dir.join("c"), RemoveKind::Dir),
+                (test_dir.join("e"), RemoveKind::Dir),
+            ],
+            "期望先删除文件，再按子目录在前的顺序删除目录"
+        );
+        assert_eq!(plan.entries[0].size, 5, "期望记录文件的大小");
+        // 没有放进计划的部分留在预览的报告中
+        assert_eq!(
+            scan.skipped,
+            vec![SkippedEntry { path: test_dir.join("f"), reason: SkipReason::Kept }],
+            "期望返回制定计划时跳过的条目"
+        );
+        assert!(scan.errors.is_empty() && scan.removed_dirs.is_empty(), "期望计划中的条目不再留在报告中");
+        assert!(test_dir.join("a/thumbs.db").exists(), "期望制定计划不修改磁盘");
+
+        // 保存以后读回来完全相同
+        let plan_file = std::env::temp_dir().join(format!("eptdir_plan_{:?}.json", std::thread::current().id()));
+        plan.save(&plan_file).expect("保存计划失败");
+        let loaded = CleanupPlan::load(&plan_file).expect("读取计划失败");
+        fs::remove_file(&plan_file).ok();
+        assert_eq!(loaded, plan, "期望保存和读取以后计划不变");
+
+        // 没有修改时间的条目写成 null，可以读回来，但执行时无法确认，跳过
+        let entry = PlanEntry { mtime: None, ..plan.entries[1].clone() };
+        let unverifiable = CleanupPlan { target: plan.target.clone(), entries: vec![entry.clone()] };
+        let loaded = CleanupPlan::from_json(&unverifiable.to_json(), &plan_file).expect("读取没有修改时间的计划失败");
+        assert_eq!(loaded, unverifiable, "期望没有修改时间的条目也能读回来");
+        let report = execute(&loaded, &CleanOptions { dry_run: true, ..Default::default() }).expect("执行失败");
+        assert_eq!(
+            report.skipped,
+            vec![SkippedEntry { path: entry.path, reason: SkipReason::Changed }],
+            "期望跳过无法确认的条目"
+        );
+
+        // 预览模式执行：报告和计划一致，不修改磁盘
+        let options = CleanOptions { dry_run: true, ..Default::default() };
+        let report = execute(&plan, &options).expect("执行失败");
+        assert!(report.skipped.is_empty(), "期望没有变化，实际跳过了{:?}", report.skipped);
+        assert_eq!(report.removed_dirs.len(), 4);
+        assert!(test_dir.join("c").exists(), "期望预览不修改磁盘");
+
+        // 计划以后：a/thumbs.db 被改写（大小变了），c 中多了一个文件
+        fs::write(test_dir.join("a/thumbs.db"), b"123456").unwrap();
+        fs::write(test_dir.join("c/new.txt"), b"new").unwrap();
+        let report = execute(&plan, &CleanOptions::default()).expect("执行失败");
+        assert_eq!(report.removed_junk_files, vec![test_dir.join("e/thumbs.db")], "期望只删除没有变化的文件");
+        assert_eq!(report.removed_dirs, vec![test_dir.join("a/b"), test_dir.join("e")], "期望只删除没有变化的目录");
+        let changed: Vec<_> =
+            report.skipped.iter().filter(|entry| entry.reason == SkipReason::Changed).map(|entry| &entry.path).collect();
+        assert_eq!(
+            changed,
+            [&test_dir.join("a"), &test_dir.join("a/thumbs.db"), &test_dir.join("c")],
+            "期望跳过变化了的条目和因此不再为空的目录"
+        );
+        assert!(test_dir.join("a/thumbs.db").exists() && test_dir.join("c/new.txt").exists());
+        assert!(!test_dir.join("e").exists(), "期望删除计划中的目录");
+        assert_eq!(report.root_status, RootStatus::NotEmpty);
+
+        // 编辑过的计划中有目标目录以外的条目：什么都不删除
+        let outside = test_dir.join("d");
+        let bad = CleanupPlan {
+            target: test_dir.join("a"),
+            entries: vec![PlanEntry { path: outside.clone(), kind: RemoveKind::Dir, size: 0, mtime: None, inode: None }],
+        };
+        let result = execute(&bad, &CleanOptions::default());
+        assert!(matches!(result, Err(EptdirError::InvalidPlan { .. })), "期望拒绝目标目录以外的条目，实际是{:?}", result);
+        let sneaky = PlanEntry { path: test_dir.join("a/../d"), ..bad.entries[0].clone() };
+        let result = execute(&CleanupPlan { entries: vec![sneaky], ..bad }, &CleanOptions::default());
+        assert!(matches!(result, Err(EptdirError::InvalidPlan { .. })), "期望拒绝带 .. 的条目");
+        assert!(outside.exists());
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 计划中的条目经过符号链接指向目标目录以外：执行时跳过，不删除目标目录以外的任何内容
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_plan_through_symlink() {
+        use std::os::unix::fs::symlink;
+        let test_dir = create_test_dir();
+        let root = test_dir.join("root");
+        let outside = test_dir.join("outside");
+        fs::create_dir(&root).unwrap();
+        fs::create_dir_all(outside.join("empty")).unwrap();
+        fs::write(outside.join("thumbs.db"), b"123").unwrap();
+        symlink(&outside, root.join("link")).unwrap();
+
+        // 跟随符号链接制定的计划：条目的路径都经过 root/link
+        let options = CleanOptions { symlinks: SymlinkPolicy::Follow, ..Default::default() };
+        let (mut plan, _) = plan(&root, &JunkRules::default(), &options).expect("计划失败");
+        assert_eq!(plan.entries.len(), 2, "期望计划中有链接指向的目录中的文件和空目录");
+        // 再手工加一个条目：身份信息和真实的文件完全一致，只有路径中间的符号链接能拦住它
+        fs::write(outside.join("victim.txt"), b"data").unwrap();
+        let victim = sys::stat_path(&outside.join("victim.txt")).unwrap();
+        plan.entries.push(PlanEntry {
+            path: root.join("link/victim.txt"),
+            kind: RemoveKind::JunkFile,
+            size: victim.len,
+            mtime: victim.modified,
+            inode: victim.inode,
+        });
+
+        let report = execute(&plan, &CleanOptions::default()).expect("执行失败");
+        assert!(report.removed_junk_files.is_empty() && report.removed_dirs.is_empty(), "期望什么都不删除");
+        assert_eq!(report.skipped.len(), 3, "期望跳过所有经过符号链接的条目");
+        assert!(report.skipped.iter().all(|entry| entry.reason == SkipReason::Symlink));
+        assert!(outside.join("thumbs.db").exists() && outside.join("empty").exists() && outside.join("victim.txt").exists());
+
+        // 计划文件中缺少 dev / ino 或修改时间的条目无法重新确认，读取时拒绝
+        let mut value = plan.to_json();
+        value["entries"][0]["ino"] = serde_json::Value::Null;
+        let result = CleanupPlan::from_json(&value, Path::new("plan.json"));
+        assert!(matches!(result, Err(EptdirError::InvalidPlan { .. })), "期望拒绝缺少 inode 的条目");
+        let mut value = plan.to_json();
+        value["entries"][0]["mtime_secs"] = serde_json::Value::Null;
+        assert!(CleanupPlan::from_json(&value, Path::new("plan.json")).is_err(), "期望拒绝缺少修改时间的条目");
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // 测试不存在的目录应该返回 TargetNotFound 错误
     #[test]
     fn test_clean_directory_nonexistent() {
         // 测试不存在的目录应该返回 Ok（跳过）
         let nonexistent = PathBuf::from("/nonexistent/path/that/does/not/exist");
         
-        let result = clean_directory(&nonexistent);
-        // 根据代码逻辑，不存在的目录会返回 Ok(())
-        assert!(result.is_ok(), "期望函数执行成功，但实际失败了");
+        let result = clean_directory(&nonexistent, &JunkRules::default(), &CleanOptions::default());
+        // 不存在的目录返回专门的 TargetNotFound 错误
+        assert!(
+            matches!(result, Err(EptdirError::TargetNotFound(ref path)) if *path == nonexistent),
+            "期望返回 TargetNotFound 错误，实际是{:?}",
+            result
+        );
     }
     
-    // 测试如果路径是文件而不是目录，应该返回Ok但跳过这个路径
+    // 测试如果路径是文件而不是目录，应该返回 NotADirectory 错误
     #[test]
     fn test_clean_directory_not_a_dir() {
         let test_dir = create_test_dir();
         let test_file = test_dir.join("test_file.txt");
         fs::File::create(&test_file).unwrap();
         
-        let result = clean_directory(&test_file);
-        // 根据代码逻辑，文件路径会返回 Ok(())
-        assert!(result.is_ok(), "期望函数执行成功，但实际失败了");
+        let result = clean_directory(&test_file, &JunkRules::default(), &CleanOptions::default());
+        // 文件路径返回专门的 NotADirectory 错误
+        assert!(
+            matches!(result, Err(EptdirError::NotADirectory(_))),
+            "期望返回 NotADirectory 错误，实际是{:?}",
+            result
+        );
         
         fs::remove_dir_all(&test_dir).ok();
     }
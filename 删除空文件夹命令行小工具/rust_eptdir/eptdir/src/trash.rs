@@ -0,0 +1,751 @@
+// ========================================
+// 隔离目录（回收站）
+// ========================================
+// 打开 CleanOptions::quarantine 后，垃圾文件和空文件夹不再被永久删除，而是移动到隔离位置：
+//   - Trash：freedesktop.org 回收站布局
+//       <回收站>/files/<名字>              被移走的文件或目录
+//       <回收站>/info/<名字>.trashinfo     原始位置和删除时间，另外有一行 TRASH_MARKER 表示是 eptdir 放进去的
+//     指定的回收站和目标目录不在同一个文件系统上时，按规范改用目标目录所在分区的回收站
+//     （$topdir/.Trash/$uid 或 $topdir/.Trash-$uid），都不能使用时才移动到指定的回收站（只能复制后删除）
+//   - Folder：普通隔离目录，每次运行一个带时间戳的子目录，保留相对目标目录的路径
+//       <隔离目录>/20261016-093000/a/b/thumbs.db
+//       <隔离目录>/20261016-093000/.eptdir-origin   目标目录的绝对路径
+// 空文件夹不会被真正移动：删除原来的目录，再在隔离位置创建一个同名的空目录，
+// 这样父目录随后变空时也能被放进同一个位置
+// 移动相对于条目所在目录的句柄进行（renameat），隔离位置中的目录也逐层打开（mkdirat / openat），
+// 和遍历一样不需要拼出完整路径，很深的目录树也不会遇到 ENAMETOOLONG
+// restore() 按照记录的原始位置把内容放回去
+use std::env;
+use std::ffi::OsStr;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{EptdirError, Operation};
+use crate::escape::{escape_path, path_bytes, path_from_bytes};
+use crate::lock;
+use crate::sys::{self, DirHandle, FileKind};
+
+// Folder 布局中记录目标目录绝对路径的文件
+pub const ORIGIN_FILE: &str = ".eptdir-origin";
+
+// eptdir 写入的 .trashinfo 中额外的一行；restore 只恢复有这一行的条目，不会放回其他程序删除的文件
+// 按规范，读取 .trashinfo 的程序会忽略不认识的键
+pub const TRASH_MARKER: &str = "X-Eptdir=1";
+
+// 隔离位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Quarantine {
+    // freedesktop.org 回收站目录（包含 files 和 info 两个子目录）
+    // 通常是 home_trash()；目标目录在另一个文件系统上时优先使用那个分区上的回收站
+    Trash(PathBuf),
+    // 普通隔离目录，每次运行在其中创建一个带时间戳的子目录
+    Folder(PathBuf),
+}
+
+impl Quarantine {
+    // 当前用户的回收站：$XDG_DATA_HOME/Trash，没有设置时是 ~/.local/share/Trash
+    pub fn home_trash() -> Option<PathBuf> {
+        // filter() 把空字符串当作没有设置
+        if let Some(data_home) = env::var_os("XDG_DATA_HOME").filter(|value| !value.is_empty()) {
+            return Some(PathBuf::from(data_home).join("Trash"));
+        }
+        env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share/Trash"))
+    }
+
+    // 隔离位置的根目录
+    pub fn dir(&self) -> &Path {
+        match self {
+            Quarantine::Trash(dir) | Quarantine::Folder(dir) => dir,
+        }
+    }
+}
+
+// 一次清理中负责把条目移动到隔离位置
+// 多线程清理时各个线程共用同一个 Mover，所以方法都只需要 &self
+pub(crate) struct Mover {
+    quarantine: Quarantine,
+    // 清理的目标目录（调用者传入的写法），用于计算相对路径
+    target: PathBuf,
+    // 隔离位置根目录的绝对路径，用于在遍历时避开它
+    root: PathBuf,
+    // Trash 布局：目标目录所在分区上的回收站（真实路径），按优先顺序；
+    // 和指定的回收站在同一个文件系统上时为空。同样要在遍历时避开
+    volumes: Vec<PathBuf>,
+    // 本次运行实际使用的位置，第一次移动时才确定并创建：Folder 布局是带时间戳的子目录，Trash 布局是回收站目录
+    // Mutex 保证多个线程同时第一次移动时只创建一个子目录
+    run_dir: Mutex<Option<PathBuf>>,
+    // Folder 布局中上一次移动到的目录（相对运行目录的路径和它的句柄）
+    // 后序遍历中相邻的两次移动通常在同一个目录或者只差一两层，不用每次都从运行目录逐层打开
+    dest: Mutex<Option<(PathBuf, DirHandle)>>,
+}
+
+impl Mover {
+    pub(crate) fn new(quarantine: &Quarantine, target: &Path) -> Self {
+        // std::path::absolute() 只拼接当前目录，不解析符号链接，也不要求路径存在
+        let root = std::path::absolute(quarantine.dir()).unwrap_or_else(|_| quarantine.dir().to_path_buf());
+        let volumes = match quarantine {
+            Quarantine::Trash(dir) => volume_trashes(target, dir),
+            Quarantine::Folder(_) => Vec::new(),
+        };
+        Mover {
+            quarantine: quarantine.clone(),
+            target: target.to_path_buf(),
+            root,
+            volumes,
+            run_dir: Mutex::new(None),
+            dest: Mutex::new(None),
+        }
+    }
+
+    // 本次运行实际使用的隔离位置，没有移动过任何条目时返回 None
+    // Folder 布局是带时间戳的子目录，Trash 布局是实际使用的回收站目录
+    pub(crate) fn location(&self) -> Option<PathBuf> {
+        self.run_dir().clone()
+    }
+
+    fn run_dir(&self) -> MutexGuard<'_, Option<PathBuf>> {
+        lock(&self.run_dir)
+    }
+
+    // path 是否就是隔离位置本身（隔离目录放在目标目录里面时，遍历要跳过它）
+    pub(crate) fn is_quarantine(&self, path: &Path) -> bool {
+        // 先比较文件名，避免对每个条目都计算绝对路径
+        let name = path.file_name();
+        if name == self.root.file_name() && std::path::absolute(path).is_ok_and(|path| path == self.root) {
+            return true;
+        }
+        // 分区上的回收站是按真实路径算出来的，同样按真实路径比较
+        self.volumes.iter().any(|trash| trash.file_name() == name)
+            && fs::canonicalize(path).is_ok_and(|path| self.volumes.contains(&path))
+    }
+
+    // 把目录 parent 中的文件 name 移动到隔离位置，返回移动后的路径
+    // path 是文件原来的路径（目录可能已经被临时改名），隔离位置和恢复时使用的原位置按它计算
+    pub(crate) fn move_file(&self, parent: &DirHandle, name: &OsStr, path: &Path) -> Result<PathBuf, EptdirError> {
+        match self.quarantine {
+            Quarantine::Folder(_) => {
+                let relative = relative_path(&self.target, path);
+                self.with_dest_dir(path, &relative, |dest_dir| move_at(parent, name, dest_dir, sys::file_name(&relative)))
+            }
+            Quarantine::Trash(_) => {
+                let (info, dest) = self.trash_reserve(path)?;
+                let moved = DirHandle::open(sys::parent_path(&dest))
+                    .and_then(|files| move_at(parent, name, &files, sys::file_name(&dest)));
+                if let Err(e) = moved {
+                    // 移动失败，删除已经写好的 .trashinfo
+                    fs::remove_file(&info).ok();
+                    return Err(EptdirError::io(path, Operation::Quarantine, e));
+                }
+                Ok(dest)
+            }
+        }
+    }
+
+    // 把空目录"移动"到隔离位置：删除原目录（目录 parent 中的 name），在隔离位置创建一个空目录
+    // parent 为 None 时按 path 删除（目标目录本身）
+    // 删除失败时返回 RemoveDir 错误（目录在删除前被写入了内容时是 DirectoryNotEmpty）
+    // 返回隔离位置中对应的空目录
+    pub(crate) fn move_dir(&self, parent: Option<&DirHandle>, name: &OsStr, path: &Path) -> Result<PathBuf, EptdirError> {
+        match self.quarantine {
+            Quarantine::Folder(_) => {
+                remove_dir_at(parent, name, path)?;
+                let relative = relative_path(&self.target, path);
+                // 目标目录本身对应运行目录，已经存在
+                match relative.file_name() {
+                    Some(dest_name) => self.with_dest_dir(path, &relative, |dest_dir| match dest_dir.create_dir(dest_name) {
+                        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => Err(e),
+                        _ => Ok(()),
+                    }),
+                    None => self.folder_run_dir(),
+                }
+            }
+            Quarantine::Trash(_) => {
+                let (info, dest) = self.trash_reserve(path)?;
+                if let Err(e) = remove_dir_at(parent, name, path) {
+                    fs::remove_file(&info).ok();
+                    return Err(e);
+                }
+                fs::create_dir(&dest).map_err(|e| EptdirError::io(&dest, Operation::Quarantine, e))?;
+                Ok(dest)
+            }
+        }
+    }
+
+    // Folder 布局本次运行的子目录，第一次调用时创建
+    fn folder_run_dir(&self) -> Result<PathBuf, EptdirError> {
+        // 创建子目录时一直持有锁，其他线程等它创建完再使用同一个子目录
+        let mut guard = self.run_dir();
+        if let Some(ref run_dir) = *guard {
+            return Ok(run_dir.clone());
+        }
+        let run_dir = create_run_dir(self.quarantine.dir(), &self.target)?;
+        *guard = Some(run_dir.clone());
+        Ok(run_dir)
+    }
+
+    // 打开（需要时逐层创建）运行目录中 relative 所在的目录，对它的句柄调用 f，返回 relative 在隔离位置中的完整路径
+    // 从上一次的目录出发，先经过 ".." 退回到共同的上级目录，再逐层进入，都不跟随符号链接
+    // 持有锁直到 f 返回：多线程清理时移动到隔离位置是串行的
+    // path 是条目原来的路径，只用于错误信息
+    fn with_dest_dir<F>(&self, path: &Path, relative: &Path, f: F) -> Result<PathBuf, EptdirError>
+    where
+        F: FnOnce(&DirHandle) -> io::Result<()>,
+    {
+        let run_dir = self.folder_run_dir()?;
+        let mut guard = lock(&self.dest);
+        let opened = self.open_dest_dir(guard.take(), &run_dir, sys::parent_path(relative)).and_then(|opened| {
+            f(&opened.1)?;
+            Ok(opened)
+        });
+        match opened {
+            Ok(opened) => {
+                *guard = Some(opened);
+                Ok(run_dir.join(relative))
+            }
+            // 出错时缓存的句柄已经被取走，下次从运行目录重新打开
+            Err(e) => Err(EptdirError::io(path, Operation::Quarantine, e)),
+        }
+    }
+
+    // 从缓存的目录 cached（没有时从运行目录）出发，打开运行目录中的 dir
+    fn open_dest_dir(
+        &self,
+        cached: Option<(PathBuf, DirHandle)>,
+        run_dir: &Path,
+        dir: &Path,
+    ) -> io::Result<(PathBuf, DirHandle)> {
+        // parent_path() 把只有一层的路径的上级目录写成 "."，这里就是运行目录本身
+        let dir = if dir == Path::new(".") { Path::new("") } else { dir };
+        let (mut current, mut handle) = match cached {
+            Some(cached) => cached,
+            None => (PathBuf::new(), DirHandle::open(run_dir)?),
+        };
+        // 常见的情况（同一个目录、上一层）直接比较字节，不逐个比较很长的路径的组成部分
+        if current.as_os_str() == dir.as_os_str() {
+            return Ok((current, handle));
+        }
+        if current.parent().is_some_and(|parent| parent.as_os_str() == dir.as_os_str()) {
+            current.pop();
+            return Ok((current, handle.parent()?));
+        }
+        while !dir.starts_with(&current) {
+            handle = handle.parent()?;
+            current.pop();
+        }
+        let rest = dir.strip_prefix(&current).map(Path::to_path_buf).unwrap_or_default();
+        for name in rest.iter() {
+            match handle.create_dir(name) {
+                Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+                _ => {}
+            }
+            handle = handle.open_dir(name, false)?;
+            current.push(name);
+        }
+        Ok((current, handle))
+    }
+
+    // Trash 布局本次运行使用的回收站，第一次调用时确定并创建 files 和 info 子目录
+    // 先试分区上的回收站，都不能使用（例如没有权限在挂载点创建目录）时才使用指定的回收站
+    fn trash_dir(&self) -> Result<PathBuf, EptdirError> {
+        let mut guard = self.run_dir();
+        if let Some(ref trash) = *guard {
+            return Ok(trash.clone());
+        }
+        let volume = self.volumes.iter().find(|trash| prepare_trash(trash, true).is_ok());
+        let trash = match volume {
+            Some(trash) => trash.clone(),
+            None => {
+                let trash = self.quarantine.dir().to_path_buf();
+                prepare_trash(&trash, false).map_err(|e| EptdirError::io(&trash, Operation::Quarantine, e))?;
+                trash
+            }
+        };
+        *guard = Some(trash.clone());
+        Ok(trash)
+    }
+
+    // 在回收站中为 path 占用一个名字：先写好 info/<名字>.trashinfo，再返回 files/<名字>
+    // .trashinfo 用 create_new 创建，名字已被占用时换下一个（thumbs.db、thumbs.db.2 ...）
+    // create_new 是原子操作，多个线程同时移动同名文件也不会占用同一个名字
+    // 分区上的回收站中也记录绝对路径（规范允许），restore 不需要知道分区的挂载点
+    fn trash_reserve(&self, path: &Path) -> Result<(PathBuf, PathBuf), EptdirError> {
+        let trash = self.trash_dir()?;
+        let files = trash.join("files");
+        let info_dir = trash.join("info");
+
+        let original = std::path::absolute(path).map_err(|e| EptdirError::io(path, Operation::Quarantine, e))?;
+        let base = match path.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => "eptdir".to_string(),
+        };
+        let content = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n{}\n",
+            encode_path(&original),
+            format_datetime(SystemTime::now(), "-", "T", ":"),
+            TRASH_MARKER
+        );
+
+        for index in 1.. {
+            let name = if index == 1 { base.clone() } else { format!("{}.{}", base, index) };
+            let info = info_dir.join(format!("{}.trashinfo", name));
+            let dest = files.join(&name);
+            // files 中已经有同名条目（例如其他程序留下的）也换一个名字
+            if dest.symlink_metadata().is_ok() {
+                continue;
+            }
+            match OpenOptions::new().write(true).create_new(true).open(&info) {
+                Ok(mut file) => {
+                    if let Err(e) = file.write_all(content.as_bytes()) {
+                        fs::remove_file(&info).ok();
+                        return Err(EptdirError::io(&info, Operation::WriteFile, e));
+                    }
+                    return Ok((info, dest));
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(EptdirError::io(&info, Operation::WriteFile, e)),
+            }
+        }
+        unreachable!("1.. 是无限的范围")
+    }
+}
+
+// 目标目录所在分区上可以使用的回收站，按优先顺序（freedesktop.org 回收站规范）：
+//   1. $topdir/.Trash/$uid：管理员准备的 .Trash 是设置了粘滞位的目录（不能是符号链接）时
+//   2. $topdir/.Trash-$uid
+// topdir 是目标目录所在文件系统的挂载点（真实路径中同一个设备号的最上层目录）
+// 指定的回收站和目标目录在同一个文件系统上时直接使用指定的回收站，返回空列表
+// 这里只计算路径，不创建任何目录；没有 uid 的平台返回空列表
+fn volume_trashes(target: &Path, trash: &Path) -> Vec<PathBuf> {
+    let Some(uid) = sys::uid() else {
+        return Vec::new();
+    };
+    let Ok(target) = fs::canonicalize(target) else {
+        return Vec::new();
+    };
+    let Some(device) = sys::device(&target) else {
+        return Vec::new();
+    };
+    // 指定的回收站可能还不存在，看它最近的已经存在的上级目录
+    if trash.ancestors().find_map(sys::device).is_none_or(|trash_device| trash_device == device) {
+        return Vec::new();
+    }
+    let mut topdir = target.as_path();
+    while let Some(parent) = topdir.parent().filter(|parent| sys::device(parent) == Some(device)) {
+        topdir = parent;
+    }
+
+    let mut trashes = Vec::new();
+    let shared = topdir.join(".Trash");
+    if sys::stat_path(&shared).is_ok_and(|meta| meta.kind == FileKind::Dir && meta.mode.is_some_and(|mode| mode & 0o1000 != 0)) {
+        trashes.push(shared.join(uid.to_string()));
+    }
+    trashes.push(topdir.join(format!(".Trash-{}", uid)));
+    trashes
+}
+
+// 准备回收站目录：创建 files 和 info 子目录
+// 分区上的回收站（private）本身要由当前用户以 0700 创建，已经存在时不能是符号链接
+fn prepare_trash(trash: &Path, private: bool) -> io::Result<()> {
+    if private {
+        match sys::create_private_dir(trash) {
+            Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+            _ => {}
+        }
+        if sys::stat_path(trash)?.kind != FileKind::Dir {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} 不是目录", escape_path(trash))));
+        }
+    }
+    for dir in [trash.join("files"), trash.join("info")] {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+// 在隔离目录中创建本次运行的子目录，并写入 ORIGIN_FILE
+// 同一秒内多次运行时在名字后面加序号：20261016-093000、20261016-093000-2 ...
+fn create_run_dir(dir: &Path, target: &Path) -> Result<PathBuf, EptdirError> {
+    fs::create_dir_all(dir).map_err(|e| EptdirError::io(dir, Operation::Quarantine, e))?;
+    let origin = std::path::absolute(target).map_err(|e| EptdirError::io(target, Operation::Quarantine, e))?;
+    let stamp = format_datetime(SystemTime::now(), "", "-", "");
+
+    for index in 1.. {
+        let name = if index == 1 { stamp.clone() } else { format!("{}-{}", stamp, index) };
+        let run_dir = dir.join(name);
+        match fs::create_dir(&run_dir) {
+            Ok(()) => {
+                let origin_file = run_dir.join(ORIGIN_FILE);
+                fs::write(&origin_file, format!("{}\n", encode_path(&origin)))
+                    .map_err(|e| EptdirError::io(&origin_file, Operation::WriteFile, e))?;
+                return Ok(run_dir);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(EptdirError::io(&run_dir, Operation::Quarantine, e)),
+        }
+    }
+    unreachable!("1.. 是无限的范围")
+}
+
+// path 相对目标目录的路径
+// 跟随符号链接时路径仍然以目标目录开头；万一不是，就去掉根目录和盘符，只保留普通的路径部分
+fn relative_path(target: &Path, path: &Path) -> PathBuf {
+    match path.strip_prefix(target) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path.components().filter(|c| matches!(c, Component::Normal(_))).collect(),
+    }
+}
+
+// 删除空目录：parent 中的 name，没有 parent 时按 path 删除
+fn remove_dir_at(parent: Option<&DirHandle>, name: &OsStr, path: &Path) -> Result<(), EptdirError> {
+    let result = match parent {
+        Some(parent) => parent.remove_dir(name),
+        None => fs::remove_dir(path),
+    };
+    result.map_err(|e| EptdirError::io(path, Operation::RemoveDir, e))
+}
+
+// 把目录 from_dir 中的文件 from 移动到目录 to_dir 中的 to，不覆盖已经存在的条目
+// 不能跨文件系统时改为复制后删除
+fn move_at(from_dir: &DirHandle, from: &OsStr, to_dir: &DirHandle, to: &OsStr) -> io::Result<()> {
+    if to_dir.stat_at(to).is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} 已经存在", escape_path(Path::new(to)))));
+    }
+    match from_dir.rename_to(from, to_dir, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            from_dir.copy_to(from, to_dir, to)?;
+            if let Err(e) = from_dir.remove_file(from) {
+                // 原文件删不掉，删除复制出来的文件，保持原样
+                to_dir.remove_file(to).ok();
+                return Err(e);
+            }
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+// 移动文件或目录，不覆盖已经存在的条目
+// rename() 不能跨文件系统，这时对普通文件改为复制后删除
+pub(crate) fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if to.symlink_metadata().is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} 已经存在", escape_path(to))));
+    }
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices && from.is_file() => {
+            fs::copy(from, to)?;
+            if let Err(e) = fs::remove_file(from) {
+                // 原文件删不掉，删除复制出来的文件，保持原样
+                fs::remove_file(to).ok();
+                return Err(e);
+            }
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+// 回收站中的条目被放回原位置后，删除对应的 .trashinfo
+// item 不在回收站的 files 目录中时什么都不做
+pub(crate) fn remove_trash_info(item: &Path) {
+    let (Some(files), Some(name)) = (item.parent(), item.file_name()) else {
+        return;
+    };
+    if files.file_name().is_some_and(|dir| dir == "files")
+        && let Some(trash) = files.parent()
+    {
+        let mut info_name = name.to_os_string();
+        info_name.push(".trashinfo");
+        fs::remove_file(trash.join("info").join(info_name)).ok();
+    }
+}
+
+// ========================================
+// 恢复
+// ========================================
+
+// 一次恢复的结果
+#[derive(Debug)]
+pub struct RestoreReport {
+    // 恢复的来源（隔离目录或回收站）
+    pub source: PathBuf,
+    // 恢复到的原始路径
+    pub restored: Vec<PathBuf>,
+    // 无法恢复的条目（例如原位置已经有同名文件）
+    pub errors: Vec<EptdirError>,
+}
+
+// 把隔离位置中的内容放回原始位置
+// source 可以是：
+//   - Folder 布局中一次运行的子目录（包含 .eptdir-origin）
+//   - Folder 布局的隔离目录本身：恢复其中所有的运行
+//   - 回收站目录（包含 info 子目录）：只恢复 eptdir 放进去的条目（.trashinfo 中有 TRASH_MARKER）
+// only_under: 只恢复原始路径位于这个目录下的条目
+// 单个条目恢复失败不会中断，错误记录在报告的 errors 中
+pub fn restore(source: &Path, only_under: Option<&Path>) -> Result<RestoreReport, EptdirError> {
+    if !source.exists() {
+        return Err(EptdirError::TargetNotFound(source.to_path_buf()));
+    }
+    if !source.is_dir() {
+        return Err(EptdirError::NotADirectory(source.to_path_buf()));
+    }
+    let only_under = match only_under {
+        Some(dir) => Some(std::path::absolute(dir).map_err(|e| EptdirError::io(dir, Operation::Restore, e))?),
+        None => None,
+    };
+
+    let mut restorer = Restorer {
+        only_under,
+        report: RestoreReport { source: source.to_path_buf(), restored: Vec::new(), errors: Vec::new() },
+    };
+
+    if source.join(ORIGIN_FILE).is_file() {
+        restorer.restore_run(source)?;
+    } else if source.join("info").is_dir() {
+        restorer.restore_trash(source)?;
+    } else {
+        // 隔离目录本身：找出所有运行子目录，按名字（也就是时间）排序
+        let mut runs: Vec<PathBuf> = fs::read_dir(source)
+            .map_err(|e| EptdirError::io(source, Operation::ReadDir, e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.join(ORIGIN_FILE).is_file())
+            .collect();
+        if runs.is_empty() {
+            return Err(EptdirError::NotAQuarantine(source.to_path_buf()));
+        }
+        runs.sort();
+        for run in runs {
+            restorer.restore_run(&run)?;
+        }
+    }
+
+    Ok(restorer.report)
+}
+
+struct Restorer {
+    only_under: Option<PathBuf>,
+    report: RestoreReport,
+}
+
+impl Restorer {
+    // 原始路径是否在恢复范围内
+    fn wanted(&self, original: &Path) -> bool {
+        self.only_under.as_ref().is_none_or(|dir| original.starts_with(dir))
+    }
+
+    // 记录一个错误，继续恢复其他条目
+    fn fail(&mut self, path: &Path, operation: Operation, error: io::Error) {
+        self.report.errors.push(EptdirError::io(path, operation, error));
+    }
+
+    // 恢复 Folder 布局中的一次运行
+    fn restore_run(&mut self, run_dir: &Path) -> Result<(), EptdirError> {
+        let origin_file = run_dir.join(ORIGIN_FILE);
+        let content =
+            fs::read_to_string(&origin_file).map_err(|e| EptdirError::io(&origin_file, Operation::ReadFile, e))?;
+        let Some(origin) = decode_path(content.trim_end()) else {
+            return Err(EptdirError::NotAQuarantine(run_dir.to_path_buf()));
+        };
+
+        self.restore_tree(run_dir, &origin, true);
+
+        // 全部放回后删除运行目录：只剩 .eptdir-origin 时才删除
+        let only_origin = fs::read_dir(run_dir)
+            .map(|entries| entries.filter_map(|entry| entry.ok()).all(|entry| entry.file_name() == ORIGIN_FILE))
+            .unwrap_or(false);
+        if only_origin {
+            fs::remove_file(&origin_file).ok();
+            fs::remove_dir(run_dir).ok();
+        }
+        Ok(())
+    }
+
+    // 把 dir 中的内容放回 original，处理完的空目录从隔离位置删除
+    fn restore_tree(&mut self, dir: &Path, original: &Path, top: bool) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => return self.fail(dir, Operation::ReadDir, e),
+        };
+        // 先收集再排序，保证恢复顺序稳定
+        let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let name = entry.file_name();
+            if top && name == ORIGIN_FILE {
+                continue;
+            }
+            let path = entry.path();
+            let dest = original.join(&name);
+            let wanted = self.wanted(&dest);
+            // 恢复范围的上级目录：本身不恢复，但要进入
+            let ancestor = self.only_under.as_ref().is_some_and(|dir| dir.starts_with(&dest));
+            if !wanted && !ancestor {
+                continue;
+            }
+
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            if is_dir {
+                // 目录：原位置不存在时重新创建（说明它是被清理掉的空目录）
+                if wanted && !dest.exists() {
+                    match fs::create_dir_all(&dest) {
+                        Ok(()) => self.report.restored.push(dest.clone()),
+                        Err(e) => {
+                            self.fail(&dest, Operation::Restore, e);
+                            continue;
+                        }
+                    }
+                }
+                self.restore_tree(&path, &dest, false);
+                // 里面的内容都放回去了，隔离位置的这个目录就是空的
+                fs::remove_dir(&path).ok();
+            } else if wanted {
+                self.restore_item(&path, &dest);
+            }
+        }
+    }
+
+    // 恢复回收站中由 eptdir 放进去的条目，其他程序删除的文件留在回收站中
+    fn restore_trash(&mut self, trash: &Path) -> Result<(), EptdirError> {
+        let info_dir = trash.join("info");
+        let entries = fs::read_dir(&info_dir).map_err(|e| EptdirError::io(&info_dir, Operation::ReadDir, e))?;
+
+        // (原始路径, files 中的条目, .trashinfo 文件)
+        let mut items: Vec<(PathBuf, PathBuf, PathBuf)> = Vec::new();
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let info = entry.path();
+            let Some(name) = info.file_name().and_then(|name| name.to_str()).and_then(|n| n.strip_suffix(".trashinfo"))
+            else {
+                continue;
+            };
+            let content = match fs::read_to_string(&info) {
+                Ok(content) => content,
+                Err(e) => {
+                    self.fail(&info, Operation::ReadFile, e);
+                    continue;
+                }
+            };
+            if !content.lines().any(|line| line.trim_end() == TRASH_MARKER) {
+                continue;
+            }
+            let Some(original) = content.lines().find_map(|line| line.strip_prefix("Path=")).and_then(decode_path)
+            else {
+                continue;
+            };
+            // 相对路径（分区上的 .Trash-uid 回收站）相对于回收站所在的分区根目录
+            let original = match trash.parent() {
+                Some(top) if original.is_relative() => top.join(original),
+                _ => original,
+            };
+            if self.wanted(&original) {
+                items.push((original, trash.join("files").join(name), info));
+            }
+        }
+
+        // 按原始路径排序：父目录先于其中的内容恢复
+        items.sort();
+        for (original, item, info) in items {
+            if self.restore_item(&item, &original) {
+                fs::remove_file(&info).ok();
+            }
+        }
+        Ok(())
+    }
+
+    // 把隔离位置中的一个条目放回原始位置，返回是否成功
+    fn restore_item(&mut self, item: &Path, original: &Path) -> bool {
+        if let Some(parent) = original.parent()
+            && let Err(e) = fs::create_dir_all(parent)
+        {
+            self.fail(parent, Operation::Restore, e);
+            return false;
+        }
+        // 原位置已经有同名目录，隔离的又是空目录：只需要把隔离的空目录删掉
+        if original.is_dir() && fs::remove_dir(item).is_ok() {
+            self.report.restored.push(original.to_path_buf());
+            return true;
+        }
+        match move_path(item, original) {
+            Ok(()) => {
+                self.report.restored.push(original.to_path_buf());
+                true
+            }
+            Err(e) => {
+                self.fail(original, Operation::Restore, e);
+                false
+            }
+        }
+    }
+}
+
+// ========================================
+// 路径编码和时间格式
+// ========================================
+
+// 按 .trashinfo 的要求对路径做百分号编码（按字节编码，不是 UTF-8 的文件名也能保存）
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for byte in path_bytes(path) {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+// encode_path 的逆操作，格式错误时返回 None
+fn decode_path(text: &str) -> Option<PathBuf> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = text.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Some(path_from_bytes(decoded))
+}
+
+// 把时间格式化成 年月日 + 时分秒（UTC）
+// date_sep / time_sep 是日期、时间内部的分隔符，middle 是日期和时间之间的分隔符
+// 例如 ("-", "T", ":") 得到 2026-10-16T09:30:00，("", "-", "") 得到 20261016-093000
+pub(crate) fn format_datetime(time: SystemTime, date_sep: &str, middle: &str, time_sep: &str) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let rem = secs % 86400;
+    format!(
+        "{:04}{ds}{:02}{ds}{:02}{mid}{:02}{ts}{:02}{ts}{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        ds = date_sep,
+        mid = middle,
+        ts = time_sep
+    )
+}
+
+// 把 1970-01-01 以来的天数转换成 (年, 月, 日)
+// 算法来自 Howard Hinnant 的 civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}